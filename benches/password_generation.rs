@@ -1,6 +1,11 @@
 use criterion::{Criterion, black_box, criterion_group, criterion_main};
-use rand::{SeedableRng, rngs::StdRng};
-use rpg_util::{GenerationParams, PasswordArgs, build_char_set, generate_passwords};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+#[cfg(feature = "rayon")]
+use rpg_util::generate_passwords_parallel;
+use rpg_util::{
+    GenerationParams, PasswordArgs, build_char_set, generate_passwords, generate_passwords_chunked,
+    generate_passwords_into,
+};
 
 fn bench_password_generation(c: &mut Criterion) {
     let args = PasswordArgs {
@@ -15,6 +20,7 @@ fn bench_password_generation(c: &mut Criterion) {
         pattern: None,
         length: 16,
         password_count: 1,
+        ..Default::default()
     };
 
     let char_set = build_char_set(&args).unwrap();
@@ -28,6 +34,7 @@ fn bench_password_generation(c: &mut Criterion) {
             min_numerals: None,
             min_symbols: None,
             pattern: None,
+            ..Default::default()
         };
         b.iter(|| generate_passwords(black_box(&char_set), black_box(&params), &mut rng))
     });
@@ -40,6 +47,7 @@ fn bench_password_generation(c: &mut Criterion) {
             min_numerals: None,
             min_symbols: None,
             pattern: None,
+            ..Default::default()
         };
         b.iter(|| generate_passwords(black_box(&char_set), black_box(&params), &mut rng))
     });
@@ -52,10 +60,138 @@ fn bench_password_generation(c: &mut Criterion) {
             min_numerals: None,
             min_symbols: None,
             pattern: None,
+            ..Default::default()
         };
         b.iter(|| generate_passwords(black_box(&char_set), black_box(&params), &mut rng))
     });
+
+    c.bench_function("generate_100000_passwords", |b| {
+        let params = GenerationParams {
+            length: 16,
+            count: 100_000,
+            min_capitals: Some(1),
+            min_numerals: Some(1),
+            min_symbols: Some(1),
+            pattern: None,
+            ..Default::default()
+        };
+        b.iter(|| generate_passwords(black_box(&char_set), black_box(&params), &mut rng))
+    });
+
+    c.bench_function("generate_100_passwords_into", |b| {
+        let params = GenerationParams {
+            length: 16,
+            count: 100,
+            min_capitals: None,
+            min_numerals: None,
+            min_symbols: None,
+            pattern: None,
+            ..Default::default()
+        };
+        let mut buffer = Vec::with_capacity(100);
+        b.iter(|| {
+            generate_passwords_into(
+                black_box(&char_set),
+                black_box(&params),
+                &mut rng,
+                &mut buffer,
+            )
+        })
+    });
+}
+
+/// Draws characters with replacement and rejects repeats, for comparison
+fn unique_by_rejection(char_set: &[u8], length: usize, rng: &mut StdRng) -> String {
+    let mut used = [false; 256];
+    let mut pass = String::with_capacity(length);
+    while pass.len() < length {
+        let c = char_set[rng.random_range(0..char_set.len())];
+        if !used[c as usize] {
+            used[c as usize] = true;
+            pass.push(c as char);
+        }
+    }
+    pass
+}
+
+fn bench_all_unique_chars(c: &mut Criterion) {
+    let char_set = build_char_set(&PasswordArgs::default()).unwrap();
+    // Near the full set, where rejection sampling keeps redrawing
+    let length = char_set.len() as u32 - 4;
+    let mut rng = StdRng::seed_from_u64(42);
+
+    c.bench_function("all_unique_chars_100_fisher_yates", |b| {
+        let params = GenerationParams {
+            length,
+            count: 100,
+            all_unique_chars: true,
+            ..Default::default()
+        };
+        b.iter(|| generate_passwords(black_box(&char_set), black_box(&params), &mut rng))
+    });
+
+    c.bench_function("all_unique_chars_100_rejection", |b| {
+        b.iter(|| {
+            (0..100)
+                .map(|_| unique_by_rejection(black_box(&char_set), length as usize, &mut rng))
+                .collect::<Vec<_>>()
+        })
+    });
+}
+
+fn bench_pin(c: &mut Criterion) {
+    let char_set: Vec<u8> = b"0123456789".to_vec();
+    let mut rng = StdRng::seed_from_u64(42);
+
+    c.bench_function("pin_6_single_class", |b| {
+        let params = GenerationParams {
+            length: 6,
+            count: 100,
+            bulk_bytes: true,
+            ..Default::default()
+        };
+        b.iter(|| generate_passwords(black_box(&char_set), black_box(&params), &mut rng))
+    });
+
+    // Without bulk_bytes the same set goes through the fill loop
+    c.bench_function("pin_6_fill_loop", |b| {
+        let params = GenerationParams {
+            length: 6,
+            count: 100,
+            ..Default::default()
+        };
+        b.iter(|| generate_passwords(black_box(&char_set), black_box(&params), &mut rng))
+    });
+}
+
+fn bench_chunked(c: &mut Criterion) {
+    let char_set = build_char_set(&PasswordArgs::default()).unwrap();
+    let params = GenerationParams {
+        length: 16,
+        count: 100_000,
+        ..Default::default()
+    };
+    let mut group = c.benchmark_group("chunked_100k");
+    group.sample_size(10);
+
+    group.bench_function("serial", |b| {
+        b.iter(|| generate_passwords_chunked(black_box(&char_set), black_box(&params), 42))
+    });
+
+    // The same chunks on every core: `cargo bench --features rayon` shows the speedup
+    #[cfg(feature = "rayon")]
+    group.bench_function("parallel", |b| {
+        b.iter(|| generate_passwords_parallel(black_box(&char_set), black_box(&params), 42))
+    });
+
+    group.finish();
 }
 
-criterion_group!(benches, bench_password_generation);
+criterion_group!(
+    benches,
+    bench_password_generation,
+    bench_all_unique_chars,
+    bench_pin,
+    bench_chunked
+);
 criterion_main!(benches);
@@ -0,0 +1,65 @@
+//! Generating passwords from an RNG of your own with `run_with_rng`
+//!
+//! Any `rand::RngCore` works. This reads bytes from a device file, such as a
+//! hardware RNG (`/dev/hwrng` on Linux) or by default `/dev/urandom`, then
+//! uses a seeded `StdRng` to show output that is the same on every run.
+//!
+//! ```bash
+//! cargo run --example custom_rng
+//! cargo run --example custom_rng -- /dev/hwrng
+//! ```
+
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use rpg_util::{PasswordArgs, run_with_rng};
+use std::fs::File;
+use std::io::Read;
+
+/// An RNG that reads its bytes from any `Read`, like a device file
+struct ReaderRng<T: Read>(T);
+
+impl<T: Read> RngCore for ReaderRng<T> {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        // RngCore can't report errors, and a short read would weaken every password
+        self.0
+            .read_exact(dest)
+            .expect("the random source stopped producing bytes");
+    }
+}
+
+fn main() {
+    let args = PasswordArgs {
+        length: 20,
+        password_count: 3,
+        ..Default::default()
+    };
+
+    let path = std::env::args().nth(1).unwrap_or("/dev/urandom".into());
+    match File::open(&path) {
+        Ok(device) => {
+            println!("From {}:", path);
+            for pass in run_with_rng(&args, &mut ReaderRng(device)).unwrap() {
+                println!("  {}", pass);
+            }
+        }
+        Err(e) => eprintln!("Could not open {}: {}", path, e),
+    }
+
+    // A seeded PRNG gives the same passwords on every run
+    println!("From StdRng seeded with 42:");
+    for pass in run_with_rng(&args, &mut StdRng::seed_from_u64(42)).unwrap() {
+        println!("  {}", pass);
+    }
+}
@@ -0,0 +1,2111 @@
+//! Password generation core
+//!
+//! Character set building, validation, and the pattern, mask and minimums
+//! generators. This module only needs `core`, `alloc` and `rand`, so it builds
+//! without the `std` feature for embedded and WASM targets. Anything that
+//! touches floating-point math, the terminal, or the filesystem lives in the
+//! crate root or the binary.
+
+use alloc::collections::BTreeSet;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use rand::Rng;
+use rand::distr::{Distribution, weighted::WeightedIndex};
+
+/// Custom error type for password generation
+#[derive(Debug, Clone)]
+pub enum PasswordError {
+    InvalidLength,
+    InvalidLengthTooLong {
+        max: u32,
+    },
+    InvalidMaxLength,
+    InvalidCount,
+    EmptyCharacterSet,
+    AllTypesDisabled,
+    RetryLimitExceeded {
+        constraint: &'static str,
+        attempts: u32,
+    },
+    KeyspaceTooSmall {
+        keyspace: u128,
+        count: u32,
+    },
+    MixedCaseUnavailable,
+    FirstCharAlphaUnavailable,
+    InvalidWeights,
+    ControlCharacter {
+        byte: u8,
+    },
+    SpaceNotAllowed,
+    TooManyRequiredTypes {
+        required: u32,
+        length: u32,
+    },
+    LengthExceedsCharacterSet {
+        length: u32,
+        available: usize,
+    },
+    InvalidSymbol {
+        ch: char,
+    },
+    MinimumAboveMaximum {
+        class: &'static str,
+        min: u32,
+        max: u32,
+    },
+    MaximumsBelowLength {
+        total: u32,
+        length: u32,
+    },
+    NonAsciiCharacter {
+        ch: char,
+    },
+    MinimumUnavailable {
+        class: &'static str,
+        min: u32,
+        /// Whether the set came from `include_chars` rather than the type flags
+        included: bool,
+    },
+}
+
+impl PasswordError {
+    /// Stable identifier for this error, for consumers that branch on failures
+    ///
+    /// Unlike the `Display` text, codes never change between releases.
+    ///
+    /// | Variant                     | Code                           | Exit status |
+    /// |-----------------------------|--------------------------------|-------------|
+    /// | `InvalidLength`             | `invalid_length`               | 10          |
+    /// | `InvalidLengthTooLong`      | `length_too_long`              | 11          |
+    /// | `InvalidMaxLength`          | `invalid_max_length`           | 12          |
+    /// | `InvalidCount`              | `invalid_count`                | 13          |
+    /// | `EmptyCharacterSet`         | `empty_character_set`          | 14          |
+    /// | `AllTypesDisabled`          | `all_types_disabled`           | 15          |
+    /// | `RetryLimitExceeded`        | `retry_limit_exceeded`         | 16          |
+    /// | `KeyspaceTooSmall`          | `keyspace_too_small`           | 17          |
+    /// | `MixedCaseUnavailable`      | `mixed_case_unavailable`       | 18          |
+    /// | `FirstCharAlphaUnavailable` | `first_char_alpha_unavailable` | 19          |
+    /// | `InvalidWeights`            | `invalid_weights`              | 20          |
+    /// | `ControlCharacter`          | `control_character`            | 21          |
+    /// | `SpaceNotAllowed`           | `space_not_allowed`            | 22          |
+    /// | `TooManyRequiredTypes`      | `too_many_required_types`      | 23          |
+    /// | `LengthExceedsCharacterSet` | `length_exceeds_character_set` | 24          |
+    /// | `InvalidSymbol`             | `invalid_symbol`               | 25          |
+    /// | `MinimumAboveMaximum`       | `minimum_above_maximum`        | 26          |
+    /// | `MaximumsBelowLength`       | `maximums_below_length`        | 27          |
+    /// | `NonAsciiCharacter`         | `non_ascii_character`          | 28          |
+    /// | `MinimumUnavailable`        | `minimum_unavailable`          | 29          |
+    pub fn code(&self) -> &'static str {
+        match self {
+            PasswordError::InvalidLength => "invalid_length",
+            PasswordError::InvalidLengthTooLong { .. } => "length_too_long",
+            PasswordError::InvalidMaxLength => "invalid_max_length",
+            PasswordError::InvalidCount => "invalid_count",
+            PasswordError::EmptyCharacterSet => "empty_character_set",
+            PasswordError::AllTypesDisabled => "all_types_disabled",
+            PasswordError::RetryLimitExceeded { .. } => "retry_limit_exceeded",
+            PasswordError::KeyspaceTooSmall { .. } => "keyspace_too_small",
+            PasswordError::MixedCaseUnavailable => "mixed_case_unavailable",
+            PasswordError::FirstCharAlphaUnavailable => "first_char_alpha_unavailable",
+            PasswordError::InvalidWeights => "invalid_weights",
+            PasswordError::ControlCharacter { .. } => "control_character",
+            PasswordError::SpaceNotAllowed => "space_not_allowed",
+            PasswordError::TooManyRequiredTypes { .. } => "too_many_required_types",
+            PasswordError::LengthExceedsCharacterSet { .. } => "length_exceeds_character_set",
+            PasswordError::InvalidSymbol { .. } => "invalid_symbol",
+            PasswordError::MinimumAboveMaximum { .. } => "minimum_above_maximum",
+            PasswordError::MaximumsBelowLength { .. } => "maximums_below_length",
+            PasswordError::NonAsciiCharacter { .. } => "non_ascii_character",
+            PasswordError::MinimumUnavailable { .. } => "minimum_unavailable",
+        }
+    }
+
+    /// Process exit status the CLI uses for this error (see `code` for the table)
+    ///
+    /// Statuses start at 10 so they don't collide with 1 (any other error) or
+    /// 2 (invalid command-line usage).
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            PasswordError::InvalidLength => 10,
+            PasswordError::InvalidLengthTooLong { .. } => 11,
+            PasswordError::InvalidMaxLength => 12,
+            PasswordError::InvalidCount => 13,
+            PasswordError::EmptyCharacterSet => 14,
+            PasswordError::AllTypesDisabled => 15,
+            PasswordError::RetryLimitExceeded { .. } => 16,
+            PasswordError::KeyspaceTooSmall { .. } => 17,
+            PasswordError::MixedCaseUnavailable => 18,
+            PasswordError::FirstCharAlphaUnavailable => 19,
+            PasswordError::InvalidWeights => 20,
+            PasswordError::ControlCharacter { .. } => 21,
+            PasswordError::SpaceNotAllowed => 22,
+            PasswordError::TooManyRequiredTypes { .. } => 23,
+            PasswordError::LengthExceedsCharacterSet { .. } => 24,
+            PasswordError::InvalidSymbol { .. } => 25,
+            PasswordError::MinimumAboveMaximum { .. } => 26,
+            PasswordError::MaximumsBelowLength { .. } => 27,
+            PasswordError::NonAsciiCharacter { .. } => 28,
+            PasswordError::MinimumUnavailable { .. } => 29,
+        }
+    }
+}
+
+impl fmt::Display for PasswordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PasswordError::InvalidLength => {
+                write!(f, "Error: Password length must be greater than 0.")
+            }
+            PasswordError::InvalidLengthTooLong { max } => {
+                write!(
+                    f,
+                    "Error: Password length exceeds maximum of {} characters.",
+                    format_thousands(*max)
+                )
+            }
+            PasswordError::InvalidMaxLength => {
+                write!(
+                    f,
+                    "Error: Maximum length must be between 1 and {} characters.",
+                    format_thousands(ABSOLUTE_MAX_PASSWORD_LENGTH)
+                )
+            }
+            PasswordError::InvalidCount => {
+                write!(
+                    f,
+                    "Error: Password count must be greater than 0.\n\
+                    Hint: Use --stream to keep generating until the output is closed."
+                )
+            }
+            PasswordError::EmptyCharacterSet => {
+                write!(
+                    f,
+                    "Error: All characters have been excluded or disabled. Cannot generate passwords.\n\
+                    Hint: Try removing some character exclusions or enabling character types."
+                )
+            }
+            PasswordError::AllTypesDisabled => {
+                write!(
+                    f,
+                    "Error: All character types are disabled and/or all remaining characters are excluded.\n\
+                    Hint: At least one character type must be enabled. Try removing --lowercase-off, --capitals-off, --numerals-off, or --symbols-off."
+                )
+            }
+            PasswordError::RetryLimitExceeded {
+                constraint,
+                attempts,
+            } => {
+                write!(
+                    f,
+                    "Error: Could not satisfy the {} constraint after {} attempts.\n\
+                    Hint: The constraint may be too tight for the character set and length. Try loosening it, or raise --retry-limit.",
+                    constraint, attempts
+                )
+            }
+            PasswordError::KeyspaceTooSmall { keyspace, count } => {
+                write!(
+                    f,
+                    "Error: Only {} distinct passwords are possible, but {} unique passwords were requested.\n\
+                    Hint: Increase the length or widen the character set.",
+                    keyspace, count
+                )
+            }
+            PasswordError::MixedCaseUnavailable => {
+                write!(
+                    f,
+                    "Error: Mixed case is required, but the character set lacks lowercase or uppercase letters.\n\
+                    Hint: Remove --capitals-off or any exclusions of a-z / A-Z."
+                )
+            }
+            PasswordError::FirstCharAlphaUnavailable => {
+                write!(
+                    f,
+                    "Error: Passwords must start with a letter, but no letter is available for the first position.\n\
+                    Hint: Enable letters, or start the pattern with L/U or the mask with a letter set like [a-zA-Z]."
+                )
+            }
+            PasswordError::InvalidWeights => {
+                write!(
+                    f,
+                    "Error: Every character class with a non-zero weight is missing from the character set.\n\
+                    Hint: Give a positive weight to at least one enabled class (letters, digits, or symbols)."
+                )
+            }
+            PasswordError::ControlCharacter { byte } => {
+                write!(
+                    f,
+                    "Error: The included characters contain the control character 0x{:02X}.\n\
+                    Hint: Passwords may only use printable characters. Remove it from --include-chars.",
+                    byte
+                )
+            }
+            PasswordError::SpaceNotAllowed => {
+                write!(
+                    f,
+                    "Error: The included characters contain a space.\n\
+                    Hint: Pass --allow-space if you really want spaces in passwords."
+                )
+            }
+            PasswordError::TooManyRequiredTypes { required, length } => {
+                write!(
+                    f,
+                    "Error: --ensure-all-types needs at least {} characters (one per character type, plus any minimums), but the length is {}.\n\
+                    Hint: Increase --length, lower the minimums, or disable some character types.",
+                    required, length
+                )
+            }
+            PasswordError::LengthExceedsCharacterSet { length, available } => {
+                write!(
+                    f,
+                    "Error: --all-unique-chars needs {} different characters, but the character set only has {}.\n\
+                    Hint: Use a shorter --length or allow more characters.",
+                    length, available
+                )
+            }
+            PasswordError::InvalidSymbol { ch } => {
+                write!(
+                    f,
+                    "Error: {:?} is not a symbol. Symbols must be printable ASCII characters other than letters, digits and space.\n\
+                    Hint: Remove it from --symbols.",
+                    ch
+                )
+            }
+            PasswordError::MinimumAboveMaximum { class, min, max } => {
+                write!(
+                    f,
+                    "Error: At least {} {} are required, but at most {} are allowed.\n\
+                    Hint: Lower --min-{} or raise --max-{}.",
+                    min, class, max, class, class
+                )
+            }
+            PasswordError::MaximumsBelowLength { total, length } => {
+                write!(
+                    f,
+                    "Error: The maximums allow at most {} characters, but the length is {}.\n\
+                    Hint: Raise a maximum, enable lowercase letters, or use a shorter --length.",
+                    total, length
+                )
+            }
+            PasswordError::NonAsciiCharacter { ch } => {
+                write!(
+                    f,
+                    "Error: {:?} (U+{:04X}) is not an ASCII character. Passwords are built from printable ASCII only.\n\
+                    Hint: Remove it from the characters you asked to include.",
+                    ch, *ch as u32
+                )
+            }
+            PasswordError::MinimumUnavailable {
+                class,
+                min,
+                included: true,
+            } => {
+                write!(
+                    f,
+                    "Error: --min-{} {} needs {}, but the included characters have none.\n\
+                    Hint: Add some {} to --include-chars, or drop --min-{}.",
+                    class, min, class, class, class
+                )
+            }
+            PasswordError::MinimumUnavailable {
+                class,
+                min,
+                included: false,
+            } => {
+                write!(
+                    f,
+                    "Error: --min-{} {} needs {}, but the character set has none.\n\
+                    Hint: Enable {} or exclude fewer characters, or drop --min-{}.",
+                    class, min, class, class, class
+                )
+            }
+        }
+    }
+}
+
+impl core::error::Error for PasswordError {}
+
+/// Formats a number with comma thousands separators (e.g. 10000 -> "10,000")
+fn format_thousands(n: u32) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+// ASCII character range constants
+const ASCII_LOWERCASE_START: u8 = b'a';
+const ASCII_LOWERCASE_END: u8 = b'z';
+const ASCII_UPPERCASE_START: u8 = b'A';
+const ASCII_UPPERCASE_END: u8 = b'Z';
+const ASCII_NUMERAL_START: u8 = b'0';
+const ASCII_NUMERAL_END: u8 = b'9';
+const ASCII_SYMBOL_RANGE_1_START: u8 = 33; // !
+const ASCII_SYMBOL_RANGE_1_END: u8 = 47; // /
+const ASCII_SYMBOL_RANGE_2_START: u8 = 58; // :
+const ASCII_SYMBOL_RANGE_2_END: u8 = 64; // @
+const ASCII_SYMBOL_RANGE_3_START: u8 = 91; // [
+const ASCII_SYMBOL_RANGE_3_END: u8 = 96; // `
+const ASCII_SYMBOL_RANGE_4_START: u8 = 123; // {
+const ASCII_SYMBOL_RANGE_4_END: u8 = 126; // ~
+
+/// Symbols accepted by nearly every password policy, used with `safe_symbols`
+pub const SAFE_SYMBOLS: &str = "!@#$%^&*";
+
+/// Version of the character ordering `build_char_set` produces
+///
+/// A seeded draw picks a character by its index in the set, so reordering or
+/// changing the built-in ranges changes seeded output even when the set of
+/// characters stays the same. Such a change must bump this number, which JSON
+/// output reports so callers can tell the two orderings apart.
+pub const fn charset_version() -> u32 {
+    1
+}
+
+/// Pattern character types
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(
+    feature = "std",
+    derive(serde::Serialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum PatternChar {
+    Lowercase,
+    Uppercase,
+    Numeric,
+    Symbol,
+}
+
+/// Parameters for password generation
+#[derive(Debug, Clone, Default)]
+pub struct GenerationParams {
+    pub length: u32,
+    pub count: u32,
+    pub min_capitals: Option<u32>,
+    pub min_numerals: Option<u32>,
+    pub min_symbols: Option<u32>,
+    /// Caps on the capitals, numerals and symbols in each password (ignored
+    /// for patterns and masks)
+    ///
+    /// Characters over a cap are redrawn from the classes still under theirs.
+    pub max_capitals: Option<u32>,
+    pub max_numerals: Option<u32>,
+    pub max_symbols: Option<u32>,
+    pub pattern: Option<Vec<PatternChar>>,
+    /// Re-roll any password whose `similarity` to this string exceeds `MAX_SIMILARITY`
+    pub exclude_similar_to: Option<String>,
+    /// Re-roll any password that exactly matches one of these (e.g. previously issued passwords)
+    pub forbidden_passwords: BTreeSet<String>,
+    /// Re-roll passwords that already appeared earlier in the same batch
+    pub unique: bool,
+    /// Re-roll passwords that lack either a lowercase or an uppercase letter
+    pub require_mixed_case: bool,
+    /// One character pool per position (see `parse_mask`); takes precedence over `pattern`
+    pub mask: Option<Vec<Vec<u8>>>,
+    /// Force the first character to be a letter (validated, not enforced, for patterns and masks)
+    pub first_char_alpha: bool,
+    /// Bias the fill characters towards some classes (ignored for patterns and masks)
+    pub weights: Option<ClassWeights>,
+    /// Require at least one character of every class in the character set
+    /// (ignored for patterns and masks)
+    ///
+    /// Uppercase, numeral and symbol minimums below 1 are raised to 1, and
+    /// passwords without a lowercase letter are re-rolled.
+    pub ensure_all_types: bool,
+    /// Use every character at most once per password, sampling without
+    /// replacement; takes precedence over minimums and weights (ignored for
+    /// patterns and masks)
+    pub all_unique_chars: bool,
+    /// Re-roll passwords with a run of more than this many identical
+    /// characters in a row, so `Some(2)` allows `aa` but not `aaa`
+    pub max_repeats: Option<u32>,
+    /// Attempts per password before a re-roll constraint gives up with
+    /// `RetryLimitExceeded`; `None` uses `MAX_REROLL_ATTEMPTS`, scaled up
+    /// for `unique` as the batch uses up the keyspace
+    pub retry_limit: Option<u32>,
+    /// Let single-class sets (PINs, hex digits, ...) draw bulk random bytes
+    /// instead of one `pick` per character
+    ///
+    /// Faster, but the output for the same RNG state differs from the
+    /// default, so leave it off wherever seeded output must stay the same.
+    pub bulk_bytes: bool,
+    /// Re-roll passwords this regex doesn't match; it may match anywhere in
+    /// the password unless anchored with `^` and `$`
+    #[cfg(feature = "regex")]
+    pub must_match: Option<regex::Regex>,
+}
+
+/// Relative sampling weights for the characters that fill a password
+///
+/// A class with weight 0, or with no characters left in the set, is never
+/// drawn by the fill loop. Required minimums are placed before the fill and
+/// are unaffected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClassWeights {
+    pub letters: u32,
+    pub digits: u32,
+    pub symbols: u32,
+}
+
+/// Default number of attempts per password before a constraint is reported as unsatisfiable
+pub const MAX_REROLL_ATTEMPTS: u32 = 1_000;
+
+/// Similarity above which a password is considered to resemble `exclude_similar_to`
+pub const MAX_SIMILARITY: f64 = 0.5;
+
+/// Arguments structure for password generation
+///
+/// With the `std` feature it serializes to JSON with one key per field, and
+/// `Display` prints the settings that differ from the defaults, one per line.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "std", derive(serde::Serialize))]
+pub struct PasswordArgs {
+    pub lowercase_off: bool,
+    pub capitals_off: bool,
+    pub numerals_off: bool,
+    pub symbols_off: bool,
+    pub exclude_chars: Vec<char>,
+    pub include_chars: Option<Vec<char>>,
+    pub min_capitals: Option<u32>,
+    pub min_numerals: Option<u32>,
+    pub min_symbols: Option<u32>,
+    pub max_capitals: Option<u32>,
+    pub max_numerals: Option<u32>,
+    pub max_symbols: Option<u32>,
+    pub pattern: Option<Vec<PatternChar>>,
+    pub length: u32,
+    pub password_count: u32,
+    /// Overrides `MAX_PASSWORD_LENGTH`; may not exceed `ABSOLUTE_MAX_PASSWORD_LENGTH`
+    pub max_length: Option<u32>,
+    /// Permit a space in `include_chars`, which is otherwise rejected
+    pub allow_space: bool,
+    /// Draw symbols from `SAFE_SYMBOLS` only, instead of every ASCII symbol
+    pub safe_symbols: bool,
+    /// Use exactly these symbols instead of the ASCII symbol ranges
+    ///
+    /// Each must be printable ASCII punctuation; takes precedence over
+    /// `safe_symbols`.
+    pub symbols: Option<Vec<char>>,
+    /// Longest allowed run of one repeated character (see `GenerationParams::max_repeats`)
+    pub max_repeats: Option<u32>,
+    /// Characters added back after `exclude_chars` is applied
+    ///
+    /// Lets a caller exclude a whole range and keep a few of it: excluding
+    /// `a-z` and adding back `m` leaves `m` as the only lowercase letter.
+    /// Each must be printable ASCII (a space needs `allow_space`).
+    pub include_back: Vec<char>,
+    /// Also exclude the other case of every letter in `exclude_chars`
+    pub exclude_case_insensitive: bool,
+}
+
+impl fmt::Display for PasswordArgs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let on_off = |off: bool| if off { "off" } else { "on" };
+        let quoted = |chars: &[char]| format!("{:?}", chars.iter().collect::<String>());
+
+        writeln!(f, "length: {}", self.length)?;
+        writeln!(f, "count: {}", self.password_count)?;
+        match self.include_chars {
+            Some(ref include) => writeln!(f, "include: {}", quoted(include))?,
+            None => {
+                writeln!(f, "lowercase: {}", on_off(self.lowercase_off))?;
+                writeln!(f, "capitals: {}", on_off(self.capitals_off))?;
+                writeln!(f, "numerals: {}", on_off(self.numerals_off))?;
+                match self.symbols {
+                    Some(ref symbols) if !self.symbols_off => {
+                        writeln!(f, "symbols: {}", quoted(symbols))?
+                    }
+                    _ if self.safe_symbols && !self.symbols_off => {
+                        writeln!(f, "symbols: {:?}", SAFE_SYMBOLS)?
+                    }
+                    _ => writeln!(f, "symbols: {}", on_off(self.symbols_off))?,
+                }
+            }
+        }
+        if !self.exclude_chars.is_empty() {
+            writeln!(f, "exclude: {}", quoted(&self.exclude_chars))?;
+        }
+        if self.exclude_case_insensitive {
+            writeln!(f, "exclude either case: yes")?;
+        }
+        if !self.include_back.is_empty() {
+            writeln!(f, "include back: {}", quoted(&self.include_back))?;
+        }
+        let limits = [
+            ("min capitals", self.min_capitals),
+            ("min numerals", self.min_numerals),
+            ("min symbols", self.min_symbols),
+            ("max capitals", self.max_capitals),
+            ("max numerals", self.max_numerals),
+            ("max symbols", self.max_symbols),
+            ("max repeats", self.max_repeats),
+            ("max length", self.max_length),
+        ];
+        for (name, value) in limits {
+            if let Some(value) = value {
+                writeln!(f, "{}: {}", name, value)?;
+            }
+        }
+        if let Some(ref pattern) = self.pattern {
+            let letters: String = pattern
+                .iter()
+                .map(|p| match p {
+                    PatternChar::Lowercase => 'L',
+                    PatternChar::Uppercase => 'U',
+                    PatternChar::Numeric => 'N',
+                    PatternChar::Symbol => 'S',
+                })
+                .collect();
+            writeln!(f, "pattern: {}", letters)?;
+        }
+        if self.allow_space {
+            writeln!(f, "allow space: yes")?;
+        }
+        Ok(())
+    }
+}
+
+/// Converts a character the caller asked to include into its set byte
+///
+/// Only printable ASCII fits in a byte unchanged; anything above U+007F is an
+/// error rather than a truncated code point (U+0100 would otherwise become NUL).
+fn include_byte(c: char, allow_space: bool) -> Result<u8, PasswordError> {
+    let byte = u8::try_from(c)
+        .ok()
+        .filter(u8::is_ascii)
+        .ok_or(PasswordError::NonAsciiCharacter { ch: c })?;
+    if byte.is_ascii_control() {
+        return Err(PasswordError::ControlCharacter { byte });
+    }
+    if byte == b' ' && !allow_space {
+        return Err(PasswordError::SpaceNotAllowed);
+    }
+    Ok(byte)
+}
+
+/// The characters `exclude_chars` removes, as a set for fast lookup
+///
+/// With `exclude_case_insensitive`, each letter brings its other case along;
+/// digits and symbols are left as they are.
+fn exclusions(args: &PasswordArgs) -> BTreeSet<char> {
+    let mut set = BTreeSet::new();
+    for &c in &args.exclude_chars {
+        set.insert(c);
+        if args.exclude_case_insensitive && c.is_alphabetic() {
+            set.extend(c.to_lowercase());
+            set.extend(c.to_uppercase());
+        }
+    }
+    set
+}
+
+/// Builds the character set based on command-line arguments
+/// Returns a vector of valid characters that can be used for password generation
+///
+/// Non-ASCII and control characters (below 32, and 127) in `include_chars`
+/// are always rejected, and a space is rejected unless `allow_space` is set.
+pub fn build_char_set(args: &PasswordArgs) -> Result<Vec<u8>, PasswordError> {
+    let mut chars = Vec::new();
+
+    // If include_chars is specified, use only those characters
+    if let Some(ref include_chars) = args.include_chars {
+        // Duplicates would weight those characters and overstate the entropy
+        for &c in include_chars {
+            let byte = include_byte(c, args.allow_space)?;
+            if !chars.contains(&byte) {
+                chars.push(byte);
+            }
+        }
+    } else {
+        // Pre-allocate with estimated capacity (max ~94 printable ASCII chars)
+        let estimated_capacity = if args.symbols_off {
+            62 // 26 lowercase + 26 uppercase + 10 numerals
+        } else {
+            94 // All printable ASCII
+        };
+        chars.reserve(estimated_capacity);
+
+        // Add lowercase letters if not disabled
+        if !args.lowercase_off {
+            chars.extend(ASCII_LOWERCASE_START..=ASCII_LOWERCASE_END);
+        }
+
+        // Add uppercase letters if not disabled
+        if !args.capitals_off {
+            chars.extend(ASCII_UPPERCASE_START..=ASCII_UPPERCASE_END);
+        }
+
+        // Add numerals if not disabled
+        if !args.numerals_off {
+            chars.extend(ASCII_NUMERAL_START..=ASCII_NUMERAL_END);
+        }
+
+        // Add symbols if not disabled: the safe subset, or the complete
+        // ASCII printable symbol ranges
+        if let (false, Some(symbols)) = (args.symbols_off, &args.symbols) {
+            for &c in symbols {
+                if !c.is_ascii_punctuation() {
+                    return Err(PasswordError::InvalidSymbol { ch: c });
+                }
+                if !chars.contains(&(c as u8)) {
+                    chars.push(c as u8);
+                }
+            }
+        } else if !args.symbols_off && args.safe_symbols {
+            chars.extend(SAFE_SYMBOLS.bytes());
+        } else if !args.symbols_off {
+            chars.extend(ASCII_SYMBOL_RANGE_1_START..=ASCII_SYMBOL_RANGE_1_END);
+            chars.extend(ASCII_SYMBOL_RANGE_2_START..=ASCII_SYMBOL_RANGE_2_END);
+            chars.extend(ASCII_SYMBOL_RANGE_3_START..=ASCII_SYMBOL_RANGE_3_END);
+            chars.extend(ASCII_SYMBOL_RANGE_4_START..=ASCII_SYMBOL_RANGE_4_END);
+        }
+    }
+
+    let exclude_set = exclusions(args);
+
+    // Filter out excluded characters
+    chars.retain(|&b| !exclude_set.contains(&(b as char)));
+
+    // Then put back the ones the caller still wants
+    for &c in &args.include_back {
+        let byte = include_byte(c, args.allow_space)?;
+        if !chars.contains(&byte) {
+            chars.push(byte);
+        }
+    }
+
+    // Validate that we have at least one character available
+    if chars.is_empty() {
+        return Err(PasswordError::EmptyCharacterSet);
+    }
+
+    Ok(chars)
+}
+
+/// Alphabet the letters of a character set are drawn from
+///
+/// Digits and symbols stay ASCII whatever the script.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Script {
+    #[default]
+    Latin,
+    Greek,
+    Cyrillic,
+}
+
+impl Script {
+    /// Parses a script name as accepted by `--script`
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "latin" => Some(Script::Latin),
+            "greek" => Some(Script::Greek),
+            "cyrillic" => Some(Script::Cyrillic),
+            _ => None,
+        }
+    }
+
+    /// Lowercase letters of the script's basic alphabet
+    pub fn lowercase(self) -> Vec<char> {
+        match self {
+            Script::Latin => ('a'..='z').collect(),
+            // Final sigma is left out; it only appears at the end of a word
+            Script::Greek => ('α'..='ω').filter(|&c| c != 'ς').collect(),
+            Script::Cyrillic => ('а'..='я').collect(),
+        }
+    }
+
+    /// Uppercase letters of the script's basic alphabet
+    pub fn uppercase(self) -> Vec<char> {
+        match self {
+            Script::Latin => ('A'..='Z').collect(),
+            // U+03A2 is unassigned
+            Script::Greek => ('Α'..='Ω').filter(|&c| c != '\u{3a2}').collect(),
+            Script::Cyrillic => ('А'..='Я').collect(),
+        }
+    }
+}
+
+/// Builds the character set with letters from `script`
+///
+/// Same as `build_char_set` except that the lowercase and uppercase letters
+/// come from `script`. `include_chars` is used as given, whatever the script.
+pub fn build_script_char_set(
+    args: &PasswordArgs,
+    script: Script,
+) -> Result<Vec<char>, PasswordError> {
+    if script == Script::Latin || args.include_chars.is_some() {
+        return Ok(build_char_set(args)?.into_iter().map(char::from).collect());
+    }
+
+    let mut chars = Vec::new();
+    if !args.lowercase_off {
+        chars.extend(script.lowercase());
+    }
+    if !args.capitals_off {
+        chars.extend(script.uppercase());
+    }
+    // Digits and symbols come from the ASCII set, exclusions applied below
+    let ascii = build_char_set(&PasswordArgs {
+        exclude_chars: Vec::new(),
+        ..args.clone()
+    })?;
+    chars.extend(
+        ascii
+            .into_iter()
+            .filter(|b| !b.is_ascii_alphabetic())
+            .map(char::from),
+    );
+
+    let exclude_set = exclusions(args);
+    chars.retain(|c| !exclude_set.contains(c));
+    for &c in &args.include_back {
+        let c = char::from(include_byte(c, args.allow_space)?);
+        if !chars.contains(&c) {
+            chars.push(c);
+        }
+    }
+    if chars.is_empty() {
+        return Err(PasswordError::EmptyCharacterSet);
+    }
+    Ok(chars)
+}
+
+/// Default maximum password length, used unless `max_length` overrides it
+pub const MAX_PASSWORD_LENGTH: u32 = 10_000;
+
+/// Hard ceiling for `max_length` overrides to prevent memory issues
+pub const ABSOLUTE_MAX_PASSWORD_LENGTH: u32 = 1_000_000;
+
+/// Validates command-line arguments
+pub fn validate_args(args: &PasswordArgs) -> Result<(), PasswordError> {
+    if args.length == 0 {
+        return Err(PasswordError::InvalidLength);
+    }
+
+    let max_length = args.max_length.unwrap_or(MAX_PASSWORD_LENGTH);
+    if max_length == 0 || max_length > ABSOLUTE_MAX_PASSWORD_LENGTH {
+        return Err(PasswordError::InvalidMaxLength);
+    }
+
+    if args.length > max_length {
+        return Err(PasswordError::InvalidLengthTooLong { max: max_length });
+    }
+
+    if args.password_count == 0 {
+        return Err(PasswordError::InvalidCount);
+    }
+
+    check_limits(
+        [args.min_capitals, args.min_numerals, args.min_symbols],
+        [args.max_capitals, args.max_numerals, args.max_symbols],
+    )?;
+    check_minimums_available(args)?;
+
+    // Check if all character types are disabled (included characters
+    // override the type flags)
+    if args.lowercase_off
+        && args.capitals_off
+        && args.numerals_off
+        && args.symbols_off
+        && args.include_chars.is_none()
+    {
+        return Err(PasswordError::AllTypesDisabled);
+    }
+    if args.capitals_off && args.numerals_off && args.symbols_off {
+        // Only lowercase letters remain, which is valid
+        // But we should check if they're all excluded
+        let test_set = build_char_set(args)?;
+        if test_set.is_empty() {
+            return Err(PasswordError::AllTypesDisabled);
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that every class with a minimum has characters in the set
+///
+/// The generator places nothing for a class the set lacks, so without this
+/// `--include-chars ab --min-symbols 2` would quietly produce no symbols.
+/// Patterns ignore the minimums, so they are exempt.
+fn check_minimums_available(args: &PasswordArgs) -> Result<(), PasswordError> {
+    let mins = [args.min_capitals, args.min_numerals, args.min_symbols];
+    if args.pattern.is_some() || mins.iter().all(|min| min.unwrap_or(0) == 0) {
+        return Ok(());
+    }
+    let char_set = build_char_set(args)?;
+    let classes = CharClasses::from_char_set(&char_set);
+    let pools = [&classes.uppercase, &classes.numeric, &classes.symbols];
+    for ((min, pool), class) in mins
+        .into_iter()
+        .zip(pools)
+        .zip(["capitals", "numerals", "symbols"])
+    {
+        if let Some(min) = min
+            && min > 0
+            && pool.is_empty()
+        {
+            return Err(PasswordError::MinimumUnavailable {
+                class,
+                min,
+                included: args.include_chars.is_some(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Checks that no minimum exceeds its maximum, for capitals, numerals and symbols
+fn check_limits(mins: [Option<u32>; 3], maxes: [Option<u32>; 3]) -> Result<(), PasswordError> {
+    for ((min, max), class) in mins
+        .into_iter()
+        .zip(maxes)
+        .zip(["capitals", "numerals", "symbols"])
+    {
+        if let (Some(min), Some(max)) = (min, max)
+            && min > max
+        {
+            return Err(PasswordError::MinimumAboveMaximum { class, min, max });
+        }
+    }
+    Ok(())
+}
+
+/// Computes the Levenshtein edit distance between two strings, by character
+fn levenshtein(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    // Two-row dynamic programming table
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+
+    for (i, &ca) in a.iter().enumerate() {
+        curr[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let cost = if ca == cb { 0 } else { 1 };
+            curr[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        core::mem::swap(&mut prev, &mut curr);
+    }
+
+    prev[b.len()]
+}
+
+/// Returns how similar two strings are, from 0.0 (nothing in common) to 1.0 (identical)
+///
+/// Computed as one minus the Levenshtein distance normalized by the longer
+/// string's length. Two empty strings are identical.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let max_len = a.chars().count().max(b.chars().count());
+    if max_len == 0 {
+        return 1.0;
+    }
+    1.0 - levenshtein(a, b) as f64 / max_len as f64
+}
+
+/// Parses a pattern string like "LLLNNNSSS" into PatternChar vector
+pub fn parse_pattern(pattern: &str) -> Result<Vec<PatternChar>, String> {
+    let mut result = Vec::new();
+    for c in pattern.chars() {
+        match c {
+            'L' | 'l' => result.push(PatternChar::Lowercase),
+            'U' | 'u' => result.push(PatternChar::Uppercase),
+            'N' | 'n' => result.push(PatternChar::Numeric),
+            'S' | 's' => result.push(PatternChar::Symbol),
+            _ => {
+                return Err(format!(
+                    "Invalid pattern character: '{}'. Use L (lowercase), U (uppercase), N (numeric), S (symbol)",
+                    c
+                ));
+            }
+        }
+    }
+    // Checked on the parsed result so it still holds if counts like `L{0}` are added
+    if result.is_empty() {
+        return Err(
+            "Pattern is empty; it needs at least one of L, U, N or S, one per character"
+                .to_string(),
+        );
+    }
+    Ok(result)
+}
+
+/// Parses a mask like `"[ab][0-9]{4}[!@#]"` into one character pool per position
+///
+/// Mask syntax:
+/// - `[...]` is a set of characters, with ranges like `a-z`; write `\]`,
+///   `\-` or `\\` for a literal `]`, `-` or `\` inside a set
+/// - any other printable ASCII character stands for itself (escape `[`, `]`,
+///   `{`, `}` and `\` with a backslash)
+/// - `{n}` repeats the preceding position `n` times in total
+///
+/// A mask longer than `ABSOLUTE_MAX_PASSWORD_LENGTH` positions is rejected
+/// while parsing, before any repetition is expanded; the usual length limit
+/// (see `validate_args`) applies afterwards. Pools are sorted and deduplicated. Masks are a richer alternative to
+/// patterns: a pattern names one of four classes per position, while a mask
+/// spells out the characters.
+pub fn parse_mask(mask: &str) -> Result<Vec<Vec<u8>>, String> {
+    let mut positions: Vec<Vec<u8>> = Vec::new();
+    let mut chars = mask.chars();
+
+    // Reads one printable ASCII character, resolving a backslash escape
+    fn literal(c: char, chars: &mut core::str::Chars) -> Result<u8, String> {
+        let c = if c == '\\' {
+            chars
+                .next()
+                .ok_or_else(|| "Mask ends with a dangling '\\'".to_string())?
+        } else {
+            c
+        };
+        if c.is_ascii_graphic() || c == ' ' {
+            Ok(c as u8)
+        } else {
+            Err(format!("Invalid mask character: '{}'", c))
+        }
+    }
+
+    let too_long = || {
+        format!(
+            "Mask is longer than the maximum of {} characters",
+            format_thousands(ABSOLUTE_MAX_PASSWORD_LENGTH)
+        )
+    };
+
+    while let Some(c) = chars.next() {
+        match c {
+            '[' => {
+                let mut pool = Vec::new();
+                let mut prev: Option<u8> = None;
+                loop {
+                    let c = chars
+                        .next()
+                        .ok_or_else(|| "Unclosed '[' in mask".to_string())?;
+                    match c {
+                        ']' => break,
+                        '-' if prev.is_some() && !chars.as_str().starts_with(']') => {
+                            let start = prev.take().unwrap_or_default();
+                            let next = chars
+                                .next()
+                                .ok_or_else(|| "Unclosed '[' in mask".to_string())?;
+                            let end = literal(next, &mut chars)?;
+                            if start > end {
+                                return Err(format!(
+                                    "Invalid mask range: '{}-{}'",
+                                    start as char, end as char
+                                ));
+                            }
+                            pool.extend(start..=end);
+                        }
+                        _ => {
+                            let b = literal(c, &mut chars)?;
+                            pool.push(b);
+                            prev = Some(b);
+                        }
+                    }
+                }
+                if pool.is_empty() {
+                    return Err("Empty '[]' in mask".to_string());
+                }
+                pool.sort_unstable();
+                pool.dedup();
+                positions.push(pool);
+            }
+            '{' => {
+                let mut digits = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(d) if d.is_ascii_digit() => digits.push(d),
+                        _ => return Err("Invalid '{n}' repetition in mask".to_string()),
+                    }
+                }
+                let n: usize = digits
+                    .parse()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| format!("Invalid repetition count: '{{{}}}'", digits))?;
+                let last = positions
+                    .last()
+                    .cloned()
+                    .ok_or_else(|| "'{n}' must follow a mask position".to_string())?;
+                if positions.len().saturating_add(n - 1) > ABSOLUTE_MAX_PASSWORD_LENGTH as usize {
+                    return Err(too_long());
+                }
+                positions.extend(core::iter::repeat_n(last, n - 1));
+            }
+            ']' | '}' => return Err(format!("Unmatched '{}' in mask", c)),
+            _ => positions.push(vec![literal(c, &mut chars)?]),
+        }
+        if positions.len() > ABSOLUTE_MAX_PASSWORD_LENGTH as usize {
+            return Err(too_long());
+        }
+    }
+
+    Ok(positions)
+}
+
+/// Removes characters that aren't in `char_set` from each mask position
+///
+/// This applies exclusions and disabled character types to a parsed mask.
+/// Returns an error naming the first position left with no characters.
+pub fn restrict_mask<C: CharUnit>(
+    mask: Vec<Vec<u8>>,
+    char_set: &[C],
+) -> Result<Vec<Vec<u8>>, String> {
+    mask.into_iter()
+        .enumerate()
+        .map(|(i, pool)| {
+            let pool: Vec<u8> = pool
+                .into_iter()
+                .filter(|&b| char_set.contains(&C::from(b)))
+                .collect();
+            if pool.is_empty() {
+                Err(format!(
+                    "Mask position {} has no characters left after exclusions",
+                    i + 1
+                ))
+            } else {
+                Ok(pool)
+            }
+        })
+        .collect()
+}
+
+/// A character type the generators can draw from
+///
+/// `u8` covers the printable ASCII sets from `build_char_set`. `char` covers
+/// sets whose letters come from another script (see `build_script_char_set`),
+/// which don't fit in a byte.
+pub trait CharUnit: Copy + Ord + Into<char> + From<u8> + fmt::Debug {}
+
+impl CharUnit for u8 {}
+
+impl CharUnit for char {}
+
+/// Per-type character pools derived from a character set
+///
+/// Built once per batch so the per-password generators don't rescan the
+/// character set for every password.
+#[derive(Debug, Clone)]
+pub(crate) struct CharClasses<C: CharUnit = u8> {
+    pub(crate) lowercase: Vec<C>,
+    pub(crate) uppercase: Vec<C>,
+    pub(crate) numeric: Vec<C>,
+    pub(crate) symbols: Vec<C>,
+    /// Weighted fill sampler; `None` fills uniformly from the whole set
+    fill: Option<WeightedFill<C>>,
+}
+
+/// Picks a class by weight, then a character uniformly within that class
+#[derive(Debug, Clone)]
+struct WeightedFill<C: CharUnit> {
+    index: WeightedIndex<u32>,
+    pools: Vec<Vec<C>>,
+}
+
+impl<C: CharUnit> CharClasses<C> {
+    pub(crate) fn from_char_set(char_set: &[C]) -> Self {
+        // Letters and digits are sorted and deduplicated; symbols keep the
+        // character set's order
+        let class = |matches: fn(char) -> bool| {
+            let mut pool: Vec<C> = char_set
+                .iter()
+                .copied()
+                .filter(|&c| matches(c.into()))
+                .collect();
+            pool.sort_unstable();
+            pool.dedup();
+            pool
+        };
+        let lowercase = class(char::is_lowercase);
+        let uppercase = class(char::is_uppercase);
+        let numeric = class(|c| c.is_ascii_digit());
+        let symbols = char_set
+            .iter()
+            .copied()
+            .filter(|&c| {
+                let c: char = c.into();
+                !(c.is_lowercase() || c.is_uppercase() || c.is_ascii_digit())
+            })
+            .collect();
+
+        CharClasses {
+            lowercase,
+            uppercase,
+            numeric,
+            symbols,
+            fill: None,
+        }
+    }
+
+    /// Pairs each class with its weight, skipping zero weights and empty pools
+    pub(crate) fn weighted_pools(&self, weights: &ClassWeights) -> Vec<(u32, Vec<C>)> {
+        let letters = [self.lowercase.as_slice(), self.uppercase.as_slice()].concat();
+        [
+            (weights.letters, letters),
+            (weights.digits, self.numeric.clone()),
+            (weights.symbols, self.symbols.clone()),
+        ]
+        .into_iter()
+        .filter(|(weight, pool)| *weight > 0 && !pool.is_empty())
+        .collect()
+    }
+
+    /// Switches the fill loop to class-weighted sampling
+    fn with_weights(mut self, weights: &ClassWeights) -> Result<Self, PasswordError> {
+        let (weights, pools): (Vec<u32>, Vec<Vec<C>>) =
+            self.weighted_pools(weights).into_iter().unzip();
+        let index = WeightedIndex::new(weights).map_err(|_| PasswordError::InvalidWeights)?;
+        self.fill = Some(WeightedFill { index, pools });
+        Ok(self)
+    }
+}
+
+/// Picks one character uniformly at random from a non-empty set
+///
+/// Character and word sampling goes through this function so it can be
+/// audited in one place; see `random_index` for how the index is drawn.
+/// Shuffles and the choice of positions to rewrite still use `rand`'s own
+/// sampling, which decides where characters go but not which ones are drawn.
+///
+/// # Panics
+///
+/// Panics if `set` is empty.
+pub fn pick<R: Rng + ?Sized, C: Copy>(set: &[C], rng: &mut R) -> C {
+    set[random_index(set.len(), rng)]
+}
+
+/// Draws an index in `0..len` with no bias, by rejection sampling
+///
+/// This is Lemire's method: a 32-bit draw `x` (64-bit when `len` doesn't fit
+/// in 32 bits) is multiplied by `len`, and the high half of the product is
+/// the index. Products whose low half falls below `2^32 % len` come from the
+/// part of the draw range that would favour some indices, so they are
+/// rejected and `x` is drawn again. Every index is then exactly equally
+/// likely. `rand`'s `random_range` is not used because, without its
+/// `unbiased` feature, it accepts those draws and carries a small bias.
+///
+/// A rejection happens with probability below `len / 2^32`. Otherwise this
+/// makes the same single draw, with the same result, as the `random_range`
+/// earlier releases used, so seeded output is unchanged except for those
+/// rare draws.
+pub(crate) fn random_index<R: Rng + ?Sized>(len: usize, rng: &mut R) -> usize {
+    assert!(len > 0, "cannot pick from an empty set");
+    match u32::try_from(len) {
+        Ok(range) => {
+            let threshold = range.wrapping_neg() % range;
+            loop {
+                let product = u64::from(rng.next_u32()) * u64::from(range);
+                if product as u32 >= threshold {
+                    return (product >> 32) as usize;
+                }
+            }
+        }
+        Err(_) => {
+            let range = len as u64;
+            let threshold = range.wrapping_neg() % range;
+            loop {
+                let product = u128::from(rng.next_u64()) * u128::from(range);
+                if product as u64 >= threshold {
+                    return (product >> 64) as usize;
+                }
+            }
+        }
+    }
+}
+
+/// Generates a password from a pattern
+pub(crate) fn generate_password_from_pattern<R: Rng, C: CharUnit>(
+    char_set: &[C],
+    classes: &CharClasses<C>,
+    pattern: &[PatternChar],
+    rng: &mut R,
+) -> String {
+    let mut pass = String::with_capacity(pattern.len());
+
+    for &pat_char in pattern {
+        let pool = match pat_char {
+            PatternChar::Lowercase => &classes.lowercase,
+            PatternChar::Uppercase => &classes.uppercase,
+            PatternChar::Numeric => &classes.numeric,
+            PatternChar::Symbol => &classes.symbols,
+        };
+        // Fall back to the full character set when the class is unavailable
+        let c = if pool.is_empty() {
+            pick(char_set, rng)
+        } else {
+            pick(pool, rng)
+        };
+        pass.push(c.into());
+    }
+
+    pass
+}
+
+/// Generates a single password ensuring minimum character type requirements
+///
+/// Fill semantics:
+/// 1. Required characters are drawn first, in the order capitals, numerals,
+///    symbols, each from its own class pool. `None` and `Some(0)` are
+///    equivalent: neither places a character nor consumes RNG output. A
+///    minimum whose class is absent from the character set is skipped.
+/// 2. The remaining `length - placed` positions are drawn from the full
+///    character set. When the minimums add up to exactly `length`, no fill
+///    draws happen.
+/// 3. The result is shuffled so required characters land in random positions.
+///
+/// If the minimums add up to more than `length`, they take precedence and the
+/// password is longer than requested.
+pub(crate) fn generate_password_with_minimums<R: Rng, C: CharUnit>(
+    char_set: &[C],
+    classes: &CharClasses<C>,
+    length: u32,
+    min_capitals: Option<u32>,
+    min_numerals: Option<u32>,
+    min_symbols: Option<u32>,
+    rng: &mut R,
+) -> String {
+    let mut pass_vec: Vec<char> = Vec::with_capacity(length as usize);
+
+    // First, ensure minimum requirements are met (capitals, numerals, symbols)
+    let requirements = [
+        (min_capitals, &classes.uppercase),
+        (min_numerals, &classes.numeric),
+        (min_symbols, &classes.symbols),
+    ];
+    for (min, pool) in requirements {
+        // Some(0) is treated exactly like None
+        let required = min.unwrap_or(0);
+        if required == 0 || pool.is_empty() {
+            continue;
+        }
+        for _ in 0..required {
+            pass_vec.push(pick(pool, rng).into());
+        }
+    }
+
+    // Fill the rest randomly
+    while pass_vec.len() < length as usize {
+        let c = match classes.fill {
+            Some(ref fill) => pick(&fill.pools[fill.index.sample(rng)], rng),
+            None => pick(char_set, rng),
+        };
+        pass_vec.push(c.into());
+    }
+
+    // Shuffle to randomize positions
+    use rand::seq::SliceRandom;
+    pass_vec.shuffle(rng);
+
+    pass_vec.into_iter().collect()
+}
+
+/// Makes a shuffled password start with a letter
+///
+/// A non-letter in position 0 is swapped with a randomly chosen letter
+/// elsewhere in the password, which keeps every minimum intact. If the
+/// password has no letters, a digit or symbol beyond its class minimum is
+/// moved to the front and redrawn from the letters in the character set
+/// (which `first_char_alpha_available` guarantees exist). Only when the
+/// numeral and symbol minimums fill the whole password does a required
+/// character get replaced.
+fn force_first_char_alpha<R: Rng, C: CharUnit>(
+    pass: String,
+    classes: &CharClasses<C>,
+    params: &GenerationParams,
+    rng: &mut R,
+) -> String {
+    let mut chars: Vec<char> = pass.chars().collect();
+    if !chars.first().is_some_and(|c| c.is_alphabetic()) {
+        let letters: Vec<usize> = (1..chars.len())
+            .filter(|&i| chars[i].is_alphabetic())
+            .collect();
+        if letters.is_empty() {
+            // Without letters, everything that isn't a digit is a symbol
+            let digits = chars.iter().filter(|c| c.is_ascii_digit()).count() as u32;
+            let symbols = chars.len() as u32 - digits;
+            let spare_digit = digits > params.min_numerals.unwrap_or(0);
+            let spare_symbol = symbols > params.min_symbols.unwrap_or(0);
+            let spare = (0..chars.len())
+                .find(|&i| {
+                    if chars[i].is_ascii_digit() {
+                        spare_digit
+                    } else {
+                        spare_symbol
+                    }
+                })
+                .unwrap_or(0);
+            chars.swap(0, spare);
+
+            let alphabet = [&classes.lowercase[..], &classes.uppercase[..]].concat();
+            chars[0] = pick(&alphabet, rng).into();
+        } else {
+            let i = letters[rng.random_range(0..letters.len())];
+            chars.swap(0, i);
+        }
+    }
+    chars.into_iter().collect()
+}
+
+/// Generates a password of `length` distinct characters from a deduplicated set
+///
+/// A partial Fisher-Yates shuffle: position `i` swaps with a random position
+/// in `i..len`, so exactly `length` draws are made however close `length` is
+/// to the size of the set. The caller checks that `length` fits.
+fn generate_password_unique_chars<R: Rng, C: CharUnit>(
+    char_set: &[C],
+    length: u32,
+    rng: &mut R,
+) -> String {
+    let length = length as usize;
+    let mut pool = char_set.to_vec();
+    for i in 0..length {
+        let j = i + random_index(pool.len() - i, rng);
+        pool.swap(i, j);
+    }
+    pool[..length].iter().map(|&c| c.into()).collect()
+}
+
+/// Whether `generate_password_single_class` can stand in for the fill loop
+///
+/// Only with `bulk_bytes`, as the two draw differently. It applies to sets of at most 256 characters from a single class (PINs,
+/// hex digits, lowercase only, ...) with nothing that shapes the fill:
+/// minimums, weights, maximums or `first_char_alpha`. Re-roll constraints
+/// still apply to its output.
+fn single_class_fast_path<C: CharUnit>(
+    char_set: &[C],
+    classes: &CharClasses<C>,
+    params: &GenerationParams,
+) -> bool {
+    let pools = [
+        &classes.lowercase,
+        &classes.uppercase,
+        &classes.numeric,
+        &classes.symbols,
+    ];
+    params.bulk_bytes
+        && char_set.len() <= 256
+        && pools.iter().filter(|pool| !pool.is_empty()).count() == 1
+        && [params.min_capitals, params.min_numerals, params.min_symbols]
+            .iter()
+            .all(|min| min.unwrap_or(0) == 0)
+        && params.weights.is_none()
+        && !has_maximums(params)
+        && !params.first_char_alpha
+}
+
+/// Generates a password from a small set with bulk random bytes
+///
+/// Each password fills a buffer of `length` bytes at a time (64 at most) with
+/// one `fill_bytes` call. Byte `b` maps to `char_set[b % n]`, except that the
+/// top `256 % n` values are rejected so every character stays equally likely;
+/// the buffer is refilled until the password is complete and any bytes left
+/// over are dropped. The set must have between 1 and 256 characters.
+fn generate_password_single_class<R: Rng, C: CharUnit>(
+    char_set: &[C],
+    length: u32,
+    rng: &mut R,
+) -> String {
+    let n = char_set.len();
+    let accepted = 256 - 256 % n;
+    let mut pass = String::with_capacity(length as usize);
+    let mut remaining = length as usize;
+    let mut buffer = [0u8; 64];
+    while remaining > 0 {
+        let bytes = &mut buffer[..remaining.min(64)];
+        rng.fill_bytes(bytes);
+        for &b in bytes.iter() {
+            if usize::from(b) < accepted && remaining > 0 {
+                pass.push(char_set[usize::from(b) % n].into());
+                remaining -= 1;
+            }
+        }
+    }
+    pass
+}
+
+/// Whether any of the class maximums is set
+fn has_maximums(params: &GenerationParams) -> bool {
+    params.max_capitals.is_some() || params.max_numerals.is_some() || params.max_symbols.is_some()
+}
+
+/// Class of a character, in `CharClasses` order: lowercase, uppercase, numeric, symbol
+fn class_index(c: char) -> usize {
+    if c.is_lowercase() {
+        0
+    } else if c.is_uppercase() {
+        1
+    } else if c.is_ascii_digit() {
+        2
+    } else {
+        3
+    }
+}
+
+/// Brings each class down to its maximum
+///
+/// A random character of a class over its cap is redrawn from the union of
+/// the classes still under theirs, until every class fits. Counts never drop
+/// below a cap, so minimums up to it still hold. If nothing is left to draw
+/// from, the password is returned as is and the re-roll check reports it.
+fn apply_maximums<R: Rng, C: CharUnit>(
+    pass: String,
+    classes: &CharClasses<C>,
+    params: &GenerationParams,
+    rng: &mut R,
+) -> String {
+    let limits = [
+        (None, &classes.lowercase),
+        (params.max_capitals, &classes.uppercase),
+        (params.max_numerals, &classes.numeric),
+        (params.max_symbols, &classes.symbols),
+    ];
+    let mut chars: Vec<char> = pass.chars().collect();
+    let mut counts = [0u32; 4];
+    for &c in &chars {
+        counts[class_index(c)] += 1;
+    }
+
+    for class in 1..limits.len() {
+        let Some(max) = limits[class].0 else {
+            continue;
+        };
+        while counts[class] > max {
+            let open: Vec<C> = (0..limits.len())
+                .filter(|&k| k != class && limits[k].0.is_none_or(|cap| counts[k] < cap))
+                .flat_map(|k| limits[k].1.iter().copied())
+                .collect();
+            if open.is_empty() {
+                return chars.into_iter().collect();
+            }
+            let over: Vec<usize> = (0..chars.len())
+                .filter(|&i| class_index(chars[i]) == class)
+                .collect();
+            let i = over[rng.random_range(0..over.len())];
+            chars[i] = pick(&open, rng).into();
+            counts[class] -= 1;
+            counts[class_index(chars[i])] += 1;
+        }
+    }
+    chars.into_iter().collect()
+}
+
+/// Produces one password per call from settings it already holds
+///
+/// `generate_passwords` sends every password through a `Generator` picked by
+/// `GenerationStrategy`; memorable passwords implement it too.
+pub trait Generator {
+    /// Draws one password from `rng`
+    fn generate<R: Rng>(&self, rng: &mut R) -> String;
+}
+
+/// How each password of a batch is built
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GenerationStrategy {
+    /// One character per position from that position's mask pool
+    Mask,
+    /// One character per position from the class the pattern names
+    Pattern,
+    /// Sampling without replacement from the deduplicated set
+    UniqueChars,
+    /// The minimums, fill and shuffle path, with weights, maximums and
+    /// `first_char_alpha` applied on top
+    Random,
+}
+
+impl GenerationStrategy {
+    /// The strategy `params` asks for: a mask wins over a pattern, and either
+    /// over `all_unique_chars`
+    pub fn from_params(params: &GenerationParams) -> Self {
+        if params.mask.is_some() {
+            GenerationStrategy::Mask
+        } else if params.pattern.is_some() {
+            GenerationStrategy::Pattern
+        } else if params.all_unique_chars {
+            GenerationStrategy::UniqueChars
+        } else {
+            GenerationStrategy::Random
+        }
+    }
+}
+
+/// The `Generator` for one batch: its strategy and the pools it draws from
+struct StrategyGenerator<'a, C: CharUnit> {
+    strategy: GenerationStrategy,
+    char_set: &'a [C],
+    classes: &'a CharClasses<C>,
+    params: &'a GenerationParams,
+}
+
+impl<'a, C: CharUnit> StrategyGenerator<'a, C> {
+    fn new(char_set: &'a [C], classes: &'a CharClasses<C>, params: &'a GenerationParams) -> Self {
+        StrategyGenerator {
+            strategy: GenerationStrategy::from_params(params),
+            char_set,
+            classes,
+            params,
+        }
+    }
+
+    /// The `Random` strategy, which has a fast path for single-class sets
+    fn random<R: Rng>(&self, rng: &mut R) -> String {
+        let (char_set, classes, params) = (self.char_set, self.classes, self.params);
+        if single_class_fast_path(char_set, classes, params) {
+            return generate_password_single_class(char_set, params.length, rng);
+        }
+        let pass = generate_password_with_minimums(
+            char_set,
+            classes,
+            params.length,
+            params.min_capitals,
+            params.min_numerals,
+            params.min_symbols,
+            rng,
+        );
+        let pass = if has_maximums(params) {
+            apply_maximums(pass, classes, params, rng)
+        } else {
+            pass
+        };
+        if params.first_char_alpha {
+            force_first_char_alpha(pass, classes, params, rng)
+        } else {
+            pass
+        }
+    }
+}
+
+impl<C: CharUnit> Generator for StrategyGenerator<'_, C> {
+    fn generate<R: Rng>(&self, rng: &mut R) -> String {
+        match (self.strategy, &self.params.mask, &self.params.pattern) {
+            (GenerationStrategy::Mask, Some(mask), _) => {
+                mask.iter().map(|pool| pick(pool, rng) as char).collect()
+            }
+            (GenerationStrategy::Pattern, _, Some(pat)) => {
+                generate_password_from_pattern(self.char_set, self.classes, pat, rng)
+            }
+            (GenerationStrategy::UniqueChars, ..) => {
+                generate_password_unique_chars(self.char_set, self.params.length, rng)
+            }
+            _ => self.random(rng),
+        }
+    }
+}
+
+/// Whether generation can honour `first_char_alpha`
+///
+/// The fill path needs letters in the character set. Patterns and masks
+/// aren't rewritten, so they must already start with a letter.
+fn first_char_alpha_available<C: CharUnit>(
+    classes: &CharClasses<C>,
+    params: &GenerationParams,
+) -> bool {
+    if let Some(ref mask) = params.mask {
+        return mask
+            .first()
+            .is_some_and(|pool| pool.iter().all(u8::is_ascii_alphabetic));
+    }
+    match params.pattern.as_ref().and_then(|p| p.first()) {
+        Some(PatternChar::Lowercase) => !classes.lowercase.is_empty(),
+        Some(PatternChar::Uppercase) => !classes.uppercase.is_empty(),
+        Some(_) => false,
+        None => !(classes.lowercase.is_empty() && classes.uppercase.is_empty()),
+    }
+}
+
+/// Generates passwords using the provided character set and RNG
+///
+/// Returns `PasswordError::RetryLimitExceeded` if a re-roll constraint such as
+/// `exclude_similar_to` can't be satisfied within `retry_limit` attempts
+/// (`MAX_REROLL_ATTEMPTS` by default).
+///
+/// # Reproducibility
+///
+/// For the same RNG state, character set and `params`, the output is stable
+/// across releases of this crate. Passwords are generated one after another,
+/// and each one draws from the RNG in this order and no other:
+///
+/// - **mask**: one `pick` per position, from that position's pool
+/// - **pattern**: one `pick` per position, from the class pool, or from the
+///   whole character set when the class is empty
+/// - **all unique characters**: one draw per position `i`, choosing the
+///   position in `i..n` of the deduplicated set to swap into it
+/// - **single-class sets with `bulk_bytes`** (at most 256 characters of one
+///   class, with no minimums, weights, maximums or `first_char_alpha`):
+///   `fill_bytes` calls of up to 64 bytes, mapped with rejection until the
+///   password is complete. Without `bulk_bytes` these sets take the default
+///   path below
+/// - **otherwise**: the minimums, the fill and the shuffle described on
+///   `generate_password_with_minimums`. With class weights, each fill
+///   character is two draws: the class, then a character within it. With
+///   maximums, each character over a cap takes two more draws after the
+///   shuffle: its position, then its replacement. With `first_char_alpha`,
+///   at most one more draw follows
+///
+/// Letter and digit pools are in sorted order; the symbol pool keeps the
+/// character set's order. A password rejected by a re-roll constraint has
+/// consumed its draws and the next attempt continues from there, so
+/// constraints that reject nothing change nothing. Different modes make
+/// different draws, so switching modes gives unrelated output.
+///
+/// The guarantee covers this crate's code only. `rand` may change the
+/// algorithm behind `StdRng` in a new release, so upgrading it is treated as
+/// a breaking change for seeded output.
+pub fn generate_passwords<R: Rng, C: CharUnit>(
+    char_set: &[C],
+    params: &GenerationParams,
+    rng: &mut R,
+) -> Result<Vec<String>, PasswordError> {
+    let mut passwords = Vec::with_capacity(params.count as usize);
+    generate_passwords_into(char_set, params, rng, &mut passwords)?;
+    Ok(passwords)
+}
+
+/// Generates a single password
+///
+/// The same as `generate_passwords` with a `count` of 1, without the `Vec`:
+/// for the same RNG state the password is the one that call would return.
+/// `count` and `unique` are ignored.
+pub fn generate_one<R: Rng, C: CharUnit>(
+    char_set: &[C],
+    params: &GenerationParams,
+    rng: &mut R,
+) -> Result<String, PasswordError> {
+    let batch = Batch::prepare(char_set, params)?;
+    let char_set = batch.distinct.as_deref().unwrap_or(char_set);
+    let params = batch.ensured.as_ref().unwrap_or(params);
+    let generator = StrategyGenerator::new(char_set, &batch.classes, params);
+    let limit = params.retry_limit.unwrap_or(MAX_REROLL_ATTEMPTS);
+    generate_constrained(&generator, params, &BTreeSet::new(), limit, rng)
+}
+
+/// Generates the passwords `args` describe, drawing from `rng`
+///
+/// This is the library's single entry point: it runs `validate_args`, builds
+/// the character set and calls `generate_passwords` with the length, count,
+/// minimums, maximums, pattern and `max_repeats` from `args`. For the same
+/// RNG state the output is what that call returns, so the reproducibility
+/// guarantee on `generate_passwords` carries over. Options `PasswordArgs`
+/// has no field for, such as masks, weights or `unique`, need
+/// `generate_passwords` with a `GenerationParams` of your own.
+///
+/// Any `Rng` works: `rand::rng()`, a seeded `StdRng`, or a hardware source
+/// or custom PRNG behind `RngCore`:
+///
+/// ```
+/// use rand::RngCore;
+/// use rpg_util::{PasswordArgs, run_with_rng};
+///
+/// /// Marsaglia's xorshift64; fine for a demo, not for real passwords
+/// struct XorShift(u64);
+///
+/// impl RngCore for XorShift {
+///     fn next_u32(&mut self) -> u32 {
+///         (self.next_u64() >> 32) as u32
+///     }
+///
+///     fn next_u64(&mut self) -> u64 {
+///         self.0 ^= self.0 << 13;
+///         self.0 ^= self.0 >> 7;
+///         self.0 ^= self.0 << 17;
+///         self.0
+///     }
+///
+///     fn fill_bytes(&mut self, dest: &mut [u8]) {
+///         for chunk in dest.chunks_mut(8) {
+///             let bytes = self.next_u64().to_le_bytes();
+///             chunk.copy_from_slice(&bytes[..chunk.len()]);
+///         }
+///     }
+/// }
+///
+/// let args = PasswordArgs {
+///     length: 20,
+///     password_count: 3,
+///     ..Default::default()
+/// };
+/// let passwords = run_with_rng(&args, &mut XorShift(396)).unwrap();
+/// assert_eq!(passwords.len(), 3);
+/// // The same seed gives the same passwords
+/// assert_eq!(passwords, run_with_rng(&args, &mut XorShift(396)).unwrap());
+/// ```
+pub fn run_with_rng<R: Rng>(
+    args: &PasswordArgs,
+    rng: &mut R,
+) -> Result<Vec<String>, PasswordError> {
+    validate_args(args)?;
+    let char_set = build_char_set(args)?;
+    let params = GenerationParams {
+        length: args.length,
+        count: args.password_count,
+        min_capitals: args.min_capitals,
+        min_numerals: args.min_numerals,
+        min_symbols: args.min_symbols,
+        max_capitals: args.max_capitals,
+        max_numerals: args.max_numerals,
+        max_symbols: args.max_symbols,
+        pattern: args.pattern.clone(),
+        max_repeats: args.max_repeats,
+        ..Default::default()
+    };
+    generate_passwords(&char_set, &params, rng)
+}
+
+/// Generates passwords into a caller-provided buffer
+///
+/// The buffer is cleared first and its allocation is reused, which avoids a
+/// fresh `Vec` per batch in long-running services. Output is identical to
+/// `generate_passwords` for the same RNG state.
+pub fn generate_passwords_into<R: Rng, C: CharUnit>(
+    char_set: &[C],
+    params: &GenerationParams,
+    rng: &mut R,
+    passwords: &mut Vec<String>,
+) -> Result<(), PasswordError> {
+    passwords.clear();
+    passwords.reserve(params.count as usize);
+
+    let batch = Batch::prepare(char_set, params)?;
+    let char_set = batch.distinct.as_deref().unwrap_or(char_set);
+    let params = batch.ensured.as_ref().unwrap_or(params);
+    let classes = &batch.classes;
+
+    // Fail up front rather than re-rolling collisions that can never resolve
+    let keyspace = if params.unique {
+        let keyspace = keyspace(char_set, classes, params);
+        if keyspace < params.count as u128 {
+            return Err(PasswordError::KeyspaceTooSmall {
+                keyspace,
+                count: params.count,
+            });
+        }
+        keyspace
+    } else {
+        0
+    };
+
+    let generator = StrategyGenerator::new(char_set, classes, params);
+    let mut seen = BTreeSet::new();
+    for _ in 0..params.count {
+        let limit = retry_limit(params, keyspace, seen.len());
+        let pass = generate_constrained(&generator, params, &seen, limit, rng)?;
+        if params.unique {
+            seen.insert(pass.clone());
+        }
+        passwords.push(pass);
+    }
+
+    Ok(())
+}
+
+/// What a batch needs before its first password: the pools, and the
+/// character set and parameters adjusted for the chosen mode
+struct Batch<C: CharUnit> {
+    /// The deduplicated set when sampling without replacement
+    distinct: Option<Vec<C>>,
+    classes: CharClasses<C>,
+    /// The parameters with `ensure_all_types` applied
+    ensured: Option<GenerationParams>,
+}
+
+impl<C: CharUnit> Batch<C> {
+    /// Checks that `params` can generate from `char_set` and builds the pools
+    fn prepare(char_set: &[C], params: &GenerationParams) -> Result<Self, PasswordError> {
+        // Sampling without replacement draws from each distinct character once
+        let mut distinct = None;
+        if params.all_unique_chars && params.pattern.is_none() && params.mask.is_none() {
+            let mut seen = BTreeSet::new();
+            let unique: Vec<C> = char_set
+                .iter()
+                .copied()
+                .filter(|&c| seen.insert(c))
+                .collect();
+            if params.length as usize > unique.len() {
+                return Err(PasswordError::LengthExceedsCharacterSet {
+                    length: params.length,
+                    available: unique.len(),
+                });
+            }
+            distinct = Some(unique);
+        }
+        let char_set = distinct.as_deref().unwrap_or(char_set);
+
+        // Build the per-type pools once for the whole batch
+        let mut classes = CharClasses::from_char_set(char_set);
+        if let Some(ref weights) = params.weights {
+            classes = classes.with_weights(weights)?;
+        }
+
+        if params.require_mixed_case
+            && (classes.lowercase.is_empty() || classes.uppercase.is_empty())
+        {
+            return Err(PasswordError::MixedCaseUnavailable);
+        }
+
+        if params.first_char_alpha && !first_char_alpha_available(&classes, params) {
+            return Err(PasswordError::FirstCharAlphaUnavailable);
+        }
+
+        let ensured =
+            if params.ensure_all_types && params.pattern.is_none() && params.mask.is_none() {
+                Some(ensure_all_types(&classes, params)?)
+            } else {
+                None
+            };
+        let params = ensured.as_ref().unwrap_or(params);
+
+        if has_maximums(params) && params.pattern.is_none() && params.mask.is_none() {
+            check_limits(
+                [params.min_capitals, params.min_numerals, params.min_symbols],
+                [params.max_capitals, params.max_numerals, params.max_symbols],
+            )?;
+            // Lowercase letters are never capped, so only a set without them can
+            // run out of characters
+            let limits = [
+                (None, &classes.lowercase),
+                (params.max_capitals, &classes.uppercase),
+                (params.max_numerals, &classes.numeric),
+                (params.max_symbols, &classes.symbols),
+            ];
+            let total = limits
+                .iter()
+                .filter(|(_, pool)| !pool.is_empty())
+                .try_fold(0u32, |total, (max, _)| {
+                    max.map(|max| total.saturating_add(max))
+                });
+            if let Some(total) = total
+                && total < params.length
+            {
+                return Err(PasswordError::MaximumsBelowLength {
+                    total,
+                    length: params.length,
+                });
+            }
+        }
+
+        Ok(Batch {
+            distinct,
+            classes,
+            ensured,
+        })
+    }
+}
+
+/// Rewrites `params` so `ensure_all_types` runs through the minimums path
+///
+/// Classes missing from the character set aren't required. The lowercase
+/// check stays on as a re-roll only when lowercase letters are available, and
+/// needs a fill position left over after the minimums to succeed.
+fn ensure_all_types<C: CharUnit>(
+    classes: &CharClasses<C>,
+    params: &GenerationParams,
+) -> Result<GenerationParams, PasswordError> {
+    let at_least_one = |min: Option<u32>, pool: &[C]| {
+        if pool.is_empty() {
+            min
+        } else {
+            Some(min.unwrap_or(0).max(1))
+        }
+    };
+    let ensured = GenerationParams {
+        min_capitals: at_least_one(params.min_capitals, &classes.uppercase),
+        min_numerals: at_least_one(params.min_numerals, &classes.numeric),
+        min_symbols: at_least_one(params.min_symbols, &classes.symbols),
+        ensure_all_types: !classes.lowercase.is_empty(),
+        ..params.clone()
+    };
+    // Minimums for classes missing from the set place nothing
+    let required = [
+        (ensured.min_capitals, &classes.uppercase),
+        (ensured.min_numerals, &classes.numeric),
+        (ensured.min_symbols, &classes.symbols),
+        (Some(1), &classes.lowercase),
+    ]
+    .iter()
+    .filter(|(_, pool)| !pool.is_empty())
+    .map(|(min, _)| min.unwrap_or(0))
+    .fold(0u32, u32::saturating_add);
+    if required > params.length {
+        return Err(PasswordError::TooManyRequiredTypes {
+            required,
+            length: params.length,
+        });
+    }
+    Ok(ensured)
+}
+
+/// Returns an upper bound on the number of distinct passwords `params` can produce
+///
+/// Saturates at `u128::MAX`. Minimums only shrink the space, so for them this
+/// is the unconstrained `char_set.len() ^ length`.
+fn keyspace<C: CharUnit>(
+    char_set: &[C],
+    classes: &CharClasses<C>,
+    params: &GenerationParams,
+) -> u128 {
+    if let Some(ref mask) = params.mask {
+        return mask
+            .iter()
+            .fold(1u128, |acc, pool| acc.saturating_mul(pool.len() as u128));
+    }
+
+    match params.pattern {
+        Some(ref pattern) => pattern.iter().fold(1u128, |acc, &pat_char| {
+            let pool = match pat_char {
+                PatternChar::Lowercase => &classes.lowercase,
+                PatternChar::Uppercase => &classes.uppercase,
+                PatternChar::Numeric => &classes.numeric,
+                PatternChar::Symbol => &classes.symbols,
+            };
+            let size = if pool.is_empty() {
+                char_set.len()
+            } else {
+                pool.len()
+            };
+            acc.saturating_mul(size as u128)
+        }),
+        None => (char_set.len() as u128)
+            .checked_pow(params.length)
+            .unwrap_or(u128::MAX),
+    }
+}
+
+/// Returns the number of attempts the next password gets before giving up
+///
+/// An explicit `retry_limit` is used as is. Otherwise, with `unique`, the
+/// default grows with the share of the keyspace already taken: once `taken`
+/// of `keyspace` passwords are out, a fresh draw is new only with
+/// probability `(keyspace - taken) / keyspace`, so a fixed budget would fail
+/// batches that use most of a small keyspace.
+fn retry_limit(params: &GenerationParams, keyspace: u128, taken: usize) -> u32 {
+    if let Some(limit) = params.retry_limit {
+        return limit;
+    }
+    if !params.unique {
+        return MAX_REROLL_ATTEMPTS;
+    }
+    let scale = keyspace.div_ceil(keyspace - taken as u128);
+    u32::try_from(scale.saturating_mul(MAX_REROLL_ATTEMPTS as u128)).unwrap_or(u32::MAX)
+}
+
+/// Returns the name of the first re-roll constraint a candidate password violates
+fn violated_constraint(
+    pass: &str,
+    params: &GenerationParams,
+    seen: &BTreeSet<String>,
+) -> Option<&'static str> {
+    // Passwords that resemble the string being rotated away from
+    if let Some(ref old) = params.exclude_similar_to
+        && similarity(pass, old) > MAX_SIMILARITY
+    {
+        return Some("exclude-similar-to");
+    }
+
+    // Passwords that were issued before
+    if params.forbidden_passwords.contains(pass) {
+        return Some("exclude-password-file");
+    }
+
+    // The minimums cover the other classes; see `ensure_all_types`
+    if params.ensure_all_types
+        && params.pattern.is_none()
+        && params.mask.is_none()
+        && !pass.chars().any(char::is_lowercase)
+    {
+        return Some("ensure-all-types");
+    }
+
+    // Passwords that `apply_maximums` couldn't bring under the caps, or that
+    // `first_char_alpha` pushed back over
+    if has_maximums(params) && params.pattern.is_none() && params.mask.is_none() {
+        let mut counts = [0u32; 4];
+        for c in pass.chars() {
+            counts[class_index(c)] += 1;
+        }
+        let caps = [
+            (params.max_capitals, counts[1], "max-capitals"),
+            (params.max_numerals, counts[2], "max-numerals"),
+            (params.max_symbols, counts[3], "max-symbols"),
+        ];
+        for (max, count, name) in caps {
+            if max.is_some_and(|max| count > max) {
+                return Some(name);
+            }
+        }
+    }
+
+    // Passwords with too long a run of one character
+    if let Some(max) = params.max_repeats
+        && longest_run(pass) > max
+    {
+        return Some("max-repeats");
+    }
+
+    // Passwords that fail a "both upper and lower case" policy
+    if params.require_mixed_case
+        && !(pass.chars().any(char::is_lowercase) && pass.chars().any(char::is_uppercase))
+    {
+        return Some("require-mixed-case");
+    }
+
+    // Passwords outside an arbitrary acceptance rule
+    #[cfg(feature = "regex")]
+    if let Some(ref re) = params.must_match
+        && !re.is_match(pass)
+    {
+        return Some("match");
+    }
+
+    // Passwords already produced earlier in this batch
+    if params.unique && seen.contains(pass) {
+        return Some("unique");
+    }
+
+    None
+}
+
+/// Length of the longest run of one repeated character in `pass`
+fn longest_run(pass: &str) -> u32 {
+    let mut longest = 0;
+    let mut run = 0;
+    let mut prev = None;
+    for c in pass.chars() {
+        run = if prev == Some(c) { run + 1 } else { 1 };
+        longest = longest.max(run);
+        prev = Some(c);
+    }
+    longest
+}
+
+/// Generates a single password, re-rolling until it satisfies every re-roll constraint
+fn generate_constrained<R: Rng>(
+    generator: &impl Generator,
+    params: &GenerationParams,
+    seen: &BTreeSet<String>,
+    limit: u32,
+    rng: &mut R,
+) -> Result<String, PasswordError> {
+    let mut attempts = 1;
+    loop {
+        let pass = generator.generate(rng);
+        match violated_constraint(&pass, params, seen) {
+            None => return Ok(pass),
+            Some(constraint) if attempts >= limit => {
+                return Err(PasswordError::RetryLimitExceeded {
+                    constraint,
+                    attempts,
+                });
+            }
+            Some(_) => attempts += 1,
+        }
+    }
+}
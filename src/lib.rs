@@ -8,6 +8,7 @@
 //! - Character exclusion with range support
 //! - Minimum character type requirements
 //! - Pattern-based generation
+//! - Memorable word + number + symbol passwords
 //! - Uniform character distribution
 //!
 //! ## Example
@@ -28,6 +29,7 @@
 //!     pattern: None,
 //!     length: 16,
 //!     password_count: 1,
+//!     ..Default::default()
 //! };
 //!
 //! let char_set = build_char_set(&args).unwrap();
@@ -39,113 +41,156 @@
 //!     min_numerals: None,
 //!     min_symbols: None,
 //!     pattern: None,
+//!     ..Default::default()
 //! };
-//! let passwords = rpg_util::generate_passwords(&char_set, &gen_params, &mut rng);
+//! let passwords = rpg_util::generate_passwords(&char_set, &gen_params, &mut rng).unwrap();
 //! ```
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::{String, ToString};
+#[cfg(feature = "std")]
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
 use rand::Rng;
-use std::collections::HashSet;
-use std::fmt;
+
+mod generator;
+#[cfg(feature = "std")]
+mod policy;
+#[cfg(feature = "tui")]
+pub mod tui;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+mod wordlist;
+
+pub use generator::*;
+#[cfg(feature = "std")]
+pub use policy::{Policy, PolicyClass};
+pub use wordlist::WORDLIST;
 
 /// Calculates password entropy in bits
+#[cfg(feature = "std")]
 pub fn calculate_entropy(char_set_size: usize, length: u32) -> f64 {
     (char_set_size as f64).log2() * length as f64
 }
 
-/// Custom error type for password generation
-#[derive(Debug, Clone)]
-pub enum PasswordError {
-    InvalidLength,
-    InvalidLengthTooLong,
-    InvalidCount,
-    EmptyCharacterSet,
-    AllTypesDisabled,
+/// The shortest length whose `calculate_entropy` reaches `bits`
+///
+/// Returns `None` when no length can: a set of fewer than two characters
+/// carries no entropy, so only a target of 0 bits is reachable, at length 0.
+#[cfg(feature = "std")]
+pub fn length_for_entropy(char_set_size: usize, bits: f64) -> Option<u32> {
+    if bits <= 0.0 {
+        return Some(0);
+    }
+    if char_set_size < 2 {
+        return None;
+    }
+    let length = (bits / (char_set_size as f64).log2()).ceil();
+    if length > u32::MAX as f64 {
+        return None;
+    }
+    // Step back if rounding pushed an exact fit up by one
+    let length = length as u32;
+    if length > 1 && calculate_entropy(char_set_size, length - 1) >= bits {
+        Some(length - 1)
+    } else {
+        Some(length)
+    }
 }
 
-impl fmt::Display for PasswordError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            PasswordError::InvalidLength => {
-                write!(f, "Error: Password length must be greater than 0.")
-            }
-            PasswordError::InvalidLengthTooLong => {
-                write!(
-                    f,
-                    "Error: Password length exceeds maximum of 10,000 characters."
-                )
-            }
-            PasswordError::InvalidCount => {
-                write!(f, "Error: Password count must be greater than 0.")
-            }
-            PasswordError::EmptyCharacterSet => {
-                write!(
-                    f,
-                    "Error: All characters have been excluded or disabled. Cannot generate passwords.\n\
-                    Hint: Try removing some character exclusions or enabling character types."
-                )
-            }
-            PasswordError::AllTypesDisabled => {
-                write!(
-                    f,
-                    "Error: All character types are disabled and/or all remaining characters are excluded.\n\
-                    Hint: At least one character type must be enabled. Try removing --capitals-off, --numerals-off, or --symbols-off."
-                )
-            }
-        }
-    }
+/// Calculates the entropy in bits of `length` characters drawn without replacement
+///
+/// This is `log2(n! / (n - length)!)`, which `--all-unique-chars` passwords
+/// carry instead of `calculate_entropy`'s `length * log2(n)`.
+#[cfg(feature = "std")]
+pub fn unique_chars_entropy(char_set_size: usize, length: u32) -> f64 {
+    (0..length as usize)
+        .map(|i| (char_set_size.saturating_sub(i) as f64).log2())
+        .sum()
 }
 
-impl std::error::Error for PasswordError {}
-
-// ASCII character range constants
-const ASCII_LOWERCASE_START: u8 = b'a';
-const ASCII_LOWERCASE_END: u8 = b'z';
-const ASCII_UPPERCASE_START: u8 = b'A';
-const ASCII_UPPERCASE_END: u8 = b'Z';
-const ASCII_NUMERAL_START: u8 = b'0';
-const ASCII_NUMERAL_END: u8 = b'9';
-const ASCII_SYMBOL_RANGE_1_START: u8 = 33; // !
-const ASCII_SYMBOL_RANGE_1_END: u8 = 47; // /
-const ASCII_SYMBOL_RANGE_2_START: u8 = 58; // :
-const ASCII_SYMBOL_RANGE_2_END: u8 = 64; // @
-const ASCII_SYMBOL_RANGE_3_START: u8 = 91; // [
-const ASCII_SYMBOL_RANGE_3_END: u8 = 96; // `
-const ASCII_SYMBOL_RANGE_4_START: u8 = 123; // {
-const ASCII_SYMBOL_RANGE_4_END: u8 = 126; // ~
-
-/// Pattern character types
-#[derive(Debug, Clone, Copy)]
-pub enum PatternChar {
-    Lowercase,
-    Uppercase,
-    Numeric,
-    Symbol,
+/// Calculates the empirical Shannon entropy of `samples`, in bits per sample
+///
+/// This is `-sum(p * log2(p))` over the observed frequency `p` of each
+/// distinct value. For a long enough sample from a uniform generator it
+/// approaches `log2(n)` for `n` possible values; a noticeably lower figure
+/// means some values come up more often than others. Returns 0 for an empty
+/// sample.
+#[cfg(feature = "std")]
+pub fn shannon_entropy<T: Ord>(samples: &[T]) -> f64 {
+    let mut counts = alloc::collections::BTreeMap::new();
+    for sample in samples {
+        *counts.entry(sample).or_insert(0usize) += 1;
+    }
+    let total = samples.len() as f64;
+    counts
+        .values()
+        .map(|&count| {
+            let p = count as f64 / total;
+            -p * p.log2()
+        })
+        .sum()
 }
 
-/// Parameters for password generation
-#[derive(Debug, Clone)]
-pub struct GenerationParams {
-    pub length: u32,
-    pub count: u32,
-    pub min_capitals: Option<u32>,
-    pub min_numerals: Option<u32>,
-    pub min_symbols: Option<u32>,
-    pub pattern: Option<Vec<PatternChar>>,
+/// Calculates the entropy in bits of one class-weighted fill character
+///
+/// Each class contributes its share of the weight times the bits needed to
+/// name the class and then a character within it. This is below
+/// `log2(char_set.len())` unless the weights happen to match the pool sizes,
+/// so biasing the fill always costs entropy. Returns 0 when no weighted class
+/// is available.
+#[cfg(feature = "std")]
+pub fn weighted_fill_entropy<C: CharUnit>(char_set: &[C], weights: &ClassWeights) -> f64 {
+    let pools = CharClasses::from_char_set(char_set).weighted_pools(weights);
+    let total: f64 = pools.iter().map(|(weight, _)| *weight as f64).sum();
+    pools
+        .iter()
+        .map(|(weight, pool)| {
+            let p = *weight as f64 / total;
+            p * ((pool.len() as f64).log2() - p.log2())
+        })
+        .sum()
 }
 
-/// Arguments structure for password generation
-pub struct PasswordArgs {
-    pub capitals_off: bool,
-    pub numerals_off: bool,
-    pub symbols_off: bool,
-    pub exclude_chars: Vec<char>,
-    pub include_chars: Option<Vec<char>>,
-    pub min_capitals: Option<u32>,
-    pub min_numerals: Option<u32>,
-    pub min_symbols: Option<u32>,
-    pub pattern: Option<Vec<PatternChar>>,
-    pub length: u32,
-    pub password_count: u32,
+/// Default attacker speed for `crack_time`: an offline attack on a fast hash
+/// with a GPU rig
+pub const DEFAULT_GUESSES_PER_SECOND: f64 = 1e10;
+
+/// Estimates how long an attacker needs to guess a password, as a readable duration
+///
+/// Uses the expected case, where the password is found after searching half of
+/// the `2^entropy_bits` keyspace. The result is rounded down to the largest
+/// whole unit, e.g. "2 days" or "3 centuries".
+#[cfg(feature = "std")]
+pub fn crack_time(entropy_bits: f64, guesses_per_second: f64) -> String {
+    const UNITS: [(f64, &str, &str); 6] = [
+        (3_155_760_000.0, "century", "centuries"),
+        (31_557_600.0, "year", "years"),
+        (86_400.0, "day", "days"),
+        (3_600.0, "hour", "hours"),
+        (60.0, "minute", "minutes"),
+        (1.0, "second", "seconds"),
+    ];
+
+    let seconds = (entropy_bits - 1.0).exp2() / guesses_per_second;
+    for (unit_seconds, singular, plural) in UNITS {
+        if seconds >= unit_seconds {
+            let n = (seconds / unit_seconds).floor();
+            return if n >= 1_000_000.0 {
+                format!("over a million {}", plural)
+            } else if n == 1.0 {
+                format!("1 {}", singular)
+            } else {
+                format!("{} {}", n, plural)
+            };
+        }
+    }
+    "less than a second".to_string()
 }
 
 /// Parses character exclusion strings, expanding ranges like "a-z" or "0-9"
@@ -195,305 +240,1052 @@ pub fn parse_exclude_chars(exclude_strings: Vec<String>) -> Result<Vec<char>, St
     Ok(exclude_chars)
 }
 
-/// Builds the character set based on command-line arguments
-/// Returns a vector of valid characters that can be used for password generation
-pub fn build_char_set(args: &PasswordArgs) -> Result<Vec<u8>, PasswordError> {
-    let mut chars = Vec::new();
+/// Vowels in both cases
+pub const VOWELS: &[char] = &['a', 'e', 'i', 'o', 'u', 'A', 'E', 'I', 'O', 'U'];
 
-    // If include_chars is specified, use only those characters
-    if let Some(ref include_chars) = args.include_chars {
-        for &c in include_chars {
-            chars.push(c as u8);
+/// Opening and closing brackets of every kind
+pub const BRACKETS: &[char] = &['(', ')', '[', ']', '{', '}', '<', '>'];
+
+/// Quote characters
+pub const QUOTES: &[char] = &['\'', '"', '`'];
+
+/// Characters that cause trouble when pasted into shell commands or config files
+pub const SHELL_UNSAFE_CHARS: &[char] = &['$', '`', '\\', '"', '\'', ';', '|', '&', '(', ')'];
+
+/// Non-Latin letters that render like a Latin letter, paired with that letter
+///
+/// Curated from the Greek and Cyrillic letters `--script` can produce, after
+/// Unicode's confusables data. Only the first character of each pair is
+/// excluded by `--exclude-homoglyphs`; the Latin letter stays.
+pub const HOMOGLYPHS: &[(char, char)] = &[
+    // Greek capitals
+    ('Α', 'A'),
+    ('Β', 'B'),
+    ('Ε', 'E'),
+    ('Ζ', 'Z'),
+    ('Η', 'H'),
+    ('Ι', 'I'),
+    ('Κ', 'K'),
+    ('Μ', 'M'),
+    ('Ν', 'N'),
+    ('Ο', 'O'),
+    ('Ρ', 'P'),
+    ('Τ', 'T'),
+    ('Υ', 'Y'),
+    ('Χ', 'X'),
+    // Greek lowercase
+    ('ι', 'i'),
+    ('ν', 'v'),
+    ('ο', 'o'),
+    ('ρ', 'p'),
+    ('υ', 'u'),
+    // Cyrillic capitals
+    ('А', 'A'),
+    ('В', 'B'),
+    ('Е', 'E'),
+    ('З', '3'),
+    ('К', 'K'),
+    ('М', 'M'),
+    ('Н', 'H'),
+    ('О', 'O'),
+    ('Р', 'P'),
+    ('С', 'C'),
+    ('Т', 'T'),
+    ('У', 'Y'),
+    ('Х', 'X'),
+    // Cyrillic lowercase
+    ('а', 'a'),
+    ('е', 'e'),
+    ('о', 'o'),
+    ('р', 'p'),
+    ('с', 'c'),
+    ('у', 'y'),
+    ('х', 'x'),
+];
+
+/// The base64url alphabet (RFC 4648 §5), safe in URLs and file names
+pub const URL_SAFE_ALPHABET: &str =
+    "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Named character classes accepted by `parse_exclude_classes`
+pub const CHARACTER_CLASSES: &[(&str, &[char])] = &[
+    ("vowels", VOWELS),
+    ("brackets", BRACKETS),
+    ("quotes", QUOTES),
+];
+
+/// Expands named character classes (e.g. "vowels", "brackets") into characters to exclude
+///
+/// Names are matched case-insensitively against `CHARACTER_CLASSES`. Unknown
+/// names produce an error listing the valid ones.
+pub fn parse_exclude_classes(names: &[String]) -> Result<Vec<char>, String> {
+    let mut exclude_chars = Vec::new();
+
+    for name in names {
+        let class = CHARACTER_CLASSES
+            .iter()
+            .find(|(class_name, _)| class_name.eq_ignore_ascii_case(name.trim()));
+        match class {
+            Some((_, chars)) => {
+                for &c in *chars {
+                    if !exclude_chars.contains(&c) {
+                        exclude_chars.push(c);
+                    }
+                }
+            }
+            None => {
+                let valid: Vec<&str> = CHARACTER_CLASSES.iter().map(|(n, _)| *n).collect();
+                return Err(format!(
+                    "Unknown character class '{}'. Valid classes: {}",
+                    name,
+                    valid.join(", ")
+                ));
+            }
         }
-    } else {
-        // Pre-allocate with estimated capacity (max ~94 printable ASCII chars)
-        let estimated_capacity = if args.symbols_off {
-            62 // 26 lowercase + 26 uppercase + 10 numerals
-        } else {
-            94 // All printable ASCII
-        };
-        chars.reserve(estimated_capacity);
+    }
+
+    Ok(exclude_chars)
+}
+
+/// An example invocation, shown in `--help` and by `--help-examples`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(serde::Serialize))]
+pub struct Example {
+    /// The heading the example is listed under in `--help`
+    pub section: &'static str,
+    pub description: &'static str,
+    pub command: &'static str,
+}
+
+const fn example(
+    section: &'static str,
+    description: &'static str,
+    command: &'static str,
+) -> Example {
+    Example {
+        section,
+        description,
+        command,
+    }
+}
+
+/// Example invocations, grouped by section in display order
+pub const EXAMPLES: &[Example] = &[
+    example("Basic Usage", "Generate 5 passwords", "rpg 5"),
+    example(
+        "Basic Usage",
+        "Generate 10 passwords of length 20",
+        "rpg 10 --length 20",
+    ),
+    example(
+        "Basic Usage",
+        "Generate 25 passwords in table format",
+        "rpg 25 --format table",
+    ),
+    example(
+        "Character Customization",
+        "Generate without capital letters",
+        "rpg 5 --capitals-off",
+    ),
+    example(
+        "Character Customization",
+        "Only alphabetic characters",
+        "rpg 5 --numerals-off --symbols-off",
+    ),
+    example(
+        "Character Customization",
+        "Exclude ranges of characters",
+        "rpg 5 --exclude-chars a-z,0-9",
+    ),
+    example(
+        "Character Customization",
+        "Exclude specific characters",
+        "rpg 5 --exclude-chars a,b,c",
+    ),
+    example(
+        "Character Customization",
+        "Use only specified characters",
+        "rpg 5 --include-chars a-z,0-9",
+    ),
+    example(
+        "Advanced Features",
+        "Pattern-based generation",
+        "rpg 5 --pattern \"LLLNNNSSS\"",
+    ),
+    example(
+        "Advanced Features",
+        "Minimum capital letters",
+        "rpg 5 --min-capitals 2",
+    ),
+    example(
+        "Advanced Features",
+        "Minimum numerals",
+        "rpg 5 --min-numerals 3",
+    ),
+    example(
+        "Advanced Features",
+        "Reproducible passwords",
+        "rpg 5 --seed 12345",
+    ),
+    example("Advanced Features", "Copy to clipboard", "rpg 1 --copy"),
+    example("Advanced Features", "JSON output", "rpg 3 --format json"),
+];
+
+/// Widest table `column_count` will choose, to keep rows within a terminal
+const MAX_TABLE_COLUMNS: usize = 5;
+
+/// Calculates the number of columns for table output
+///
+/// Up to 3 passwords are printed in a single column. Beyond that, each column
+/// count from 2 to 5 is scored by twice the number of empty cells in the last
+/// row plus how far the layout is from square (`|rows - columns|`), and the
+/// lowest score wins. Ties go to fewer empty cells, then to more columns.
+///
+/// Primes never divide evenly, so they always leave a short last row; the
+/// score keeps it close to full (7 -> 4 columns as 4+3, 11 -> 4 columns as
+/// 4+4+3, 13 -> 5 columns as 5+5+3). Large counts are capped at 5 columns.
+pub fn column_count(password_count: u32) -> usize {
+    if password_count <= 3 {
+        return 1;
+    }
 
-        // Add lowercase letters (always included)
-        chars.extend(ASCII_LOWERCASE_START..=ASCII_LOWERCASE_END);
+    let count = password_count as usize;
+    (2..=MAX_TABLE_COLUMNS)
+        .min_by_key(|&columns| {
+            let rows = count.div_ceil(columns);
+            let empty = rows * columns - count;
+            // Empty cells count double, and reversing the column count makes
+            // ties prefer wider tables
+            (
+                2 * empty + rows.abs_diff(columns),
+                empty,
+                usize::MAX - columns,
+            )
+        })
+        .unwrap_or(1)
+}
 
-        // Add uppercase letters if not disabled
-        if !args.capitals_off {
-            chars.extend(ASCII_UPPERCASE_START..=ASCII_UPPERCASE_END);
+/// Picks `k` items uniformly at random from `items`, holding only `k` at a time
+///
+/// Reservoir sampling (Algorithm R): the first `k` items fill the reservoir,
+/// then item `i` (counting from 0) replaces a random slot with probability
+/// `k / (i + 1)`, one `random_range` draw per item after the first `k`. With
+/// fewer than `k` items, all of them are returned in order.
+pub fn reservoir_sample<T, R: Rng>(
+    items: impl IntoIterator<Item = T>,
+    k: usize,
+    rng: &mut R,
+) -> Vec<T> {
+    let mut reservoir = Vec::with_capacity(k);
+    for (i, item) in items.into_iter().enumerate() {
+        if i < k {
+            reservoir.push(item);
+        } else {
+            let j = rng.random_range(0..=i);
+            if j < k {
+                reservoir[j] = item;
+            }
         }
+    }
+    reservoir
+}
+
+/// A run of password positions that draw from the same pool
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntropyTerm {
+    pub label: String,
+    pub pool_size: usize,
+    pub positions: u32,
+}
+
+#[cfg(feature = "std")]
+impl EntropyTerm {
+    pub fn bits(&self) -> f64 {
+        calculate_entropy(self.pool_size, self.positions)
+    }
+}
+
+/// Breakdown of how the entropy of a password configuration is computed
+///
+/// The base keyspace is the sum of the `terms`. Each entry in `deductions`
+/// names a constraint and the bits it removes from that base.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntropyExplanation {
+    pub terms: Vec<EntropyTerm>,
+    pub deductions: Vec<(String, f64)>,
+    /// Number of distinct characters the positions draw from, all pools together
+    pub charset_size: usize,
+}
+
+#[cfg(feature = "std")]
+impl EntropyExplanation {
+    /// Entropy of the base keyspace before constraints
+    pub fn base_bits(&self) -> f64 {
+        self.terms.iter().map(EntropyTerm::bits).sum()
+    }
+
+    /// Final entropy after all deductions
+    pub fn total_bits(&self) -> f64 {
+        self.base_bits() - self.deductions.iter().map(|(_, bits)| bits).sum::<f64>()
+    }
+}
 
-        // Add numerals if not disabled
-        if !args.numerals_off {
-            chars.extend(ASCII_NUMERAL_START..=ASCII_NUMERAL_END);
+#[cfg(feature = "std")]
+impl fmt::Display for EntropyExplanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Entropy breakdown:")?;
+        for term in &self.terms {
+            writeln!(
+                f,
+                "  {}: {} x log2({}) = {:.2} bits",
+                term.label,
+                term.positions,
+                term.pool_size,
+                term.bits()
+            )?;
+        }
+        writeln!(f, "  Base keyspace: {:.2} bits", self.base_bits())?;
+        for (constraint, bits) in &self.deductions {
+            writeln!(f, "  {}: -{:.2} bits", constraint, bits)?;
         }
+        writeln!(f, "  Final: {:.2} bits", self.total_bits())
+    }
+}
+
+/// Explains the entropy of passwords generated with `char_set` and `params`
+///
+/// Patterns get one term per position, sized by the class pool it draws from.
+/// Otherwise every position draws from the full character set, and each
+/// required character is deducted as the difference between the full set and
+/// its class pool. This is conservative: the shuffle that scatters required
+/// characters adds back a little entropy that isn't counted. Re-roll
+/// constraints only remove a handful of candidates and aren't deducted.
+#[cfg(feature = "std")]
+pub fn explain_entropy<C: CharUnit>(
+    char_set: &[C],
+    params: &GenerationParams,
+) -> EntropyExplanation {
+    let classes = CharClasses::from_char_set(char_set);
+
+    if let Some(ref mask) = params.mask {
+        let terms = mask
+            .iter()
+            .enumerate()
+            .map(|(i, pool)| EntropyTerm {
+                label: format!("Position {} (mask)", i + 1),
+                pool_size: pool.len(),
+                positions: 1,
+            })
+            .collect();
+        let drawn: std::collections::BTreeSet<u8> = mask.iter().flatten().copied().collect();
+        return EntropyExplanation {
+            terms,
+            deductions: Vec::new(),
+            charset_size: drawn.len(),
+        };
+    }
+
+    if let Some(ref pattern) = params.pattern {
+        let mut drawn = std::collections::BTreeSet::new();
+        let terms = pattern
+            .iter()
+            .enumerate()
+            .map(|(i, &pat_char)| {
+                let (name, pool) = match pat_char {
+                    PatternChar::Lowercase => ("lowercase", &classes.lowercase),
+                    PatternChar::Uppercase => ("uppercase", &classes.uppercase),
+                    PatternChar::Numeric => ("numeric", &classes.numeric),
+                    PatternChar::Symbol => ("symbol", &classes.symbols),
+                };
+                // Mirrors the full-set fallback in generate_password_from_pattern
+                let pool = if pool.is_empty() { char_set } else { pool };
+                drawn.extend(pool.iter().copied());
+                EntropyTerm {
+                    label: format!("Position {} ({})", i + 1, name),
+                    pool_size: pool.len(),
+                    positions: 1,
+                }
+            })
+            .collect();
+        return EntropyExplanation {
+            terms,
+            deductions: Vec::new(),
+            charset_size: drawn.len(),
+        };
+    }
 
-        // Add symbols if not disabled (complete ASCII printable symbol ranges)
-        if !args.symbols_off {
-            chars.extend(ASCII_SYMBOL_RANGE_1_START..=ASCII_SYMBOL_RANGE_1_END);
-            chars.extend(ASCII_SYMBOL_RANGE_2_START..=ASCII_SYMBOL_RANGE_2_END);
-            chars.extend(ASCII_SYMBOL_RANGE_3_START..=ASCII_SYMBOL_RANGE_3_END);
-            chars.extend(ASCII_SYMBOL_RANGE_4_START..=ASCII_SYMBOL_RANGE_4_END);
+    let requirements = [
+        (params.min_capitals, &classes.uppercase, "capitals"),
+        (params.min_numerals, &classes.numeric, "numerals"),
+        (params.min_symbols, &classes.symbols, "symbols"),
+    ];
+
+    let mut placed = 0;
+    let mut deductions = Vec::new();
+    for (min, pool, name) in requirements {
+        let min = min.unwrap_or(0);
+        if min == 0 || pool.is_empty() {
+            continue;
         }
+        placed += min;
+        let bits = calculate_entropy(char_set.len(), min) - calculate_entropy(pool.len(), min);
+        deductions.push((
+            format!("Minimum {} {} (pool of {})", min, name, pool.len()),
+            bits,
+        ));
     }
 
-    // Convert exclude_chars Vec to HashSet for O(1) lookup
-    let exclude_set: HashSet<char> = args.exclude_chars.iter().cloned().collect();
+    if let Some(ref weights) = params.weights {
+        let fill = params.length.saturating_sub(placed) as f64;
+        let per_char =
+            calculate_entropy(char_set.len(), 1) - weighted_fill_entropy(char_set, weights);
+        deductions.push((
+            format!(
+                "Class weights {}:{}:{} (letters:digits:symbols)",
+                weights.letters, weights.digits, weights.symbols
+            ),
+            per_char * fill,
+        ));
+    }
 
-    // Filter out excluded characters
-    chars.retain(|&b| !exclude_set.contains(&(b as char)));
+    if params.all_unique_chars {
+        deductions.push((
+            "All characters unique".to_string(),
+            calculate_entropy(char_set.len(), params.length)
+                - unique_chars_entropy(char_set.len(), params.length),
+        ));
+    }
 
-    // Validate that we have at least one character available
-    if chars.is_empty() {
-        return Err(PasswordError::EmptyCharacterSet);
+    EntropyExplanation {
+        terms: vec![EntropyTerm {
+            label: "Any character".to_string(),
+            pool_size: char_set.len(),
+            // Minimums that exceed the length make the password longer
+            positions: params.length.max(placed),
+        }],
+        deductions,
+        charset_size: char_set.len(),
     }
+}
+
+/// Symbols that may end a memorable password (widely accepted by site policies)
+pub const MEMORABLE_SYMBOLS: &[u8] = b"!@#$%&*?";
 
-    Ok(chars)
+/// Parameters for memorable "Word + number + symbol" passwords
+#[derive(Debug, Clone)]
+pub struct MemorableParams {
+    /// Number of capitalized words from `WORDLIST`
+    pub words: u32,
+    /// Number of digits appended after the words
+    pub digits: u32,
+    /// Whether to append one symbol from `MEMORABLE_SYMBOLS`
+    pub symbol: bool,
 }
 
-/// Maximum allowed password length to prevent memory issues
-const MAX_PASSWORD_LENGTH: u32 = 10_000;
+impl Default for MemorableParams {
+    fn default() -> Self {
+        MemorableParams {
+            words: 2,
+            digits: 2,
+            symbol: true,
+        }
+    }
+}
 
-/// Validates command-line arguments
-pub fn validate_args(args: &PasswordArgs) -> Result<(), PasswordError> {
-    if args.length == 0 {
-        return Err(PasswordError::InvalidLength);
+/// Generates a memorable password such as `MapleOtter42!`
+///
+/// Each word is drawn from `WORDLIST` and capitalized, followed by the
+/// requested number of digits and an optional trailing symbol.
+pub fn generate_memorable<R: Rng>(params: &MemorableParams, rng: &mut R) -> String {
+    let mut pass = String::new();
+
+    for _ in 0..params.words {
+        let word = pick(&WORDLIST, rng);
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            pass.push(first.to_ascii_uppercase());
+            pass.push_str(chars.as_str());
+        }
     }
 
-    if args.length > MAX_PASSWORD_LENGTH {
-        return Err(PasswordError::InvalidLengthTooLong);
+    for _ in 0..params.digits {
+        pass.push(pick(b"0123456789", rng) as char);
     }
 
-    if args.password_count == 0 {
-        return Err(PasswordError::InvalidCount);
+    if params.symbol {
+        pass.push(pick(MEMORABLE_SYMBOLS, rng) as char);
     }
 
-    // Check if all character types are disabled
-    if args.capitals_off && args.numerals_off && args.symbols_off {
-        // Only lowercase letters remain, which is valid
-        // But we should check if they're all excluded
-        let test_set = build_char_set(args)?;
-        if test_set.is_empty() {
-            return Err(PasswordError::AllTypesDisabled);
-        }
+    pass
+}
+
+impl Generator for MemorableParams {
+    fn generate<R: Rng>(&self, rng: &mut R) -> String {
+        generate_memorable(self, rng)
     }
+}
 
-    Ok(())
+/// Calculates the entropy of a memorable password in bits
+///
+/// Only the random choices count: words contribute `log2(WORDLIST.len())`
+/// each, digits `log2(10)` each and the symbol `log2(MEMORABLE_SYMBOLS.len())`.
+/// Capitalization is fixed, so it adds nothing.
+#[cfg(feature = "std")]
+pub fn memorable_entropy(params: &MemorableParams) -> f64 {
+    let mut bits = calculate_entropy(WORDLIST.len(), params.words);
+    bits += calculate_entropy(10, params.digits);
+    if params.symbol {
+        bits += calculate_entropy(MEMORABLE_SYMBOLS.len(), 1);
+    }
+    bits
 }
 
-/// Calculates the number of columns for table output
-pub fn column_count(password_count: u32) -> usize {
-    // Use a more reasonable default: prefer 3-4 columns for readability
-    // but adapt based on count
-    match password_count {
-        1..=3 => 1,
-        4..=8 => 2,
-        9..=15 => 3,
-        16..=24 => 4,
-        _ => {
-            // For larger counts, use a divisor that makes sense
-            if password_count.is_multiple_of(5) {
-                5
-            } else if password_count.is_multiple_of(4) {
-                4
-            } else if password_count.is_multiple_of(3) {
-                3
-            } else if password_count.is_multiple_of(2) {
-                2
-            } else {
-                3 // Default to 3 columns for readability
+/// Shortest run of consecutive characters `find_sequences` reports
+pub const MIN_SEQUENCE_LENGTH: usize = 3;
+
+/// Finds runs of consecutive ascending or descending characters, like "abc" or "321"
+///
+/// Only runs of letters or digits count, compared case-insensitively, and
+/// each must be at least `MIN_SEQUENCE_LENGTH` long. Runs are returned in
+/// the order they appear.
+pub fn find_sequences(password: &str) -> Vec<String> {
+    let chars: Vec<char> = password.chars().collect();
+    let step = |a: char, b: char| -> i32 {
+        if !(a.is_ascii_alphanumeric() && b.is_ascii_alphanumeric())
+            || a.is_ascii_digit() != b.is_ascii_digit()
+        {
+            return 0;
+        }
+        let diff = b.to_ascii_lowercase() as i32 - a.to_ascii_lowercase() as i32;
+        if diff.abs() == 1 { diff } else { 0 }
+    };
+
+    let mut sequences = Vec::new();
+    let mut start = 0;
+    while start + 1 < chars.len() {
+        let direction = step(chars[start], chars[start + 1]);
+        let mut end = start + 1;
+        if direction != 0 {
+            while end + 1 < chars.len() && step(chars[end], chars[end + 1]) == direction {
+                end += 1;
+            }
+            if end - start + 1 >= MIN_SEQUENCE_LENGTH {
+                sequences.push(chars[start..=end].iter().collect());
             }
         }
+        start = end;
     }
+    sequences
 }
 
-/// Parses a pattern string like "LLLNNNSSS" into PatternChar vector
-pub fn parse_pattern(pattern: &str) -> Result<Vec<PatternChar>, String> {
-    let mut result = Vec::new();
-    for c in pattern.chars() {
-        match c {
-            'L' | 'l' => result.push(PatternChar::Lowercase),
-            'U' | 'u' => result.push(PatternChar::Uppercase),
-            'N' | 'n' => result.push(PatternChar::Numeric),
-            'S' | 's' => result.push(PatternChar::Symbol),
-            _ => {
-                return Err(format!(
-                    "Invalid pattern character: '{}'. Use L (lowercase), U (uppercase), N (numeric), S (symbol)",
-                    c
-                ));
-            }
+/// Returns true if any run of letters or digits forms a sequence (see `find_sequences`)
+pub fn contains_sequence(password: &str) -> bool {
+    !find_sequences(password).is_empty()
+}
+
+/// Returns true if the same character appears three or more times in a row
+pub fn contains_repeats(password: &str) -> bool {
+    password
+        .chars()
+        .collect::<Vec<_>>()
+        .windows(3)
+        .any(|w| w[0] == w[1] && w[1] == w[2])
+}
+
+/// Coarse strength rating derived from entropy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Strength {
+    VeryWeak,
+    Weak,
+    Reasonable,
+    Strong,
+    VeryStrong,
+}
+
+impl Strength {
+    /// Rates `entropy_bits`: under 28 is very weak, under 36 weak, under 60
+    /// reasonable, under 128 strong, and anything above very strong
+    pub fn from_entropy(entropy_bits: f64) -> Self {
+        match entropy_bits {
+            b if b < 28.0 => Strength::VeryWeak,
+            b if b < 36.0 => Strength::Weak,
+            b if b < 60.0 => Strength::Reasonable,
+            b if b < 128.0 => Strength::Strong,
+            _ => Strength::VeryStrong,
         }
     }
-    Ok(result)
 }
 
-/// Generates a password from a pattern
-fn generate_password_from_pattern<R: Rng>(
-    char_set: &[u8],
-    pattern: &[PatternChar],
-    rng: &mut R,
-) -> String {
-    let mut pass = String::with_capacity(pattern.len());
-
-    let lowercase: Vec<u8> = (ASCII_LOWERCASE_START..=ASCII_LOWERCASE_END)
-        .filter(|&b| char_set.contains(&b))
-        .collect();
-    let uppercase: Vec<u8> = (ASCII_UPPERCASE_START..=ASCII_UPPERCASE_END)
-        .filter(|&b| char_set.contains(&b))
-        .collect();
-    let numeric: Vec<u8> = (ASCII_NUMERAL_START..=ASCII_NUMERAL_END)
-        .filter(|&b| char_set.contains(&b))
-        .collect();
-    let symbols: Vec<u8> = char_set
-        .iter()
-        .filter(|&&b| {
-            !(ASCII_LOWERCASE_START..=ASCII_LOWERCASE_END).contains(&b)
-                && !(ASCII_UPPERCASE_START..=ASCII_UPPERCASE_END).contains(&b)
-                && !(ASCII_NUMERAL_START..=ASCII_NUMERAL_END).contains(&b)
-        })
-        .copied()
-        .collect();
-
-    for &pat_char in pattern {
-        let char_byte = match pat_char {
-            PatternChar::Lowercase => {
-                if lowercase.is_empty() {
-                    char_set[rng.random_range(0..char_set.len())]
-                } else {
-                    lowercase[rng.random_range(0..lowercase.len())]
-                }
-            }
-            PatternChar::Uppercase => {
-                if uppercase.is_empty() {
-                    char_set[rng.random_range(0..char_set.len())]
-                } else {
-                    uppercase[rng.random_range(0..uppercase.len())]
-                }
-            }
-            PatternChar::Numeric => {
-                if numeric.is_empty() {
-                    char_set[rng.random_range(0..char_set.len())]
-                } else {
-                    numeric[rng.random_range(0..numeric.len())]
-                }
-            }
-            PatternChar::Symbol => {
-                if symbols.is_empty() {
-                    char_set[rng.random_range(0..char_set.len())]
-                } else {
-                    symbols[rng.random_range(0..symbols.len())]
-                }
-            }
+impl fmt::Display for Strength {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Strength::VeryWeak => "very weak",
+            Strength::Weak => "weak",
+            Strength::Reasonable => "reasonable",
+            Strength::Strong => "strong",
+            Strength::VeryStrong => "very strong",
         };
-        pass.push(char_byte as char);
+        f.write_str(name)
     }
+}
 
-    pass
+/// Assessment of an existing password from `check_password`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PasswordCheck {
+    /// Length in characters
+    pub length: usize,
+    /// Combined size of the character classes the password uses
+    pub pool_size: usize,
+    pub entropy_bits: f64,
+    pub strength: Strength,
+    /// Sequences found by `find_sequences`
+    pub sequences: Vec<String>,
+    /// Whether `contains_repeats` holds
+    pub has_repeats: bool,
 }
 
-/// Generates a single password ensuring minimum character type requirements
-fn generate_password_with_minimums<R: Rng>(
-    char_set: &[u8],
-    length: u32,
-    min_capitals: Option<u32>,
-    min_numerals: Option<u32>,
-    min_symbols: Option<u32>,
-    rng: &mut R,
-) -> String {
-    let mut pass_vec: Vec<char> = Vec::with_capacity(length as usize);
-
-    // First, ensure minimum requirements are met
-
-    // Collect character sets for each type
-    let capitals: Vec<u8> = (ASCII_UPPERCASE_START..=ASCII_UPPERCASE_END)
-        .filter(|&b| char_set.contains(&b))
-        .collect();
-    let numerals: Vec<u8> = (ASCII_NUMERAL_START..=ASCII_NUMERAL_END)
-        .filter(|&b| char_set.contains(&b))
-        .collect();
-    let symbols: Vec<u8> = char_set
-        .iter()
-        .filter(|&&b| {
-            !(ASCII_LOWERCASE_START..=ASCII_LOWERCASE_END).contains(&b)
-                && !(ASCII_UPPERCASE_START..=ASCII_UPPERCASE_END).contains(&b)
-                && !(ASCII_NUMERAL_START..=ASCII_NUMERAL_END).contains(&b)
-        })
-        .copied()
-        .collect();
-
-    // Add required capitals
-    if let Some(min) = min_capitals {
-        for _ in 0..min {
-            if !capitals.is_empty() {
-                let idx = rng.random_range(0..capitals.len());
-                pass_vec.push(capitals[idx] as char);
-            }
-        }
+/// Scores an existing password by the character classes it draws from
+///
+/// The keyspace assumes each character was picked at random from every class
+/// that appears: 26 lowercase, 26 uppercase, 10 digits, and 32 symbols (any
+/// other character counts as a symbol). Sequences and repeats are reported
+/// but not deducted, so the entropy is an upper bound for human-chosen
+/// passwords.
+#[cfg(feature = "std")]
+pub fn check_password(password: &str) -> PasswordCheck {
+    let has = |f: fn(&char) -> bool| password.chars().any(|c| f(&c));
+    let pool_size = [
+        (has(char::is_ascii_lowercase), 26),
+        (has(char::is_ascii_uppercase), 26),
+        (has(char::is_ascii_digit), 10),
+        (has(|c| !c.is_ascii_alphanumeric()), 32),
+    ]
+    .iter()
+    .filter(|(present, _)| *present)
+    .map(|(_, size)| size)
+    .sum();
+
+    let length = password.chars().count();
+    let entropy_bits = if pool_size == 0 {
+        0.0
+    } else {
+        calculate_entropy(pool_size, length as u32)
+    };
+    PasswordCheck {
+        length,
+        pool_size,
+        entropy_bits,
+        strength: Strength::from_entropy(entropy_bits),
+        sequences: find_sequences(password),
+        has_repeats: contains_repeats(password),
     }
+}
 
-    // Add required numerals
-    if let Some(min) = min_numerals {
-        for _ in 0..min {
-            if !numerals.is_empty() {
-                let idx = rng.random_range(0..numerals.len());
-                pass_vec.push(numerals[idx] as char);
-            }
+/// Summary statistics over a batch of passwords, from `batch_stats`
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchStats {
+    pub count: usize,
+    /// Total characters across the batch
+    pub characters: usize,
+    /// Occurrences of each character, in character order
+    pub frequencies: alloc::collections::BTreeMap<char, usize>,
+    /// Per-password entropy estimates, as `check_password` computes them
+    pub min_entropy: f64,
+    pub mean_entropy: f64,
+    pub max_entropy: f64,
+    pub lowercase: usize,
+    pub uppercase: usize,
+    pub digits: usize,
+    /// Everything that isn't a letter or digit
+    pub symbols: usize,
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for BatchStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let percent = |n: usize| 100.0 * n as f64 / self.characters.max(1) as f64;
+        writeln!(
+            f,
+            "Batch statistics: {} passwords, {} characters",
+            self.count, self.characters
+        )?;
+        writeln!(
+            f,
+            "  Entropy (estimated): min {:.1}, mean {:.1}, max {:.1} bits",
+            self.min_entropy, self.mean_entropy, self.max_entropy
+        )?;
+        for (name, n) in [
+            ("Lowercase", self.lowercase),
+            ("Uppercase", self.uppercase),
+            ("Digits", self.digits),
+            ("Symbols", self.symbols),
+        ] {
+            writeln!(f, "  {}: {} ({:.1}%)", name, n, percent(n))?;
+        }
+        writeln!(f, "  Character frequencies:")?;
+        for (c, n) in &self.frequencies {
+            writeln!(f, "    {} {} ({:.2}%)", c, n, percent(*n))?;
         }
+        Ok(())
     }
+}
 
-    // Add required symbols
-    if let Some(min) = min_symbols {
-        for _ in 0..min {
-            if !symbols.is_empty() {
-                let idx = rng.random_range(0..symbols.len());
-                pass_vec.push(symbols[idx] as char);
+/// Computes character, class and entropy statistics for a batch
+///
+/// Meant for checking that exclusions and class weights behave as intended.
+/// Entropy is estimated from each password alone (see `check_password`), so
+/// it doesn't know which character set generated the batch. An empty batch
+/// reports zero entropy.
+#[cfg(feature = "std")]
+pub fn batch_stats(passwords: &[String]) -> BatchStats {
+    let mut stats = BatchStats {
+        count: passwords.len(),
+        characters: 0,
+        frequencies: alloc::collections::BTreeMap::new(),
+        min_entropy: f64::INFINITY,
+        mean_entropy: 0.0,
+        max_entropy: 0.0,
+        lowercase: 0,
+        uppercase: 0,
+        digits: 0,
+        symbols: 0,
+    };
+    let mut total_entropy = 0.0;
+    for pass in passwords {
+        for c in pass.chars() {
+            stats.characters += 1;
+            *stats.frequencies.entry(c).or_insert(0) += 1;
+            if c.is_lowercase() {
+                stats.lowercase += 1;
+            } else if c.is_uppercase() {
+                stats.uppercase += 1;
+            } else if c.is_ascii_digit() {
+                stats.digits += 1;
+            } else {
+                stats.symbols += 1;
             }
         }
+        let bits = check_password(pass).entropy_bits;
+        stats.min_entropy = stats.min_entropy.min(bits);
+        stats.max_entropy = stats.max_entropy.max(bits);
+        total_entropy += bits;
+    }
+    if passwords.is_empty() {
+        stats.min_entropy = 0.0;
+    } else {
+        stats.mean_entropy = total_entropy / passwords.len() as f64;
     }
+    stats
+}
+
+/// Derives the seed for password `index` (counting from 0) from `master`
+///
+/// Used by `--derive-seeds`, where each password gets its own RNG seeded with
+/// `derive_seed(seed, i)`, so password `i` doesn't depend on how many came
+/// before it. The derivation is SplitMix64's output function applied to
+/// `master + (index + 1) * 0x9E3779B97F4A7C15` (wrapping), which spreads
+/// neighbouring indices across the whole `u64` range. It is part of the
+/// seeded-output contract and won't change.
+pub fn derive_seed(master: u64, index: u64) -> u64 {
+    let mut z = master.wrapping_add(index.wrapping_add(1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Passwords per chunk in `generate_passwords_chunked` and `generate_passwords_parallel`
+pub const GENERATION_CHUNK: u32 = 10_000;
 
-    // Fill the rest randomly
-    while pass_vec.len() < length as usize {
-        let c_byte = char_set[rng.random_range(0..char_set.len())];
-        pass_vec.push(c_byte as char);
+/// Generates a batch in chunks of `GENERATION_CHUNK`, each from its own RNG
+///
+/// Chunk `i` is generated by `generate_passwords` with an `StdRng` seeded
+/// from `derive_seed(seed, i)`, so any chunk can be generated on its own and
+/// the batch is the same however the chunks are scheduled. This is the
+/// serial counterpart of `generate_passwords_parallel`, and its output is
+/// not the same as one `generate_passwords` call with a single RNG. With
+/// `unique`, passwords are only guaranteed distinct within a chunk.
+#[cfg(feature = "std")]
+pub fn generate_passwords_chunked<C: CharUnit>(
+    char_set: &[C],
+    params: &GenerationParams,
+    seed: u64,
+) -> Result<Vec<String>, PasswordError> {
+    let mut passwords = Vec::with_capacity(params.count as usize);
+    for index in 0..chunk_total(params.count) {
+        passwords.append(&mut generate_chunk(char_set, params, seed, index)?);
     }
+    Ok(passwords)
+}
 
-    // Shuffle to randomize positions
-    use rand::seq::SliceRandom;
-    pass_vec.shuffle(rng);
+/// Generates the same batch as `generate_passwords_chunked`, a chunk per thread
+///
+/// Chunks run on rayon's global thread pool and are joined in order, so the
+/// output for a seed doesn't depend on the number of threads.
+#[cfg(feature = "rayon")]
+pub fn generate_passwords_parallel<C: CharUnit + Sync>(
+    char_set: &[C],
+    params: &GenerationParams,
+    seed: u64,
+) -> Result<Vec<String>, PasswordError> {
+    use rayon::prelude::*;
+
+    let chunks = (0..chunk_total(params.count))
+        .into_par_iter()
+        .map(|index| generate_chunk(char_set, params, seed, index))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(chunks.concat())
+}
 
-    pass_vec.into_iter().collect()
+/// The number of chunks in a batch of `count`
+#[cfg(feature = "std")]
+fn chunk_total(count: u32) -> u32 {
+    count.div_ceil(GENERATION_CHUNK)
 }
 
-/// Generates passwords using the provided character set and RNG
-pub fn generate_passwords<R: Rng>(
-    char_set: &[u8],
+/// Generates chunk `index` of a batch for `generate_passwords_chunked`
+#[cfg(feature = "std")]
+fn generate_chunk<C: CharUnit>(
+    char_set: &[C],
     params: &GenerationParams,
-    rng: &mut R,
-) -> Vec<String> {
-    let mut passwords = Vec::with_capacity(params.count as usize);
+    seed: u64,
+    index: u32,
+) -> Result<Vec<String>, PasswordError> {
+    use rand::{SeedableRng, rngs::StdRng};
+
+    let done = index * GENERATION_CHUNK;
+    let params = GenerationParams {
+        count: (params.count - done).min(GENERATION_CHUNK),
+        ..params.clone()
+    };
+    let mut rng = StdRng::seed_from_u64(derive_seed(seed, u64::from(index)));
+    generate_passwords(char_set, &params, &mut rng)
+}
+
+/// Computes the Luhn (mod 10) check digit to append to `digits`
+///
+/// Starting from the rightmost digit, every other digit is doubled (with 9
+/// subtracted when the result exceeds 9) and everything is summed; the check
+/// digit brings the total up to a multiple of 10.
+///
+/// # Panics
+///
+/// Panics if `digits` contains anything other than ASCII digits.
+pub fn luhn_check_digit(digits: &str) -> char {
+    let sum: u32 = digits
+        .bytes()
+        .rev()
+        .enumerate()
+        .map(|(i, b)| {
+            assert!(b.is_ascii_digit(), "luhn_check_digit requires ASCII digits");
+            let d = u32::from(b - b'0');
+            if i % 2 == 0 {
+                let doubled = d * 2;
+                if doubled > 9 { doubled - 9 } else { doubled }
+            } else {
+                d
+            }
+        })
+        .sum();
+    char::from(b'0' + ((10 - sum % 10) % 10) as u8)
+}
+
+/// Returns the lowercase hex SHA-256 digest of a password
+///
+/// This is a plain, unsalted fast hash, meant for fixtures and lookup tables.
+/// It is not a substitute for a password hashing function such as bcrypt or
+/// Argon2 when storing credentials.
+#[cfg(feature = "hash")]
+pub fn sha256_hex(password: &str) -> String {
+    use core::fmt::Write;
+    use sha2::{Digest, Sha256};
+
+    Sha256::digest(password.as_bytes())
+        .iter()
+        .fold(String::with_capacity(64), |mut hex, byte| {
+            let _ = write!(hex, "{:02x}", byte);
+            hex
+        })
+}
+
+/// HMAC-SHA256 (RFC 2104) of `message` under `key`
+#[cfg(feature = "hash")]
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    use hmac::{Hmac, Mac};
+
+    let mut mac =
+        Hmac::<sha2::Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(message);
+    mac.finalize().into_bytes().into()
+}
+
+/// Derives a 32-byte RNG seed from a numeric seed and a secret pepper
+///
+/// The result is HMAC-SHA256 keyed with `pepper` over the seed's 8
+/// little-endian bytes. The same seed and pepper always give the same RNG
+/// seed, and without the pepper the seed alone says nothing about it. Anyone
+/// who learns both can regenerate every password, and a short or guessable
+/// pepper adds little, since an attacker with the seed can try peppers.
+#[cfg(feature = "hash")]
+pub fn peppered_seed(seed: u64, pepper: &str) -> [u8; 32] {
+    hmac_sha256(pepper.as_bytes(), &seed.to_le_bytes())
+}
+
+/// PBKDF2-HMAC-SHA256 iterations used by `account_seed`
+///
+/// OWASP's 2023 recommendation for PBKDF2-SHA256. Changing it changes every
+/// account password, so it is fixed rather than configurable.
+#[cfg(feature = "hash")]
+pub const ACCOUNT_KDF_ITERATIONS: u32 = 600_000;
+
+/// PBKDF2 (RFC 8018) with HMAC-SHA256, producing one 32-byte block
+#[cfg(feature = "hash")]
+fn pbkdf2_hmac_sha256(password: &[u8], salt: &[u8], iterations: u32) -> [u8; 32] {
+    pbkdf2::pbkdf2_hmac_array::<sha2::Sha256, 32>(password, salt, iterations)
+}
+
+/// Derives a 32-byte RNG seed for `account` from a master secret
+///
+/// The seed is PBKDF2-HMAC-SHA256 of `master` with the salt
+/// `rpg-account:` followed by the account name, over
+/// `ACCOUNT_KDF_ITERATIONS` iterations. The same pair always gives the same
+/// seed, so passwords can be regenerated instead of stored, and distinct
+/// accounts get unrelated seeds. Everything rests on the master secret:
+/// anyone who learns it can derive the password for any account name, and
+/// the iterations only slow down guessing a weak one.
+#[cfg(feature = "hash")]
+pub fn account_seed(master: &str, account: &str) -> [u8; 32] {
+    let salt = [b"rpg-account:", account.as_bytes()].concat();
+    pbkdf2_hmac_sha256(master.as_bytes(), &salt, ACCOUNT_KDF_ITERATIONS)
+}
+
+/// Hashes a password with bcrypt, returning the standard `$2b$` string
+///
+/// `cost` is the log2 work factor (4 to 31; `bcrypt::DEFAULT_COST` is 12).
+/// The salt comes from the OS RNG, so output differs between runs even with a
+/// seeded generator. Passwords over bcrypt's 72-byte limit are rejected rather
+/// than silently truncated.
+#[cfg(feature = "bcrypt")]
+pub fn bcrypt_hash(password: &str, cost: u32) -> Result<String, String> {
+    bcrypt::non_truncating_hash(password, cost).map_err(|e| e.to_string())
+}
+
+/// Hashes a password with Argon2id, returning a PHC-format string
+///
+/// `t_cost` is the number of iterations; memory and parallelism use the
+/// `argon2` crate defaults. The 16-byte salt comes straight from the OS
+/// entropy source, never from a seeded generator; if that source fails the
+/// error is returned rather than panicking.
+#[cfg(feature = "argon2")]
+pub fn argon2_hash(password: &str, t_cost: u32) -> Result<String, String> {
+    use argon2::password_hash::{PasswordHasher, SaltString};
+    use argon2::{Algorithm, Argon2, Params, Version};
+    use rand::TryRngCore;
+
+    let params = Params::new(Params::DEFAULT_M_COST, t_cost, Params::DEFAULT_P_COST, None)
+        .map_err(|e| e.to_string())?;
+    let mut salt_bytes = [0u8; 16];
+    rand::rngs::OsRng
+        .try_fill_bytes(&mut salt_bytes)
+        .map_err(|e| e.to_string())?;
+    let salt = SaltString::encode_b64(&salt_bytes).map_err(|e| e.to_string())?;
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| e.to_string())
+}
+
+/// Quotes a value for safe use in POSIX shell `eval`/`source`
+///
+/// Values made only of ASCII alphanumerics are returned as-is. Anything else
+/// is wrapped in single quotes, with embedded single quotes written as `'\''`.
+pub fn shell_quote(value: &str) -> String {
+    if !value.is_empty() && value.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return value.to_string();
+    }
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// ANSI color for letters in `colorize` output (cyan)
+pub const LETTER_COLOR: &str = "\x1b[36m";
+/// ANSI color for digits in `colorize` output (yellow)
+pub const DIGIT_COLOR: &str = "\x1b[33m";
+/// ANSI color for symbols in `colorize` output (magenta)
+pub const SYMBOL_COLOR: &str = "\x1b[35m";
+/// Resets the color after each run in `colorize` output
+pub const COLOR_RESET: &str = "\x1b[0m";
 
-    for _ in 0..params.count {
-        let pass = if let Some(ref pat) = params.pattern {
-            generate_password_from_pattern(char_set, pat, rng)
+/// Wraps each run of letters, digits and symbols in its ANSI color
+///
+/// Makes it easy to tell `l`, `1` and `|` or `O` and `0` apart on screen.
+/// Whitespace is left uncolored. Only for display: the escapes are not part
+/// of the password.
+pub fn colorize(password: &str) -> String {
+    let color = |c: char| {
+        if c.is_whitespace() {
+            None
+        } else if c.is_alphabetic() {
+            Some(LETTER_COLOR)
+        } else if c.is_ascii_digit() {
+            Some(DIGIT_COLOR)
         } else {
-            generate_password_with_minimums(
-                char_set,
-                params.length,
-                params.min_capitals,
-                params.min_numerals,
-                params.min_symbols,
-                rng,
-            )
-        };
-        passwords.push(pass);
+            Some(SYMBOL_COLOR)
+        }
+    };
+    let mut out = String::with_capacity(password.len() * 4);
+    let mut current = None;
+    for c in password.chars() {
+        let next = color(c);
+        if next != current {
+            if current.is_some() {
+                out.push_str(COLOR_RESET);
+            }
+            if let Some(code) = next {
+                out.push_str(code);
+            }
+            current = next;
+        }
+        out.push(c);
+    }
+    if current.is_some() {
+        out.push_str(COLOR_RESET);
+    }
+    out
+}
+
+/// Number of characters `text` takes on screen, skipping ANSI color escapes
+pub fn display_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip to the end of the `ESC [ ... m` sequence
+            chars.by_ref().find(|&c| c == 'm');
+        } else {
+            width += 1;
+        }
     }
+    width
+}
 
-    passwords
+/// Pads `text` with spaces to `width` on-screen characters
+fn pad_to_width(out: &mut String, text: &str, width: usize) {
+    out.push_str(text);
+    for _ in display_width(text)..width {
+        out.push(' ');
+    }
 }
 
-/// Prints passwords in column format
-pub fn print_columns(passwords: Vec<String>, column_count: usize, show_header: bool) {
+/// Formats passwords in column format, returning the text that `print_columns` prints
+pub fn format_columns(passwords: &[String], column_count: usize, show_header: bool) -> String {
+    use core::fmt::Write;
+
+    let mut out = String::new();
     if show_header {
-        println!(
+        let _ = writeln!(
+            out,
             "Printing {} passwords in {} columns",
             passwords.len(),
             column_count
@@ -503,42 +1295,187 @@ pub fn print_columns(passwords: Vec<String>, column_count: usize, show_header: b
     if column_count == 1 {
         // Simple one-per-line output
         for pass in passwords {
-            println!("{}", pass);
+            let _ = writeln!(out, "{}", pass);
         }
-        return;
+        return out;
     }
 
-    // Calculate column width for alignment
-    let max_width = passwords.iter().map(|p| p.len()).max().unwrap_or(0).max(1);
+    // Calculate column width for alignment; colored passwords pad by what shows
+    let max_width = passwords
+        .iter()
+        .map(|p| display_width(p))
+        .max()
+        .unwrap_or(0)
+        .max(1);
 
     let mut col = 0;
     for pass in passwords {
-        print!("{:<width$}", pass, width = max_width);
+        pad_to_width(&mut out, pass, max_width);
         col += 1;
         if col == column_count {
             col = 0;
-            println!();
+            out.push('\n');
         } else {
-            print!(" ");
+            out.push(' ');
         }
     }
     // Add trailing newline if last row is incomplete
     if col != 0 {
-        println!();
+        out.push('\n');
     }
+
+    out
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Formats passwords one per line with their entropy in a trailing column
+///
+/// `entropy_bits[i]` belongs to `passwords[i]`. Passwords are padded to a
+/// common width so the bits line up, e.g. `Xy8$k2   94.3 bits`.
+pub fn format_with_entropy(passwords: &[String], entropy_bits: &[f64]) -> String {
+    use core::fmt::Write;
 
-    fn create_test_args(
-        capitals_off: bool,
-        numerals_off: bool,
+    let max_width = passwords
+        .iter()
+        .map(|p| display_width(p))
+        .max()
+        .unwrap_or(0);
+    let mut out = String::new();
+    for (pass, bits) in passwords.iter().zip(entropy_bits) {
+        pad_to_width(&mut out, pass, max_width);
+        let _ = writeln!(out, "   {:.1} bits", bits);
+    }
+    out
+}
+
+/// Fills an output template for one password
+///
+/// Supported placeholders are `{password}`, `{n}` (the 1-based `index`),
+/// `{entropy}` (bits, one decimal) and `{length}` (in characters). `{{` and
+/// `}}` produce literal braces. Unknown placeholders and unbalanced braces are
+/// errors.
+pub fn apply_template(
+    template: &str,
+    password: &str,
+    index: usize,
+    entropy_bits: f64,
+) -> Result<String, String> {
+    let mut out = String::with_capacity(template.len() + password.len());
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                out.push('{');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(format!("Unclosed placeholder '{{{}'", name)),
+                    }
+                }
+                match name.as_str() {
+                    "password" => out.push_str(password),
+                    "n" => out.push_str(&index.to_string()),
+                    "entropy" => out.push_str(&format!("{:.1}", entropy_bits)),
+                    "length" => out.push_str(&password.chars().count().to_string()),
+                    _ => {
+                        return Err(format!(
+                            "Unknown placeholder '{{{}}}'. Valid placeholders: {{password}}, {{n}}, {{entropy}}, {{length}}",
+                            name
+                        ));
+                    }
+                }
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                out.push('}');
+            }
+            '}' => return Err("Unmatched '}' (use '}}' for a literal brace)".to_string()),
+            _ => out.push(c),
+        }
+    }
+
+    Ok(out)
+}
+
+/// Prints passwords in column format
+#[cfg(feature = "std")]
+pub fn print_columns(passwords: Vec<String>, column_count: usize, show_header: bool) {
+    use std::io::Write;
+
+    let text = format_columns(&passwords, column_count, show_header);
+    // Unlike print!, a closed pipe (e.g. `| head`) ends the listing without a panic
+    let _ = std::io::stdout().lock().write_all(text.as_bytes());
+}
+
+/// Prints passwords in column format as they arrive, without collecting them
+///
+/// `print_columns` scans the whole batch for the widest password before
+/// printing anything. This pads every column to `width_hint` instead, so it
+/// matches `print_columns` (without the header, which needs the count) when
+/// all passwords are `width_hint` characters long, the usual case. A longer
+/// password still prints in full and pushes the rest of its row right.
+#[cfg(feature = "std")]
+pub fn print_columns_streaming(
+    passwords: impl Iterator<Item = String>,
+    column_count: usize,
+    width_hint: usize,
+) {
+    let stdout = std::io::stdout();
+    // Output errors such as a closed pipe end the listing, like print! would
+    let _ = write_columns_streaming(&mut stdout.lock(), passwords, column_count, width_hint);
+}
+
+/// Writes the `print_columns_streaming` output to `out`
+#[cfg(feature = "std")]
+pub(crate) fn write_columns_streaming<W: std::io::Write>(
+    out: &mut W,
+    passwords: impl Iterator<Item = String>,
+    column_count: usize,
+    width_hint: usize,
+) -> std::io::Result<()> {
+    let column_count = column_count.max(1);
+    let width = width_hint.max(1);
+    let mut col = 0;
+    for pass in passwords {
+        if column_count == 1 {
+            writeln!(out, "{}", pass)?;
+            continue;
+        }
+        write!(out, "{:<width$}", pass, width = width)?;
+        col += 1;
+        if col == column_count {
+            col = 0;
+            writeln!(out)?;
+        } else {
+            write!(out, " ")?;
+        }
+    }
+    // Add trailing newline if last row is incomplete
+    if col != 0 {
+        writeln!(out)?;
+    }
+    out.flush()
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::generator::{generate_password_from_pattern, generate_password_with_minimums};
+    use std::collections::{BTreeSet, HashSet};
+
+    fn create_test_args(
+        capitals_off: bool,
+        numerals_off: bool,
         symbols_off: bool,
         exclude_chars: Vec<char>,
     ) -> PasswordArgs {
         PasswordArgs {
+            lowercase_off: false,
             capitals_off,
             numerals_off,
             symbols_off,
@@ -547,9 +1484,19 @@ mod tests {
             min_capitals: None,
             min_numerals: None,
             min_symbols: None,
+            max_capitals: None,
+            max_numerals: None,
+            max_symbols: None,
             pattern: None,
             length: 16,
             password_count: 1,
+            max_length: None,
+            allow_space: false,
+            safe_symbols: false,
+            symbols: None,
+            include_back: Vec::new(),
+            max_repeats: None,
+            exclude_case_insensitive: false,
         }
     }
 
@@ -591,6 +1538,205 @@ mod tests {
         assert!(!char_set.contains(&b'@'));
     }
 
+    /// Golden test for `charset_version`: seeded output depends on this exact
+    /// order, so a change here must come with a version bump
+    #[test]
+    fn test_default_char_set_order() {
+        let char_set = build_char_set(&create_test_args(false, false, false, vec![])).unwrap();
+        assert_eq!(
+            String::from_utf8(char_set).unwrap(),
+            "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789\
+             !\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~"
+        );
+        assert_eq!(charset_version(), 1);
+    }
+
+    #[test]
+    fn test_generate_passwords_max_repeats() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        // Three characters make runs of three common in 40-character passwords
+        let params = GenerationParams {
+            length: 40,
+            count: 50,
+            max_repeats: Some(2),
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(370);
+        let passwords = generate_passwords(b"abc", &params, &mut rng).unwrap();
+        for pass in &passwords {
+            assert!(
+                !["aaa", "bbb", "ccc"].iter().any(|run| pass.contains(run)),
+                "{pass}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_policy_validation() {
+        let policy = Policy::from_json(
+            r#"{"min_length": 12, "required_classes": ["capitals", "symbols"], "max_repeats": 2}"#,
+        )
+        .unwrap();
+        assert!(policy.requires(PolicyClass::Symbols));
+        assert!(!policy.requires(PolicyClass::Lowercase));
+
+        let err = Policy::from_json(r#"{"min_length": 20, "max_length": 10}"#).unwrap_err();
+        assert!(err.contains("greater than max_length"), "{err}");
+        let err = Policy::from_json(
+            r#"{"max_length": 2, "required_classes": ["lowercase", "capitals", "numerals"]}"#,
+        )
+        .unwrap_err();
+        assert!(err.contains("3 classes are required"), "{err}");
+        assert!(Policy::from_json(r#"{"max_repeats": 0}"#).is_err());
+        assert!(Policy::from_json(r#"{"min_lenght": 8}"#).is_err());
+        assert!(Policy::from_json(r#"{"required_classes": ["emoji"]}"#).is_err());
+    }
+
+    #[test]
+    fn test_policy_apply() {
+        let policy = Policy::from_json(
+            r#"{"min_length": 20, "max_length": 30, "required_classes": ["numerals"], "forbidden_chars": "0O", "max_repeats": 3}"#,
+        )
+        .unwrap();
+
+        let mut args = create_test_args(false, false, false, vec![]);
+        args.max_repeats = Some(5);
+        policy.apply(&mut args, false).unwrap();
+        assert_eq!(args.length, 20);
+        assert_eq!(args.min_numerals, Some(1));
+        assert!(args.exclude_chars.contains(&'0') && args.exclude_chars.contains(&'O'));
+        assert_eq!(args.max_repeats, Some(3));
+
+        // Contradictions with the command line are errors, not silent overrides
+        let mut args = create_test_args(false, false, false, vec![]);
+        assert!(
+            policy
+                .apply(&mut args, true)
+                .unwrap_err()
+                .contains("outside")
+        );
+        let mut args = create_test_args(false, true, false, vec![]);
+        let err = policy.apply(&mut args, false).unwrap_err();
+        assert!(err.contains("--numerals-off"), "{err}");
+        let mut args = create_test_args(false, false, false, vec![]);
+        args.include_chars = Some(vec!['a', 'b', '0']);
+        let err = policy.apply(&mut args, false).unwrap_err();
+        assert!(err.contains("requires numerals"), "{err}");
+    }
+
+    #[test]
+    fn test_validate_args_minimum_unavailable() {
+        let mut args = create_test_args(false, false, false, vec![]);
+        args.include_chars = Some(vec!['a', 'b']);
+        args.min_symbols = Some(2);
+        let err = validate_args(&args).unwrap_err();
+        assert!(matches!(
+            err,
+            PasswordError::MinimumUnavailable {
+                class: "symbols",
+                min: 2,
+                included: true,
+            }
+        ));
+        assert!(err.to_string().contains("included characters have none"));
+
+        // Exclusions that remove a whole class hit the same check
+        let mut args = create_test_args(false, false, false, ('0'..='9').collect());
+        args.min_numerals = Some(1);
+        assert!(matches!(
+            validate_args(&args),
+            Err(PasswordError::MinimumUnavailable {
+                class: "numerals",
+                included: false,
+                ..
+            })
+        ));
+
+        // Satisfiable and zero minimums still pass
+        args.min_numerals = Some(0);
+        args.min_capitals = Some(3);
+        assert!(validate_args(&args).is_ok());
+    }
+
+    #[test]
+    fn test_build_char_set_include_back() {
+        let exclude: Vec<char> = ('a'..='z').collect();
+        let mut args = create_test_args(false, false, false, exclude);
+        args.include_back = vec!['m', 'n', 'm'];
+        let char_set = build_char_set(&args).unwrap();
+        assert!(char_set.contains(&b'm'));
+        assert!(char_set.contains(&b'n'));
+        assert!(!char_set.contains(&b'a'));
+        assert_eq!(char_set.len(), 26 + 10 + 32 + 2);
+
+        args.include_back = vec!['é'];
+        assert!(matches!(
+            build_char_set(&args),
+            Err(PasswordError::NonAsciiCharacter { ch: 'é' })
+        ));
+        args.include_back = vec![' '];
+        assert!(matches!(
+            build_char_set(&args),
+            Err(PasswordError::SpaceNotAllowed)
+        ));
+    }
+
+    #[test]
+    fn test_build_char_set_safe_symbols() {
+        let mut args = create_test_args(false, false, false, vec!['&']);
+        args.safe_symbols = true;
+        let char_set = build_char_set(&args).unwrap();
+        let symbols: Vec<u8> = char_set
+            .iter()
+            .copied()
+            .filter(|b| b.is_ascii_punctuation())
+            .collect();
+        assert_eq!(symbols, b"!@#$%^*");
+        assert_eq!(char_set.len(), 62 + 7);
+    }
+
+    #[test]
+    fn test_password_args_display() {
+        let mut args = create_test_args(false, false, true, vec!['l', '1']);
+        args.min_capitals = Some(2);
+        args.pattern = Some(vec![PatternChar::Uppercase, PatternChar::Numeric]);
+        let shown = args.to_string();
+        assert!(shown.contains("length: 16\n"), "{shown}");
+        assert!(shown.contains("symbols: off\n"), "{shown}");
+        assert!(shown.contains("capitals: on\n"), "{shown}");
+        assert!(shown.contains("exclude: \"l1\"\n"), "{shown}");
+        assert!(shown.contains("min capitals: 2\n"), "{shown}");
+        assert!(shown.contains("pattern: UN\n"), "{shown}");
+        assert!(!shown.contains("max"), "{shown}");
+
+        args.include_chars = Some(vec!['a', 'b']);
+        let shown = args.to_string();
+        assert!(shown.contains("include: \"ab\"\n"), "{shown}");
+        assert!(!shown.contains("symbols"), "{shown}");
+    }
+
+    #[test]
+    fn test_build_char_set_inline_symbols() {
+        let mut args = create_test_args(false, false, false, vec!['-']);
+        args.symbols = Some(vec!['_', '-', '.', '_']);
+        let char_set = build_char_set(&args).unwrap();
+        let symbols: Vec<u8> = char_set
+            .iter()
+            .copied()
+            .filter(|b| !b.is_ascii_alphanumeric())
+            .collect();
+        assert_eq!(symbols, b"_.");
+
+        for bad in ['a', '7', ' ', '\t', 'é'] {
+            args.symbols = Some(vec!['!', bad]);
+            assert!(matches!(
+                build_char_set(&args),
+                Err(PasswordError::InvalidSymbol { ch }) if ch == bad
+            ));
+        }
+    }
+
     #[test]
     fn test_build_char_set_with_exclusions() {
         let args = create_test_args(false, false, false, vec!['a', 'b', 'c']);
@@ -643,22 +1789,73 @@ mod tests {
         assert!(matches!(result.unwrap_err(), PasswordError::InvalidCount));
     }
 
+    #[test]
+    fn test_examples() {
+        assert!(!EXAMPLES.is_empty());
+        for example in EXAMPLES {
+            assert!(example.command.starts_with("rpg "), "{}", example.command);
+            assert!(!example.description.is_empty());
+        }
+        // Sections stay contiguous so --help can print each heading once
+        let mut sections: Vec<&str> = EXAMPLES.iter().map(|e| e.section).collect();
+        sections.dedup();
+        let mut unique = sections.clone();
+        unique.sort_unstable();
+        unique.dedup();
+        assert_eq!(sections.len(), unique.len());
+    }
+
+    #[test]
+    fn test_reservoir_sample() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let items: Vec<u32> = (0..1000).collect();
+        let sample = reservoir_sample(items.iter().copied(), 10, &mut StdRng::seed_from_u64(383));
+        assert_eq!(sample.len(), 10);
+        let distinct: BTreeSet<u32> = sample.iter().copied().collect();
+        assert_eq!(distinct.len(), 10);
+        assert!(sample.iter().all(|item| items.contains(item)));
+
+        // Seeded runs are reproducible
+        let again = reservoir_sample(items.iter().copied(), 10, &mut StdRng::seed_from_u64(383));
+        assert_eq!(again, sample);
+
+        // Fewer items than k returns them all
+        let mut rng = StdRng::seed_from_u64(383);
+        assert_eq!(reservoir_sample(0..3, 10, &mut rng), [0, 1, 2]);
+        assert!(reservoir_sample(0..3, 0, &mut rng).is_empty());
+
+        // Every item is about equally likely to be picked
+        let mut hits = [0u32; 10];
+        for _ in 0..10_000 {
+            for i in reservoir_sample(0..10, 3, &mut rng) {
+                hits[i] += 1;
+            }
+        }
+        assert!(
+            hits.iter().all(|&h| (2_700..3_300).contains(&h)),
+            "{hits:?}"
+        );
+    }
+
     #[test]
     fn test_column_count() {
         assert_eq!(column_count(1), 1);
         assert_eq!(column_count(2), 1);
         assert_eq!(column_count(3), 1);
         assert_eq!(column_count(4), 2);
-        assert_eq!(column_count(5), 2);
-        assert_eq!(column_count(6), 2);
+        assert_eq!(column_count(5), 3);
+        assert_eq!(column_count(6), 3);
         assert_eq!(column_count(9), 3);
-        assert_eq!(column_count(10), 3);
+        assert_eq!(column_count(10), 5);
+        assert_eq!(column_count(12), 4);
         assert_eq!(column_count(16), 4);
-        assert_eq!(column_count(20), 4);
+        assert_eq!(column_count(20), 5);
         assert_eq!(column_count(25), 5);
     }
 
     #[test]
+    #[allow(clippy::manual_range_contains)]
     fn test_column_count_large() {
         // Test that large numbers default to reasonable values
         let cols = column_count(100);
@@ -722,6 +1919,66 @@ mod tests {
         assert!(result.unwrap_err().contains("Invalid range"));
     }
 
+    #[test]
+    fn test_parse_exclude_classes_vowels() {
+        let chars = parse_exclude_classes(&["vowels".to_string()]).unwrap();
+        assert_eq!(chars.len(), 10);
+
+        let args = create_test_args(false, false, false, chars);
+        let char_set = build_char_set(&args).unwrap();
+        for v in b"aeiouAEIOU" {
+            assert!(!char_set.contains(v));
+        }
+        assert!(char_set.contains(&b'b'));
+        assert!(char_set.contains(&b'B'));
+    }
+
+    #[test]
+    fn test_parse_exclude_classes_brackets() {
+        let chars = parse_exclude_classes(&["brackets".to_string(), "Quotes".to_string()]).unwrap();
+
+        let args = create_test_args(false, false, false, chars);
+        let char_set = build_char_set(&args).unwrap();
+        for b in b"()[]{}<>'\"`" {
+            assert!(!char_set.contains(b));
+        }
+        assert!(char_set.contains(&b'!'));
+    }
+
+    #[test]
+    fn test_parse_exclude_classes_unknown() {
+        let err = parse_exclude_classes(&["consonants".to_string()]).unwrap_err();
+        assert!(err.contains("Unknown character class 'consonants'"));
+        assert!(err.contains("vowels, brackets, quotes"));
+    }
+
+    #[test]
+    fn test_shannon_entropy() {
+        // A perfectly uniform sample of n values carries exactly log2(n) bits
+        let uniform: Vec<u8> = (0..64u8).cycle().take(64 * 100).collect();
+        assert!((shannon_entropy(&uniform) - 6.0).abs() < 1e-9);
+
+        // A generated sample comes close
+        use rand::{SeedableRng, rngs::StdRng};
+        let char_set = build_char_set(&create_test_args(false, false, false, vec![])).unwrap();
+        let params = GenerationParams {
+            length: 100,
+            count: 1_000,
+            ..Default::default()
+        };
+        let passwords =
+            generate_passwords(&char_set, &params, &mut StdRng::seed_from_u64(384)).unwrap();
+        let bytes: Vec<u8> = passwords.concat().into_bytes();
+        let expected = (char_set.len() as f64).log2();
+        assert!((shannon_entropy(&bytes) - expected).abs() < 0.01);
+
+        // A constant sample has none, and a biased one less than uniform
+        assert_eq!(shannon_entropy(b"aaaa"), 0.0);
+        assert!((shannon_entropy(b"aabb") - 1.0).abs() < 1e-9);
+        assert!(shannon_entropy(b"aaab") < 1.0);
+        assert_eq!(shannon_entropy::<u8>(&[]), 0.0);
+    }
+
     #[test]
     fn test_calculate_entropy() {
         // Test with different character set sizes and lengths
@@ -739,6 +1996,25 @@ mod tests {
         assert!(entropy4 > entropy2);
     }
 
+    #[test]
+    fn test_length_for_entropy() {
+        // 6 bits per character, so exact fits don't round up
+        assert_eq!(length_for_entropy(64, 96.0), Some(16));
+        assert_eq!(length_for_entropy(64, 128.0), Some(22));
+        assert_eq!(length_for_entropy(94, 128.0), Some(20));
+        for size in [2, 10, 26, 62, 94] {
+            for bits in [1.0, 40.0, 64.0, 128.0, 256.0] {
+                let length = length_for_entropy(size, bits).unwrap();
+                assert!(calculate_entropy(size, length) >= bits);
+                assert!(calculate_entropy(size, length - 1) < bits);
+            }
+        }
+
+        assert_eq!(length_for_entropy(1, 8.0), None);
+        assert_eq!(length_for_entropy(0, 8.0), None);
+        assert_eq!(length_for_entropy(1, 0.0), Some(0));
+    }
+
     #[test]
     fn test_password_error_display() {
         let err1 = PasswordError::InvalidLength;
@@ -747,7 +2023,7 @@ mod tests {
                 .contains("Password length must be greater than 0")
         );
 
-        let err2 = PasswordError::InvalidLengthTooLong;
+        let err2 = PasswordError::InvalidLengthTooLong { max: 10_000 };
         assert!(err2.to_string().contains("exceeds maximum of 10,000"));
 
         let err3 = PasswordError::InvalidCount;
@@ -789,6 +2065,34 @@ mod tests {
         assert!(!char_set.contains(&b'A'));
     }
 
+    #[test]
+    fn test_build_char_set_exclude_case_insensitive() {
+        let mut args = create_test_args(false, false, false, vec!['a', 'Q', '1', '!']);
+        let char_set = build_char_set(&args).unwrap();
+        assert!(!char_set.contains(&b'a') && char_set.contains(&b'A'));
+        assert!(!char_set.contains(&b'Q') && char_set.contains(&b'q'));
+
+        args.exclude_case_insensitive = true;
+        let char_set = build_char_set(&args).unwrap();
+        for c in b"aAqQ1!" {
+            assert!(!char_set.contains(c), "{}", *c as char);
+        }
+        assert!(char_set.contains(&b'b') && char_set.contains(&b'B'));
+        assert_eq!(char_set.len(), 94 - 6);
+
+        // Other scripts fold case the same way
+        args.exclude_chars = vec!['ω'];
+        let char_set = build_script_char_set(&args, Script::Greek).unwrap();
+        assert!(!char_set.contains(&'ω') && !char_set.contains(&'Ω'));
+    }
+
+    #[test]
+    fn test_build_char_set_dedups_include_chars() {
+        let mut args = create_test_args(false, false, false, vec![]);
+        args.include_chars = Some(vec!['a', 'b', 'a', 'c', 'b']);
+        assert_eq!(build_char_set(&args).unwrap(), b"abc");
+    }
+
     #[test]
     fn test_build_char_set_with_include_chars_and_exclusions() {
         let mut args = create_test_args(false, false, false, vec!['a']);
@@ -801,6 +2105,101 @@ mod tests {
         assert!(char_set.contains(&b'c'));
     }
 
+    #[test]
+    fn test_build_char_set_rejects_non_ascii_include_chars() {
+        let mut args = create_test_args(false, false, false, vec![]);
+        args.include_chars = Some(vec!['a', 'é', 'b']);
+        assert!(matches!(
+            build_char_set(&args),
+            Err(PasswordError::NonAsciiCharacter { ch: 'é' })
+        ));
+
+        // U+0100 truncates to 0x00; it must error, never become a NUL
+        for c in ['\u{100}', '\u{80}', '\u{ff}'] {
+            args.include_chars = Some(vec!['a', c]);
+            assert!(matches!(
+                build_char_set(&args),
+                Err(PasswordError::NonAsciiCharacter { ch }) if ch == c
+            ));
+            args.include_chars = None;
+            args.include_back = vec![c];
+            assert!(matches!(
+                build_char_set(&args),
+                Err(PasswordError::NonAsciiCharacter { ch }) if ch == c
+            ));
+            args.include_back.clear();
+        }
+    }
+
+    #[test]
+    fn test_build_char_set_rejects_space_and_control_chars() {
+        let mut args = create_test_args(false, false, false, vec![]);
+        args.include_chars = Some(vec!['a', ' ', 'b']);
+        assert!(matches!(
+            build_char_set(&args),
+            Err(PasswordError::SpaceNotAllowed)
+        ));
+
+        args.allow_space = true;
+        assert_eq!(build_char_set(&args).unwrap(), b"a b");
+
+        for c in ['\t', '\n', '\0', '\x7f'] {
+            args.include_chars = Some(vec!['a', c]);
+            assert!(matches!(
+                build_char_set(&args),
+                Err(PasswordError::ControlCharacter { byte }) if byte == c as u8
+            ));
+        }
+    }
+
+    #[test]
+    fn test_build_script_char_set() {
+        let mut args = create_test_args(false, false, true, vec!['ω']);
+        let char_set = build_script_char_set(&args, Script::Greek).unwrap();
+        // 24 lowercase less the excluded omega, 24 uppercase, 10 digits
+        assert_eq!(char_set.len(), 23 + 24 + 10);
+        assert!(!char_set.contains(&'ς') && !char_set.contains(&'ω'));
+        assert!(!char_set.iter().any(char::is_ascii_alphabetic));
+
+        // Characters put back survive the exclusions, as in build_char_set
+        args.exclude_chars.extend(['5', '7']);
+        args.include_back = vec!['7'];
+        let with_back = build_script_char_set(&args, Script::Greek).unwrap();
+        assert!(with_back.contains(&'7') && !with_back.contains(&'5'));
+        assert_eq!(with_back.len(), char_set.len() - 1);
+        args.exclude_chars = vec!['ω'];
+        args.include_back.clear();
+
+        let passwords = generate_passwords(
+            &char_set,
+            &GenerationParams {
+                length: 12,
+                count: 20,
+                min_capitals: Some(2),
+                min_numerals: Some(2),
+                ..Default::default()
+            },
+            &mut rand::rng(),
+        )
+        .unwrap();
+        for pass in &passwords {
+            assert_eq!(pass.chars().count(), 12);
+            assert!(pass.chars().filter(|c| c.is_uppercase()).count() >= 2);
+            assert!(pass.chars().all(|c| char_set.contains(&c)));
+        }
+
+        // Latin matches build_char_set exactly
+        args.exclude_chars.clear();
+        let latin: Vec<char> = build_char_set(&args)
+            .unwrap()
+            .into_iter()
+            .map(char::from)
+            .collect();
+        assert_eq!(build_script_char_set(&args, Script::Latin).unwrap(), latin);
+        assert_eq!(Script::parse("cyrillic"), Some(Script::Cyrillic));
+        assert_eq!(Script::parse("klingon"), None);
+    }
+
     #[test]
     fn test_validate_args_too_long() {
         let mut args = create_test_args(false, false, false, vec![]);
@@ -809,7 +2208,48 @@ mod tests {
         assert!(result.is_err());
         assert!(matches!(
             result.unwrap_err(),
-            PasswordError::InvalidLengthTooLong
+            PasswordError::InvalidLengthTooLong { max: 10_000 }
+        ));
+    }
+
+    #[test]
+    fn test_validate_args_custom_max_length() {
+        let mut args = create_test_args(false, false, false, vec![]);
+        args.max_length = Some(64);
+
+        args.length = 64;
+        assert!(validate_args(&args).is_ok());
+
+        args.length = 65;
+        let err = validate_args(&args).unwrap_err();
+        assert!(matches!(
+            err,
+            PasswordError::InvalidLengthTooLong { max: 64 }
+        ));
+        assert!(err.to_string().contains("exceeds maximum of 64 characters"));
+    }
+
+    #[test]
+    fn test_validate_args_raised_max_length() {
+        let mut args = create_test_args(false, false, false, vec![]);
+        args.max_length = Some(50_000);
+
+        args.length = 20_000;
+        assert!(validate_args(&args).is_ok());
+
+        args.length = 50_001;
+        let err = validate_args(&args).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("exceeds maximum of 50,000 characters")
+        );
+
+        // Overrides above the absolute ceiling are rejected outright
+        args.max_length = Some(ABSOLUTE_MAX_PASSWORD_LENGTH + 1);
+        args.length = 16;
+        assert!(matches!(
+            validate_args(&args).unwrap_err(),
+            PasswordError::InvalidMaxLength
         ));
     }
 
@@ -835,26 +2275,31 @@ mod tests {
 
     #[test]
     fn test_column_count_multiples() {
-        // Test multiples of 5
+        // Even splits near a square win outright
         assert_eq!(column_count(25), 5);
         assert_eq!(column_count(30), 5);
         assert_eq!(column_count(35), 5);
 
-        // Test multiples of 4 (but not 5)
+        // Otherwise the last row is kept as full as possible
+        assert_eq!(column_count(26), 4); // 4 x 6 + 2
+        assert_eq!(column_count(27), 4); // 4 x 6 + 3
         assert_eq!(column_count(28), 4);
-        assert_eq!(column_count(32), 4);
-
-        // Test multiples of 3 (but not 4 or 5)
-        assert_eq!(column_count(27), 3);
-        assert_eq!(column_count(33), 3);
-
-        // Test multiples of 2 (but not 3, 4, or 5)
-        assert_eq!(column_count(26), 2);
-        assert_eq!(column_count(34), 2);
+        assert_eq!(column_count(34), 5); // 5 x 6 + 4
+    }
 
-        // Test prime numbers (should default to 3)
-        assert_eq!(column_count(29), 3);
-        assert_eq!(column_count(31), 3);
+    #[test]
+    fn test_column_count_primes() {
+        // Primes always leave a short last row, but never a nearly empty one
+        assert_eq!(column_count(7), 4); // 4 + 3
+        assert_eq!(column_count(11), 4); // 4 + 4 + 3
+        assert_eq!(column_count(13), 5); // 5 + 5 + 3
+        assert_eq!(column_count(29), 5); // 5 x 5 + 4
+        assert_eq!(column_count(31), 4); // 4 x 7 + 3
+
+        for n in 4..=200 {
+            let columns = column_count(n);
+            assert!((2..=MAX_TABLE_COLUMNS).contains(&columns));
+        }
     }
 
     #[test]
@@ -888,9 +2333,9 @@ mod tests {
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("Invalid pattern character"));
 
-        // Test empty pattern
-        let pattern4 = parse_pattern("").unwrap();
-        assert_eq!(pattern4.len(), 0);
+        // An empty pattern would produce empty passwords
+        let result = parse_pattern("");
+        assert!(result.unwrap_err().contains("Pattern is empty"));
     }
 
     #[test]
@@ -908,7 +2353,12 @@ mod tests {
         ];
 
         let mut rng = StdRng::seed_from_u64(42);
-        let password = generate_password_from_pattern(&char_set, &pattern, &mut rng);
+        let password = generate_password_from_pattern(
+            &char_set,
+            &CharClasses::from_char_set(&char_set),
+            &pattern,
+            &mut rng,
+        );
 
         assert_eq!(password.len(), 4);
         // Verify each character type (we can't predict exact chars due to randomness,
@@ -934,7 +2384,12 @@ mod tests {
         ];
 
         let mut rng = StdRng::seed_from_u64(123);
-        let password = generate_password_from_pattern(&char_set, &pattern, &mut rng);
+        let password = generate_password_from_pattern(
+            &char_set,
+            &CharClasses::from_char_set(&char_set),
+            &pattern,
+            &mut rng,
+        );
 
         assert_eq!(password.len(), 4);
         // All should be lowercase since that's all that's available
@@ -952,7 +2407,12 @@ mod tests {
         let pattern = vec![PatternChar::Lowercase]; // Will fallback to char_set
 
         let mut rng = StdRng::seed_from_u64(456);
-        let password = generate_password_from_pattern(&char_set, &pattern, &mut rng);
+        let password = generate_password_from_pattern(
+            &char_set,
+            &CharClasses::from_char_set(&char_set),
+            &pattern,
+            &mut rng,
+        );
 
         assert_eq!(password.len(), 1);
         // Should fallback to any character from char_set
@@ -960,208 +2420,1956 @@ mod tests {
     }
 
     #[test]
-    fn test_generate_password_from_pattern_empty_uppercase() {
-        use rand::{SeedableRng, rngs::StdRng};
+    fn test_generate_password_from_pattern_empty_uppercase() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        // Character set with no uppercase
+        let char_set = vec![b'a', b'b', b'0', b'1', b'!', b'@'];
+        let pattern = vec![PatternChar::Uppercase]; // Will fallback to char_set
+
+        let mut rng = StdRng::seed_from_u64(789);
+        let password = generate_password_from_pattern(
+            &char_set,
+            &CharClasses::from_char_set(&char_set),
+            &pattern,
+            &mut rng,
+        );
+
+        assert_eq!(password.len(), 1);
+        assert!(char_set.contains(&(password.chars().next().unwrap() as u8)));
+    }
+
+    #[test]
+    fn test_generate_password_from_pattern_empty_numeric() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        // Character set with no numeric
+        let char_set = vec![b'a', b'b', b'A', b'B', b'!', b'@'];
+        let pattern = vec![PatternChar::Numeric]; // Will fallback to char_set
+
+        let mut rng = StdRng::seed_from_u64(1011);
+        let password = generate_password_from_pattern(
+            &char_set,
+            &CharClasses::from_char_set(&char_set),
+            &pattern,
+            &mut rng,
+        );
+
+        assert_eq!(password.len(), 1);
+        assert!(char_set.contains(&(password.chars().next().unwrap() as u8)));
+    }
+
+    #[test]
+    fn test_generate_password_from_pattern_empty_symbols() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        // Character set with no symbols
+        let char_set = vec![b'a', b'b', b'A', b'B', b'0', b'1'];
+        let pattern = vec![PatternChar::Symbol]; // Will fallback to char_set
+
+        let mut rng = StdRng::seed_from_u64(1213);
+        let password = generate_password_from_pattern(
+            &char_set,
+            &CharClasses::from_char_set(&char_set),
+            &pattern,
+            &mut rng,
+        );
+
+        assert_eq!(password.len(), 1);
+        assert!(char_set.contains(&(password.chars().next().unwrap() as u8)));
+    }
+
+    #[test]
+    fn test_generate_password_with_minimums() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let char_set = vec![
+            b'a', b'b', b'c', // lowercase
+            b'A', b'B', b'C', // uppercase
+            b'0', b'1', b'2', // numeric
+            b'!', b'@', b'#', // symbols
+        ];
+
+        let mut rng = StdRng::seed_from_u64(456);
+        let password = generate_password_with_minimums(
+            &char_set,
+            &CharClasses::from_char_set(&char_set),
+            10,
+            Some(2),
+            Some(2),
+            Some(2),
+            &mut rng,
+        );
+
+        assert_eq!(password.len(), 10);
+
+        // Count character types
+        let mut capitals = 0;
+        let mut numerals = 0;
+        let mut symbols = 0;
+
+        for c in password.chars() {
+            if c.is_ascii_uppercase() {
+                capitals += 1;
+            } else if c.is_ascii_digit() {
+                numerals += 1;
+            } else if !c.is_alphanumeric() {
+                symbols += 1;
+            }
+        }
+
+        assert!(capitals >= 2);
+        assert!(numerals >= 2);
+        assert!(symbols >= 2);
+    }
+
+    #[test]
+    fn test_generate_password_with_minimums_empty_sets() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        // Only lowercase available
+        let char_set = vec![b'a', b'b', b'c'];
+
+        let mut rng = StdRng::seed_from_u64(789);
+        let password = generate_password_with_minimums(
+            &char_set,
+            &CharClasses::from_char_set(&char_set),
+            5,
+            Some(2),
+            Some(2),
+            Some(2),
+            &mut rng,
+        );
+
+        assert_eq!(password.len(), 5);
+        // All should be lowercase since that's all available
+        for c in password.chars() {
+            assert!(c.is_ascii_lowercase());
+        }
+    }
+
+    #[test]
+    fn test_generate_password_with_minimums_no_minimums() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let char_set = vec![b'a', b'b', b'c', b'A', b'B', b'0', b'1', b'!', b'@'];
+
+        let mut rng = StdRng::seed_from_u64(101);
+        let password = generate_password_with_minimums(
+            &char_set,
+            &CharClasses::from_char_set(&char_set),
+            8,
+            None,
+            None,
+            None,
+            &mut rng,
+        );
+
+        assert_eq!(password.len(), 8);
+    }
+
+    #[test]
+    fn test_generate_passwords() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let char_set = vec![b'a', b'b', b'c', b'1', b'2', b'3'];
+        let params = GenerationParams {
+            length: 5,
+            count: 3,
+            min_capitals: None,
+            min_numerals: None,
+            min_symbols: None,
+            pattern: None,
+            ..Default::default()
+        };
+
+        let mut rng = StdRng::seed_from_u64(202);
+        let passwords = generate_passwords(&char_set, &params, &mut rng).unwrap();
+
+        assert_eq!(passwords.len(), 3);
+        for pass in &passwords {
+            assert_eq!(pass.len(), 5);
+        }
+    }
+
+    #[test]
+    fn test_generate_passwords_with_pattern() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let char_set = vec![b'a', b'b', b'A', b'B', b'0', b'1', b'!', b'@'];
+        let pattern = vec![
+            PatternChar::Lowercase,
+            PatternChar::Uppercase,
+            PatternChar::Numeric,
+            PatternChar::Symbol,
+        ];
+        let params = GenerationParams {
+            length: 4,
+            count: 2,
+            min_capitals: None,
+            min_numerals: None,
+            min_symbols: None,
+            pattern: Some(pattern),
+            ..Default::default()
+        };
+
+        let mut rng = StdRng::seed_from_u64(303);
+        let passwords = generate_passwords(&char_set, &params, &mut rng).unwrap();
+
+        assert_eq!(passwords.len(), 2);
+        for pass in &passwords {
+            assert_eq!(pass.len(), 4);
+        }
+    }
+
+    #[test]
+    fn test_generate_passwords_with_minimums() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let char_set = vec![
+            b'a', b'b', b'c', b'A', b'B', b'C', b'0', b'1', b'2', b'!', b'@', b'#',
+        ];
+        let params = GenerationParams {
+            length: 8,
+            count: 2,
+            min_capitals: Some(1),
+            min_numerals: Some(1),
+            min_symbols: Some(1),
+            pattern: None,
+            ..Default::default()
+        };
+
+        let mut rng = StdRng::seed_from_u64(404);
+        let passwords = generate_passwords(&char_set, &params, &mut rng).unwrap();
+
+        assert_eq!(passwords.len(), 2);
+        for pass in &passwords {
+            assert_eq!(pass.len(), 8);
+            // Verify minimums are met
+            let mut has_capital = false;
+            let mut has_numeral = false;
+            let mut has_symbol = false;
+            for c in pass.chars() {
+                if c.is_ascii_uppercase() {
+                    has_capital = true;
+                } else if c.is_ascii_digit() {
+                    has_numeral = true;
+                } else if !c.is_alphanumeric() {
+                    has_symbol = true;
+                }
+            }
+            assert!(has_capital);
+            assert!(has_numeral);
+            assert!(has_symbol);
+        }
+    }
+
+    #[test]
+    fn test_generate_passwords_into_matches_allocating_version() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let char_set = vec![
+            b'a', b'b', b'c', b'A', b'B', b'C', b'0', b'1', b'2', b'!', b'@', b'#',
+        ];
+        let params = GenerationParams {
+            length: 12,
+            count: 5,
+            min_capitals: Some(1),
+            min_numerals: Some(1),
+            min_symbols: None,
+            pattern: None,
+            ..Default::default()
+        };
+
+        let mut rng = StdRng::seed_from_u64(505);
+        let expected = generate_passwords(&char_set, &params, &mut rng).unwrap();
+
+        // Pre-fill the buffer to confirm it is cleared before reuse
+        let mut buffer = vec!["stale".to_string(); 8];
+        let mut rng = StdRng::seed_from_u64(505);
+        generate_passwords_into(&char_set, &params, &mut rng, &mut buffer).unwrap();
+
+        assert_eq!(buffer, expected);
+    }
+
+    #[test]
+    fn test_generate_passwords_seeded_output_pinned() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        // Pins seeded output so performance refactors can't silently change it
+        let args = create_test_args(false, false, false, vec![]);
+        let char_set = build_char_set(&args).unwrap();
+
+        let params = GenerationParams {
+            length: 12,
+            count: 3,
+            min_capitals: Some(2),
+            min_numerals: Some(2),
+            min_symbols: Some(1),
+            pattern: None,
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(2024);
+        assert_eq!(
+            generate_passwords(&char_set, &params, &mut rng).unwrap(),
+            vec!["Vp.N40rb75wp", "<OY*9Tk1]2zH", "+89UyXe0NS;Y"]
+        );
+
+        let params = GenerationParams {
+            length: 6,
+            count: 3,
+            min_capitals: None,
+            min_numerals: None,
+            min_symbols: None,
+            pattern: Some(parse_pattern("ULNSLN").unwrap()),
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(2024);
+        assert_eq!(
+            generate_passwords(&char_set, &params, &mut rng).unwrap(),
+            vec!["Nv7;k6", "Og0'e1", "Yh5'y3"]
+        );
+    }
+
+    #[test]
+    fn test_pick_distribution_is_uniform() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        // Chi-square goodness-of-fit over a small set. With 6 categories
+        // (5 degrees of freedom) the 99.9% critical value is ~20.5.
+        let set = [b'a', b'b', b'c', b'd', b'e', b'f'];
+        let samples = 60_000;
+        let mut counts = [0usize; 6];
+
+        let mut rng = StdRng::seed_from_u64(300);
+        for _ in 0..samples {
+            let b = pick(&set, &mut rng);
+            counts[(b - b'a') as usize] += 1;
+        }
+
+        let expected = samples as f64 / set.len() as f64;
+        let chi_square: f64 = counts
+            .iter()
+            .map(|&observed| {
+                let diff = observed as f64 - expected;
+                diff * diff / expected
+            })
+            .sum();
+
+        assert!(chi_square < 20.5, "chi-square too high: {}", chi_square);
+    }
+
+    #[test]
+    fn test_pick_single_element() {
+        let mut rng = rand::rng();
+        assert_eq!(pick(b"x", &mut rng), b'x');
+    }
+
+    #[test]
+    fn test_pick_rejects_biased_draws() {
+        // For 3 elements 2^32 % 3 == 1, so the draw 0 is the one rejected,
+        // leaving (2^32 - 1) / 3 accepted draws per index
+        let mut rng = StepRng(0, 1);
+        assert_eq!(pick(b"abc", &mut rng), b'a');
+        assert_eq!(rng.0, 2);
+
+        // The top of the draw range maps to the last index
+        let mut rng = StepRng(u32::MAX, 1);
+        assert_eq!(pick(b"abc", &mut rng), b'c');
+        assert_eq!(rng.0, 0);
+
+        // A power of two rejects nothing
+        let mut rng = StepRng(0, 1);
+        assert_eq!(pick(b"ab", &mut rng), b'a');
+        assert_eq!(rng.0, 1);
+    }
+
+    #[test]
+    fn test_similarity() {
+        assert_eq!(similarity("abcd", "abcd"), 1.0);
+        assert_eq!(similarity("", ""), 1.0);
+        assert_eq!(similarity("abcd", "wxyz"), 0.0);
+        // One substitution out of four characters
+        assert!((similarity("abcd", "abce") - 0.75).abs() < f64::EPSILON);
+        // Insertions count against the longer string
+        assert!((similarity("abc", "abcd") - 0.75).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_generate_passwords_rejects_similar() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        // The only possible password is identical to the excluded one
+        let char_set = vec![b'a'];
+        let params = GenerationParams {
+            length: 4,
+            count: 1,
+            exclude_similar_to: Some("aaaa".to_string()),
+            ..Default::default()
+        };
+
+        let mut rng = StdRng::seed_from_u64(301);
+        let result = generate_passwords(&char_set, &params, &mut rng);
+        assert!(matches!(
+            result.unwrap_err(),
+            PasswordError::RetryLimitExceeded {
+                constraint: "exclude-similar-to",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_generate_passwords_accepts_dissimilar() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let char_set = vec![b'a', b'b', b'c'];
+        let params = GenerationParams {
+            length: 8,
+            count: 5,
+            exclude_similar_to: Some("zzzzzzzz".to_string()),
+            ..Default::default()
+        };
+
+        let mut rng = StdRng::seed_from_u64(302);
+        let passwords = generate_passwords(&char_set, &params, &mut rng).unwrap();
+        assert_eq!(passwords.len(), 5);
+        for pass in &passwords {
+            assert!(similarity(pass, "zzzzzzzz") <= MAX_SIMILARITY);
+        }
+    }
+
+    #[test]
+    fn test_generate_passwords_avoids_forbidden() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let char_set = vec![b'a', b'b'];
+        let params = GenerationParams {
+            length: 1,
+            count: 20,
+            forbidden_passwords: BTreeSet::from(["a".to_string()]),
+            ..Default::default()
+        };
+
+        let mut rng = StdRng::seed_from_u64(312);
+        let passwords = generate_passwords(&char_set, &params, &mut rng).unwrap();
+        assert!(passwords.iter().all(|p| p == "b"));
+    }
+
+    #[test]
+    fn test_generate_passwords_forbidden_space_exhausted() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        // Every possible length-1 password is forbidden
+        let char_set = vec![b'a', b'b'];
+        let params = GenerationParams {
+            length: 1,
+            count: 1,
+            forbidden_passwords: BTreeSet::from(["a".to_string(), "b".to_string()]),
+            ..Default::default()
+        };
+
+        let mut rng = StdRng::seed_from_u64(313);
+        assert!(matches!(
+            generate_passwords(&char_set, &params, &mut rng).unwrap_err(),
+            PasswordError::RetryLimitExceeded {
+                constraint: "exclude-password-file",
+                attempts: MAX_REROLL_ATTEMPTS,
+            }
+        ));
+    }
+
+    #[test]
+    fn test_generate_one_matches_generate_passwords() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let args = create_test_args(false, false, false, vec![]);
+        let char_set = build_char_set(&args).unwrap();
+        let cases = [
+            GenerationParams {
+                length: 16,
+                count: 1,
+                ..Default::default()
+            },
+            GenerationParams {
+                length: 12,
+                count: 1,
+                min_capitals: Some(2),
+                min_symbols: Some(3),
+                max_repeats: Some(1),
+                ..Default::default()
+            },
+            GenerationParams {
+                length: 20,
+                count: 1,
+                all_unique_chars: true,
+                ..Default::default()
+            },
+            GenerationParams {
+                length: 10,
+                count: 1,
+                ensure_all_types: true,
+                first_char_alpha: true,
+                ..Default::default()
+            },
+        ];
+        for (seed, params) in cases.iter().enumerate() {
+            let one = generate_one(&char_set, params, &mut StdRng::seed_from_u64(seed as u64));
+            let batch =
+                generate_passwords(&char_set, params, &mut StdRng::seed_from_u64(seed as u64));
+            assert_eq!(one.unwrap(), batch.unwrap()[0]);
+        }
+
+        // Setup errors are the same too
+        let params = GenerationParams {
+            length: 8,
+            count: 1,
+            require_mixed_case: true,
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(381);
+        assert!(matches!(
+            generate_one(b"abc".as_slice(), &params, &mut rng),
+            Err(PasswordError::MixedCaseUnavailable)
+        ));
+    }
+
+    #[test]
+    fn test_generation_strategy_routing() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let base = GenerationParams {
+            length: 8,
+            count: 1,
+            ..Default::default()
+        };
+        let pattern = GenerationParams {
+            pattern: Some(parse_pattern("NNNN").unwrap()),
+            ..base.clone()
+        };
+        let mask = GenerationParams {
+            mask: Some(parse_mask("[x]{3}").unwrap()),
+            ..pattern.clone()
+        };
+        let unique = GenerationParams {
+            all_unique_chars: true,
+            ..base.clone()
+        };
+        assert_eq!(
+            GenerationStrategy::from_params(&base),
+            GenerationStrategy::Random
+        );
+        assert_eq!(
+            GenerationStrategy::from_params(&pattern),
+            GenerationStrategy::Pattern
+        );
+        assert_eq!(
+            GenerationStrategy::from_params(&mask),
+            GenerationStrategy::Mask
+        );
+        assert_eq!(
+            GenerationStrategy::from_params(&unique),
+            GenerationStrategy::UniqueChars
+        );
+
+        let char_set = build_char_set(&create_test_args(false, false, false, vec![])).unwrap();
+        let mut rng = StdRng::seed_from_u64(387);
+        let pass = generate_one(&char_set, &pattern, &mut rng).unwrap();
+        assert!(
+            pass.len() == 4 && pass.chars().all(|c| c.is_ascii_digit()),
+            "{pass}"
+        );
+        assert_eq!(generate_one(&char_set, &mask, &mut rng).unwrap(), "xxx");
+        let pass = generate_one(&char_set, &unique, &mut rng).unwrap();
+        assert_eq!(pass.chars().collect::<BTreeSet<_>>().len(), 8, "{pass}");
+
+        // Memorable passwords go through the same trait
+        let params = MemorableParams::default();
+        let via_trait = params.generate(&mut StdRng::seed_from_u64(387));
+        let direct = generate_memorable(&params, &mut StdRng::seed_from_u64(387));
+        assert_eq!(via_trait, direct);
+    }
+
+    #[test]
+    fn test_generate_passwords_retry_limit() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        // "aaa" is the only password and always breaks max_repeats
+        let char_set = vec![b'a'];
+        let mut params = GenerationParams {
+            length: 3,
+            count: 1,
+            max_repeats: Some(1),
+            retry_limit: Some(25),
+            ..Default::default()
+        };
+
+        let mut rng = StdRng::seed_from_u64(379);
+        let err = generate_passwords(&char_set, &params, &mut rng).unwrap_err();
+        assert!(matches!(
+            err,
+            PasswordError::RetryLimitExceeded {
+                constraint: "max-repeats",
+                attempts: 25,
+            }
+        ));
+        assert!(err.to_string().contains("--retry-limit"));
+
+        // A satisfiable constraint still succeeds with a single attempt when
+        // nothing needs re-rolling
+        params.max_repeats = Some(3);
+        params.retry_limit = Some(1);
+        assert_eq!(
+            generate_passwords(&char_set, &params, &mut rng).unwrap(),
+            ["aaa"]
+        );
+    }
+
+    #[test]
+    fn test_generate_passwords_duplicates_without_unique() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        // 4 possible passwords, 10 requested: duplicates are unavoidable
+        let char_set = vec![b'a', b'b'];
+        let params = GenerationParams {
+            length: 2,
+            count: 10,
+            ..Default::default()
+        };
+
+        let mut rng = StdRng::seed_from_u64(313);
+        let passwords = generate_passwords(&char_set, &params, &mut rng).unwrap();
+        let distinct: HashSet<_> = passwords.iter().collect();
+        assert!(distinct.len() < passwords.len());
+    }
+
+    #[test]
+    fn test_generate_passwords_unique() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        // Request the entire keyspace
+        let char_set = vec![b'a', b'b'];
+        let params = GenerationParams {
+            length: 2,
+            count: 4,
+            unique: true,
+            ..Default::default()
+        };
+
+        let mut rng = StdRng::seed_from_u64(313);
+        let passwords = generate_passwords(&char_set, &params, &mut rng).unwrap();
+        let distinct: HashSet<_> = passwords.iter().collect();
+        assert_eq!(distinct.len(), 4);
+
+        // The last of 10,000 PINs collides 9,999 times in 10,000 on average,
+        // far past the fixed default budget
+        let char_set: Vec<u8> = (b'0'..=b'9').collect();
+        let params = GenerationParams {
+            length: 4,
+            count: 10_000,
+            unique: true,
+            ..Default::default()
+        };
+        let passwords = generate_passwords(&char_set, &params, &mut rng).unwrap();
+        let distinct: HashSet<_> = passwords.iter().collect();
+        assert_eq!(distinct.len(), 10_000);
+    }
+
+    #[test]
+    fn test_generate_passwords_unique_keyspace_too_small() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let char_set = vec![b'a', b'b'];
+        let params = GenerationParams {
+            length: 2,
+            count: 5,
+            unique: true,
+            ..Default::default()
+        };
+
+        let mut rng = StdRng::seed_from_u64(313);
+        assert!(matches!(
+            generate_passwords(&char_set, &params, &mut rng).unwrap_err(),
+            PasswordError::KeyspaceTooSmall {
+                keyspace: 4,
+                count: 5
+            }
+        ));
+
+        // Pattern keyspace is the product of the class pool sizes
+        let params = GenerationParams {
+            length: 2,
+            count: 11,
+            pattern: Some(vec![PatternChar::Numeric]),
+            unique: true,
+            ..Default::default()
+        };
+        let char_set: Vec<u8> = (b'0'..=b'9').collect();
+        assert!(matches!(
+            generate_passwords(&char_set, &params, &mut rng).unwrap_err(),
+            PasswordError::KeyspaceTooSmall {
+                keyspace: 10,
+                count: 11
+            }
+        ));
+    }
+
+    #[test]
+    fn test_crack_time() {
+        // One guess per second makes the expected time 2^(bits - 1) seconds
+        assert_eq!(crack_time(0.0, 1.0), "less than a second");
+        assert_eq!(crack_time(1.0, 1.0), "1 second");
+        assert_eq!(crack_time(7.0, 1.0), "1 minute");
+        assert_eq!(crack_time(13.0, 1.0), "1 hour");
+        assert_eq!(crack_time(20.0, 1.0), "6 days");
+        assert_eq!(crack_time(30.0, 1.0), "17 years");
+        assert_eq!(crack_time(40.0, 1.0), "174 centuries");
+        assert_eq!(crack_time(128.0, 1.0), "over a million centuries");
+    }
+
+    #[test]
+    fn test_crack_time_scales_with_rate() {
+        assert_eq!(crack_time(40.0, DEFAULT_GUESSES_PER_SECOND), "54 seconds");
+        assert_eq!(crack_time(40.0, 1e3), "17 years");
+    }
+
+    #[test]
+    fn test_explain_entropy_pattern() {
+        let args = create_test_args(false, false, false, vec![]);
+        let char_set = build_char_set(&args).unwrap();
+        let params = GenerationParams {
+            length: 3,
+            count: 1,
+            pattern: Some(parse_pattern("ULN").unwrap()),
+            ..Default::default()
+        };
+
+        let explanation = explain_entropy(&char_set, &params);
+        let pools: Vec<usize> = explanation.terms.iter().map(|t| t.pool_size).collect();
+        assert_eq!(pools, vec![26, 26, 10]);
+        assert!(explanation.deductions.is_empty());
+        assert_eq!(explanation.charset_size, 62);
+
+        let expected = 2.0 * 26f64.log2() + 10f64.log2();
+        assert!((explanation.total_bits() - expected).abs() < 1e-9);
+        // Far below the naive 3 x log2(94)
+        assert!(explanation.total_bits() < calculate_entropy(char_set.len(), 3));
+
+        let text = explanation.to_string();
+        assert!(text.contains("Position 1 (uppercase): 1 x log2(26) = 4.70 bits"));
+        assert!(text.contains("Position 3 (numeric): 1 x log2(10) = 3.32 bits"));
+        assert!(text.contains("Final: 12.72 bits"));
+    }
+
+    #[test]
+    fn test_explain_entropy_minimums() {
+        let args = create_test_args(false, false, false, vec![]);
+        let char_set = build_char_set(&args).unwrap();
+        let params = GenerationParams {
+            length: 16,
+            count: 1,
+            min_capitals: Some(2),
+            min_numerals: Some(0),
+            ..Default::default()
+        };
+
+        let explanation = explain_entropy(&char_set, &params);
+        assert_eq!(explanation.terms.len(), 1);
+        assert_eq!(explanation.terms[0].positions, 16);
+        assert_eq!(explanation.charset_size, 94);
+        // Some(0) is the same as None and deducts nothing
+        assert_eq!(explanation.deductions.len(), 1);
+
+        let expected = 14.0 * 94f64.log2() + 2.0 * 26f64.log2();
+        assert!((explanation.total_bits() - expected).abs() < 1e-9);
+    }
+
+    /// Validates a full number, check digit included, with the Luhn algorithm
+    fn luhn_valid(number: &str) -> bool {
+        let sum: u32 = number
+            .bytes()
+            .rev()
+            .enumerate()
+            .map(|(i, b)| {
+                let d = u32::from(b - b'0');
+                if i % 2 == 1 {
+                    (d * 2) / 10 + (d * 2) % 10
+                } else {
+                    d
+                }
+            })
+            .sum();
+        sum.is_multiple_of(10)
+    }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    fn test_sha256_hex() {
+        // Test vectors from FIPS 180-2
+        assert_eq!(
+            sha256_hex(""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            sha256_hex("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    fn test_hmac_sha256() {
+        let hex = |bytes: [u8; 32]| {
+            bytes
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>()
+        };
+        // Test cases 2 and 6 from RFC 4231; the second key is longer than a block
+        assert_eq!(
+            hex(hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(
+            hex(hmac_sha256(
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            )),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+
+        assert_eq!(peppered_seed(7, "pepper"), peppered_seed(7, "pepper"));
+        assert_ne!(peppered_seed(7, "pepper"), peppered_seed(7, "salt"));
+        assert_ne!(peppered_seed(7, "pepper"), peppered_seed(8, "pepper"));
+    }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    fn test_account_seed() {
+        let hex = |bytes: [u8; 32]| {
+            bytes
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>()
+        };
+        // Published PBKDF2-HMAC-SHA256 vectors for "password" and "salt"
+        assert_eq!(
+            hex(pbkdf2_hmac_sha256(b"password", b"salt", 1)),
+            "120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b"
+        );
+        assert_eq!(
+            hex(pbkdf2_hmac_sha256(b"password", b"salt", 4096)),
+            "c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a"
+        );
+
+        let seed = account_seed("master", "example.com");
+        assert_eq!(seed, account_seed("master", "example.com"));
+        assert_ne!(seed, account_seed("master", "example.org"));
+        assert_ne!(seed, account_seed("other", "example.com"));
+    }
+
+    #[cfg(feature = "bcrypt")]
+    #[test]
+    fn test_bcrypt_hash_verifies() {
+        // Minimum cost keeps the test fast
+        let hash = bcrypt_hash("correct horse", 4).unwrap();
+        assert!(hash.starts_with("$2b$04$"));
+        assert!(bcrypt::verify("correct horse", &hash).unwrap());
+        assert!(!bcrypt::verify("battery staple", &hash).unwrap());
+
+        assert!(bcrypt_hash("x", 3).is_err());
+        assert!(bcrypt_hash(&"x".repeat(73), 4).is_err());
+    }
+
+    #[cfg(feature = "argon2")]
+    #[test]
+    fn test_argon2_hash_verifies() {
+        use argon2::password_hash::{PasswordHash, PasswordVerifier};
+
+        let hash = argon2_hash("correct horse", 1).unwrap();
+        assert!(hash.starts_with("$argon2id$v=19$"));
+        assert!(hash.contains("t=1"));
+        let parsed = PasswordHash::new(&hash).unwrap();
+        let argon2 = argon2::Argon2::default();
+        assert!(argon2.verify_password(b"correct horse", &parsed).is_ok());
+        assert!(argon2.verify_password(b"battery staple", &parsed).is_err());
+
+        assert!(argon2_hash("x", 0).is_err());
+    }
+
+    #[test]
+    fn test_luhn_check_digit() {
+        // Reference values
+        assert_eq!(luhn_check_digit("7992739871"), '3');
+        assert_eq!(luhn_check_digit("37828224631000"), '5');
+        assert_eq!(luhn_check_digit("0"), '0');
+        assert_eq!(luhn_check_digit(""), '0');
+    }
+
+    #[test]
+    fn test_luhn_check_digit_generated() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let char_set: Vec<u8> = (b'0'..=b'9').collect();
+        let params = GenerationParams {
+            length: 15,
+            count: 50,
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(318);
+        for pass in generate_passwords(&char_set, &params, &mut rng).unwrap() {
+            let number = format!("{}{}", pass, luhn_check_digit(&pass));
+            assert!(
+                luhn_valid(&number),
+                "{} should pass Luhn validation",
+                number
+            );
+        }
+    }
+
+    #[test]
+    fn test_generate_passwords_require_mixed_case() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        // Short passwords from a letter-heavy set often miss a case
+        let args = create_test_args(false, false, false, vec![]);
+        let char_set = build_char_set(&args).unwrap();
+        let params = GenerationParams {
+            length: 3,
+            count: 200,
+            require_mixed_case: true,
+            ..Default::default()
+        };
+
+        let mut rng = StdRng::seed_from_u64(322);
+        for pass in generate_passwords(&char_set, &params, &mut rng).unwrap() {
+            assert!(pass.bytes().any(|b| b.is_ascii_lowercase()), "{}", pass);
+            assert!(pass.bytes().any(|b| b.is_ascii_uppercase()), "{}", pass);
+        }
+    }
+
+    #[test]
+    fn test_generate_passwords_ensure_all_types() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        // Classes missing from the set aren't required
+        let char_set: Vec<u8> = b"abcXYZ!?".to_vec();
+        let params = GenerationParams {
+            length: 3,
+            count: 200,
+            min_capitals: Some(0),
+            ensure_all_types: true,
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(343);
+        for pass in generate_passwords(&char_set, &params, &mut rng).unwrap() {
+            assert!(pass.bytes().any(|b| b.is_ascii_lowercase()), "{}", pass);
+            assert!(pass.bytes().any(|b| b.is_ascii_uppercase()), "{}", pass);
+            assert!(pass.bytes().any(|b| b.is_ascii_punctuation()), "{}", pass);
+        }
+
+        // Minimums count towards the characters required
+        let params = GenerationParams {
+            length: 4,
+            min_symbols: Some(3),
+            ..params
+        };
+        assert!(matches!(
+            generate_passwords(&char_set, &params, &mut rng).unwrap_err(),
+            PasswordError::TooManyRequiredTypes {
+                required: 5,
+                length: 4
+            }
+        ));
+    }
+
+    #[test]
+    fn test_generate_passwords_all_unique_chars() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        // Duplicates in the set don't count as extra characters
+        let char_set: Vec<u8> = b"abcdeab".to_vec();
+        let params = GenerationParams {
+            length: 5,
+            count: 100,
+            all_unique_chars: true,
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(350);
+        for pass in generate_passwords(&char_set, &params, &mut rng).unwrap() {
+            let mut sorted: Vec<u8> = pass.bytes().collect();
+            sorted.sort_unstable();
+            assert_eq!(sorted, b"abcde", "{}", pass);
+        }
+
+        let params = GenerationParams {
+            length: 6,
+            ..params
+        };
+        assert!(matches!(
+            generate_passwords(&char_set, &params, &mut rng).unwrap_err(),
+            PasswordError::LengthExceedsCharacterSet {
+                length: 6,
+                available: 5
+            }
+        ));
+
+        // Sampling without replacement loses log2(n^L / (n! / (n-L)!)) bits
+        assert!((unique_chars_entropy(5, 5) - 120f64.log2()).abs() < 1e-9);
+        assert!(unique_chars_entropy(94, 16) < calculate_entropy(94, 16));
+        let explanation = explain_entropy(&char_set[..5], &params);
+        assert_eq!(explanation.deductions[0].0, "All characters unique");
+    }
+
+    #[test]
+    fn test_generate_passwords_all_unique_chars_subset() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let args = create_test_args(false, false, false, vec![]);
+        let char_set = build_char_set(&args).unwrap();
+        let params = GenerationParams {
+            length: 90,
+            count: 50,
+            all_unique_chars: true,
+            ..Default::default()
+        };
+        let passwords =
+            generate_passwords(&char_set, &params, &mut StdRng::seed_from_u64(351)).unwrap();
+        for pass in &passwords {
+            let mut seen = std::collections::BTreeSet::new();
+            for b in pass.bytes() {
+                assert!(char_set.contains(&b), "{} not in the set", b as char);
+                assert!(seen.insert(b), "{} repeated in {}", b as char, pass);
+            }
+            assert_eq!(seen.len(), 90);
+        }
+
+        // The same seed gives the same passwords
+        let again =
+            generate_passwords(&char_set, &params, &mut StdRng::seed_from_u64(351)).unwrap();
+        assert_eq!(passwords, again);
+    }
+
+    #[test]
+    fn test_generate_passwords_maximums() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let args = create_test_args(false, false, false, vec![]);
+        let char_set = build_char_set(&args).unwrap();
+        let params = GenerationParams {
+            length: 64,
+            count: 100,
+            min_symbols: Some(2),
+            max_capitals: Some(0),
+            max_numerals: Some(5),
+            max_symbols: Some(3),
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(358);
+        for pass in generate_passwords(&char_set, &params, &mut rng).unwrap() {
+            let count = |f: fn(&u8) -> bool| pass.bytes().filter(f).count();
+            assert_eq!(pass.len(), 64);
+            assert_eq!(count(u8::is_ascii_uppercase), 0, "{}", pass);
+            assert!(count(u8::is_ascii_digit) <= 5, "{}", pass);
+            let symbols = count(u8::is_ascii_punctuation);
+            assert!((2..=3).contains(&symbols), "{}", pass);
+        }
+
+        // Without lowercase letters the caps bound the length
+        let char_set: Vec<u8> = b"ABC123!?".to_vec();
+        let params = GenerationParams {
+            length: 8,
+            count: 1,
+            max_capitals: Some(2),
+            max_numerals: Some(2),
+            ..Default::default()
+        };
+        assert!(generate_passwords(&char_set, &params, &mut rng).is_ok());
+        let params = GenerationParams {
+            max_symbols: Some(3),
+            ..params
+        };
+        assert!(matches!(
+            generate_passwords(&char_set, &params, &mut rng).unwrap_err(),
+            PasswordError::MaximumsBelowLength {
+                total: 7,
+                length: 8
+            }
+        ));
+
+        // A minimum raised by ensure_all_types counts too
+        let params = GenerationParams {
+            length: 8,
+            max_symbols: Some(0),
+            ensure_all_types: true,
+            ..Default::default()
+        };
+        assert!(matches!(
+            generate_passwords(&char_set, &params, &mut rng).unwrap_err(),
+            PasswordError::MinimumAboveMaximum {
+                class: "symbols",
+                min: 1,
+                max: 0
+            }
+        ));
+    }
+
+    #[test]
+    fn test_validate_args_minimum_above_maximum() {
+        let mut args = create_test_args(false, false, false, vec![]);
+        args.min_numerals = Some(4);
+        args.max_numerals = Some(4);
+        assert!(validate_args(&args).is_ok());
+        args.max_numerals = Some(3);
+        assert!(matches!(
+            validate_args(&args),
+            Err(PasswordError::MinimumAboveMaximum {
+                class: "numerals",
+                min: 4,
+                max: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn test_generate_passwords_single_class_is_uniform() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        // 7 doesn't divide 256, so the rejected bytes matter
+        let char_set: Vec<u8> = b"abcdefg".to_vec();
+        let params = GenerationParams {
+            length: 70,
+            count: 100,
+            bulk_bytes: true,
+            ..Default::default()
+        };
+        let mut counts = [0usize; 7];
+        for pass in generate_passwords(&char_set, &params, &mut StdRng::seed_from_u64(359)).unwrap()
+        {
+            assert_eq!(pass.len(), 70);
+            for b in pass.bytes() {
+                counts[usize::from(b - b'a')] += 1;
+            }
+        }
+        // 1,000 expected per character
+        assert!(
+            counts.iter().all(|&n| (850..1150).contains(&n)),
+            "{:?}",
+            counts
+        );
+    }
+
+    #[test]
+    fn test_generate_passwords_require_mixed_case_without_capitals() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let args = create_test_args(true, false, false, vec![]);
+        let char_set = build_char_set(&args).unwrap();
+        let params = GenerationParams {
+            length: 16,
+            count: 1,
+            require_mixed_case: true,
+            ..Default::default()
+        };
+
+        let mut rng = StdRng::seed_from_u64(322);
+        assert!(matches!(
+            generate_passwords(&char_set, &params, &mut rng).unwrap_err(),
+            PasswordError::MixedCaseUnavailable
+        ));
+    }
+
+    #[test]
+    fn test_parse_mask_classes() {
+        let mask = parse_mask("[ab][0-9][!@#]x").unwrap();
+        assert_eq!(mask.len(), 4);
+        assert_eq!(mask[0], b"ab");
+        assert_eq!(mask[1], b"0123456789");
+        assert_eq!(mask[2], b"!#@");
+        assert_eq!(mask[3], b"x");
+
+        // Mixed ranges, duplicates and escapes inside a set
+        let mask = parse_mask("[a-cA-Cb][\\]\\-]").unwrap();
+        assert_eq!(mask[0], b"ABCabc");
+        assert_eq!(mask[1], b"-]");
+        // A trailing '-' is literal
+        assert_eq!(parse_mask("[a-]").unwrap()[0], b"-a");
+    }
+
+    #[test]
+    fn test_parse_mask_repetition() {
+        let mask = parse_mask("[ab][0-9]{4}[!@#]").unwrap();
+        assert_eq!(mask.len(), 6);
+        assert!(mask[1..5].iter().all(|pool| pool == b"0123456789"));
+        assert_eq!(parse_mask("x{3}").unwrap(), vec![b"x".to_vec(); 3]);
+        assert_eq!(parse_mask("[ab]{1}").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_parse_mask_errors() {
+        assert!(parse_mask("[ab").is_err());
+        assert!(parse_mask("[]").is_err());
+        assert!(parse_mask("[z-a]").is_err());
+        assert!(parse_mask("{3}").is_err());
+        assert!(parse_mask("a{0}").is_err());
+        assert!(parse_mask("a{x}").is_err());
+        assert!(parse_mask("a}").is_err());
+        assert!(parse_mask("\\").is_err());
+        assert!(parse_mask("é").is_err());
+
+        // Oversized repeats fail before anything is expanded
+        let err = parse_mask("a{4000000000}").unwrap_err();
+        assert!(err.contains("maximum of 1,000,000 characters"), "{err}");
+        assert!(parse_mask("a{18446744073709551616}").is_err());
+        assert_eq!(parse_mask("ab{1000000}").unwrap_err(), err);
+        assert_eq!(parse_mask("a{1000000}b").unwrap_err(), err);
+    }
+
+    #[test]
+    fn test_restrict_mask() {
+        let args = create_test_args(false, false, true, vec![]);
+        let char_set = build_char_set(&args).unwrap();
+
+        let mask = restrict_mask(parse_mask("[a-c!][0-9]").unwrap(), &char_set).unwrap();
+        assert_eq!(mask[0], b"abc");
+
+        let err = restrict_mask(parse_mask("[a-c][!@#]").unwrap(), &char_set).unwrap_err();
+        assert!(err.contains("Mask position 2"));
+    }
+
+    #[test]
+    fn test_generate_passwords_from_mask() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let mask = parse_mask("[ab][0-9]{4}[!@#]").unwrap();
+        let char_set: Vec<u8> = (33..=126).collect();
+        let params = GenerationParams {
+            length: mask.len() as u32,
+            count: 50,
+            mask: Some(mask.clone()),
+            ..Default::default()
+        };
+
+        let mut rng = StdRng::seed_from_u64(326);
+        for pass in generate_passwords(&char_set, &params, &mut rng).unwrap() {
+            assert_eq!(pass.len(), 6);
+            for (b, pool) in pass.bytes().zip(&mask) {
+                assert!(pool.contains(&b), "{} not allowed in {}", b as char, pass);
+            }
+        }
+
+        let explanation = explain_entropy(&char_set, &params);
+        let expected = 2f64.log2() + 4.0 * 10f64.log2() + 3f64.log2();
+        assert!((explanation.total_bits() - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_generate_passwords_first_char_alpha() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let args = create_test_args(false, false, false, vec![]);
+        let char_set = build_char_set(&args).unwrap();
+        let params = GenerationParams {
+            length: 8,
+            count: 100,
+            min_numerals: Some(3),
+            min_symbols: Some(3),
+            first_char_alpha: true,
+            ..Default::default()
+        };
+
+        let mut rng = StdRng::seed_from_u64(327);
+        for pass in generate_passwords(&char_set, &params, &mut rng).unwrap() {
+            assert!(pass.as_bytes()[0].is_ascii_alphabetic(), "{}", pass);
+            // The swap keeps the minimums intact
+            assert!(pass.bytes().filter(u8::is_ascii_digit).count() >= 3);
+        }
+
+        // With no letters to swap in, a spare digit or symbol is redrawn
+        let no_letters: Vec<u8> = char_set
+            .iter()
+            .copied()
+            .filter(|b| !b.is_ascii_alphabetic())
+            .chain([b'a'])
+            .collect();
+        let params = GenerationParams {
+            length: 4,
+            count: 100,
+            min_numerals: Some(2),
+            min_symbols: Some(1),
+            first_char_alpha: true,
+            ..Default::default()
+        };
+        for pass in generate_passwords(&no_letters, &params, &mut rng).unwrap() {
+            assert!(pass.starts_with('a'), "{}", pass);
+            assert!(
+                pass.bytes().filter(u8::is_ascii_digit).count() >= 2,
+                "{}",
+                pass
+            );
+            assert!(
+                pass.bytes().filter(u8::is_ascii_punctuation).count() >= 1,
+                "{}",
+                pass
+            );
+        }
+    }
+
+    #[test]
+    fn test_generate_passwords_first_char_alpha_unavailable() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let mut rng = StdRng::seed_from_u64(327);
+        let digits: Vec<u8> = (b'0'..=b'9').collect();
+        let params = GenerationParams {
+            length: 8,
+            count: 1,
+            first_char_alpha: true,
+            ..Default::default()
+        };
+        assert!(matches!(
+            generate_passwords(&digits, &params, &mut rng).unwrap_err(),
+            PasswordError::FirstCharAlphaUnavailable
+        ));
+
+        // Patterns and masks must already start with a letter
+        let args = create_test_args(false, false, false, vec![]);
+        let char_set = build_char_set(&args).unwrap();
+        for (pattern, mask, ok) in [
+            (Some("NLL"), None, false),
+            (Some("ULL"), None, true),
+            (None, Some("[a-z0-9]xx"), false),
+            (None, Some("[a-zA-Z]{3}"), true),
+        ] {
+            let params = GenerationParams {
+                length: 3,
+                count: 1,
+                pattern: pattern.map(|p| parse_pattern(p).unwrap()),
+                mask: mask.map(|m| parse_mask(m).unwrap()),
+                first_char_alpha: true,
+                ..Default::default()
+            };
+            assert_eq!(
+                generate_passwords(&char_set, &params, &mut rng).is_ok(),
+                ok,
+                "{:?} {:?}",
+                pattern,
+                mask
+            );
+        }
+    }
+
+    #[test]
+    fn test_generate_passwords_class_weights() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let args = create_test_args(false, false, false, vec![]);
+        let char_set = build_char_set(&args).unwrap();
+        let params = GenerationParams {
+            length: 100,
+            count: 100,
+            weights: Some(ClassWeights {
+                letters: 8,
+                digits: 1,
+                symbols: 1,
+            }),
+            ..Default::default()
+        };
+
+        let mut rng = StdRng::seed_from_u64(328);
+        let passwords = generate_passwords(&char_set, &params, &mut rng).unwrap();
+        let total = 10_000.0;
+        let fraction = |f: fn(&u8) -> bool| {
+            passwords
+                .iter()
+                .map(|p| p.bytes().filter(f).count())
+                .sum::<usize>() as f64
+                / total
+        };
+        assert!((fraction(u8::is_ascii_alphabetic) - 0.8).abs() < 0.03);
+        assert!((fraction(u8::is_ascii_digit) - 0.1).abs() < 0.03);
+        assert!((fraction(u8::is_ascii_punctuation) - 0.1).abs() < 0.03);
+
+        // A zero weight removes the class from the fill
+        let params = GenerationParams {
+            weights: Some(ClassWeights {
+                letters: 1,
+                digits: 0,
+                symbols: 0,
+            }),
+            ..params
+        };
+        for pass in generate_passwords(&char_set, &params, &mut rng).unwrap() {
+            assert!(pass.bytes().all(|b| b.is_ascii_alphabetic()), "{}", pass);
+        }
+
+        // Nothing left to draw from
+        let digits: Vec<u8> = (b'0'..=b'9').collect();
+        assert!(matches!(
+            generate_passwords(&digits, &params, &mut rng).unwrap_err(),
+            PasswordError::InvalidWeights
+        ));
+    }
+
+    /// Pins seeded output for every generation mode
+    ///
+    /// These values are the RNG-draw contract documented on
+    /// `generate_passwords`. A failure here means seeded output changed for
+    /// existing users: only update the expected values deliberately, with a
+    /// CHANGELOG entry.
+    #[test]
+    fn test_seeded_output_is_pinned() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let char_set = build_char_set(&create_test_args(false, false, false, vec![])).unwrap();
+        let seeded = |params: GenerationParams| {
+            let params = GenerationParams {
+                length: 12,
+                count: 3,
+                ..params
+            };
+            generate_passwords(&char_set, &params, &mut StdRng::seed_from_u64(42)).unwrap()
+        };
+
+        assert_eq!(
+            seeded(GenerationParams::default()),
+            ["|6>~NMZXxdm7", "m:4<r>7VKT^a", "b!R8eW.X/p7u"]
+        );
+        assert_eq!(
+            seeded(GenerationParams {
+                min_capitals: Some(2),
+                min_numerals: Some(2),
+                min_symbols: Some(1),
+                ..Default::default()
+            }),
+            ["|6`~NM5N2dD7", "1:4WE>6VK:^a", "b!R8eWUN8p?2"]
+        );
+        assert_eq!(
+            seeded(GenerationParams {
+                pattern: Some(parse_pattern("LLUUNNSS").unwrap()),
+                ..Default::default()
+            }),
+            ["dnGO86~-", "zaQK37&`", "qdMA89>;"]
+        );
+        assert_eq!(
+            seeded(GenerationParams {
+                mask: Some(parse_mask("[A-F]{2}-[0-9]{3}").unwrap()),
+                ..Default::default()
+            }),
+            ["AD-586", "FC-064", "CE-861"]
+        );
+        assert_eq!(
+            seeded(GenerationParams {
+                weights: Some(ClassWeights {
+                    letters: 1,
+                    digits: 4,
+                    symbols: 0,
+                }),
+                ..Default::default()
+            }),
+            ["S75096414B50", "2649eP7I844i", "42485296P4UI"]
+        );
+        assert_eq!(
+            seeded(GenerationParams {
+                first_char_alpha: true,
+                ..Default::default()
+            }),
+            ["M6>~N|ZXxdm7", "m%7:a>TKV4<^", "b!R8eW.X/p7u"]
+        );
+        // Constraints that reject nothing draw nothing extra
+        assert_eq!(
+            seeded(GenerationParams {
+                exclude_similar_to: Some("aaaaaaaaaaaa".to_string()),
+                unique: true,
+                ..Default::default()
+            }),
+            ["|6>~NMZXxdm7", "m:4<r>7VKT^a", "b!R8eW.X/p7u"]
+        );
+        assert_eq!(
+            seeded(GenerationParams {
+                all_unique_chars: true,
+                ..Default::default()
+            }),
+            ["mXy0?9~Q|lfT", "G(t=8qWh;_e1", ">L&p/Z;A!kx2"]
+        );
+        assert_eq!(
+            seeded(GenerationParams {
+                max_symbols: Some(1),
+                ..Default::default()
+            }),
+            ["06>iNMZXxdm7", "nKj4NPaTVXC.", "iUXyGCRfVM8<"]
+        );
+        // Single-class sets keep one pick per character, as in 1.1.1
+        let digits: Vec<u8> = b"0123456789".to_vec();
+        let params = GenerationParams {
+            length: 6,
+            count: 3,
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_passwords(&digits, &params, &mut StdRng::seed_from_u64(42)).unwrap(),
+            ["285651", "946034", "168014"]
+        );
+        let hex: Vec<u8> = b"abcdef".to_vec();
+        assert_eq!(
+            generate_passwords(&hex, &params, &mut StdRng::seed_from_u64(42)).unwrap(),
+            ["bfddda", "fcdacc", "adfabc"]
+        );
+        // and take the fill_bytes path only with bulk_bytes
+        let params = GenerationParams {
+            bulk_bytes: true,
+            ..params
+        };
+        assert_eq!(
+            generate_passwords(&digits, &params, &mut StdRng::seed_from_u64(42)).unwrap(),
+            ["269499", "513390", "921236"]
+        );
+
+        let mut rng = StdRng::seed_from_u64(42);
+        let params = MemorableParams {
+            words: 2,
+            digits: 2,
+            symbol: true,
+        };
+        let memorable: Vec<String> = (0..3)
+            .map(|_| generate_memorable(&params, &mut rng))
+            .collect();
+        assert_eq!(
+            memorable,
+            ["CandyKayak25*", "MuffinWolf49!", "MooseGecko37@"]
+        );
+    }
+
+    #[test]
+    fn test_weighted_fill_entropy() {
+        let args = create_test_args(false, false, false, vec![]);
+        let char_set = build_char_set(&args).unwrap();
 
-        // Character set with no uppercase
-        let char_set = vec![b'a', b'b', b'0', b'1', b'!', b'@'];
-        let pattern = vec![PatternChar::Uppercase]; // Will fallback to char_set
+        // Weights proportional to the pool sizes are as good as uniform
+        let proportional = ClassWeights {
+            letters: 52,
+            digits: 10,
+            symbols: 32,
+        };
+        assert!((weighted_fill_entropy(&char_set, &proportional) - 94f64.log2()).abs() < 1e-9);
 
-        let mut rng = StdRng::seed_from_u64(789);
-        let password = generate_password_from_pattern(&char_set, &pattern, &mut rng);
+        // Any other bias costs entropy
+        let skewed = ClassWeights {
+            letters: 8,
+            digits: 1,
+            symbols: 1,
+        };
+        let bits = weighted_fill_entropy(&char_set, &skewed);
+        let expected = 0.8 * (52f64.log2() - 0.8f64.log2())
+            + 0.1 * (10f64.log2() - 0.1f64.log2())
+            + 0.1 * (32f64.log2() - 0.1f64.log2());
+        assert!((bits - expected).abs() < 1e-9);
+        assert!(bits < 94f64.log2());
 
-        assert_eq!(password.len(), 1);
-        assert!(char_set.contains(&(password.chars().next().unwrap() as u8)));
+        let params = GenerationParams {
+            length: 16,
+            count: 1,
+            min_numerals: Some(2),
+            weights: Some(skewed),
+            ..Default::default()
+        };
+        let explanation = explain_entropy(&char_set, &params);
+        assert_eq!(explanation.deductions.len(), 2);
+        let expected = 2.0 * 10f64.log2() + 14.0 * bits;
+        assert!((explanation.total_bits() - expected).abs() < 1e-9);
     }
 
     #[test]
-    fn test_generate_password_from_pattern_empty_numeric() {
-        use rand::{SeedableRng, rngs::StdRng};
+    fn test_find_sequences() {
+        assert_eq!(find_sequences("xabcx321"), vec!["abc", "321"]);
+        assert_eq!(find_sequences("aBcD"), vec!["aBcD"]);
+        // Too short, broken by a class change, or not alphanumeric
+        assert!(find_sequences("ab9a").is_empty());
+        assert!(find_sequences("89a").is_empty());
+        assert!(find_sequences("!\"#").is_empty());
+        assert!(contains_sequence("pass1234"));
+        assert!(!contains_sequence("p4ss"));
+
+        assert!(contains_repeats("paaass"));
+        assert!(!contains_repeats("paass"));
+    }
 
-        // Character set with no numeric
-        let char_set = vec![b'a', b'b', b'A', b'B', b'!', b'@'];
-        let pattern = vec![PatternChar::Numeric]; // Will fallback to char_set
+    #[test]
+    fn test_check_password() {
+        let weak = check_password("secret");
+        assert_eq!(weak.pool_size, 26);
+        assert_eq!(weak.strength, Strength::Weak);
+        assert!(weak.sequences.is_empty());
+
+        let strong = check_password("vR7#qLp2!zWm9$Tx");
+        assert_eq!(strong.length, 16);
+        assert_eq!(strong.pool_size, 94);
+        assert_eq!(strong.strength, Strength::Strong);
+        assert!(!strong.has_repeats);
+
+        assert_eq!(check_password("").strength, Strength::VeryWeak);
+        assert_eq!(Strength::from_entropy(128.0), Strength::VeryStrong);
+    }
 
-        let mut rng = StdRng::seed_from_u64(1011);
-        let password = generate_password_from_pattern(&char_set, &pattern, &mut rng);
+    #[test]
+    fn test_generate_memorable_structure() {
+        use rand::{SeedableRng, rngs::StdRng};
 
-        assert_eq!(password.len(), 1);
-        assert!(char_set.contains(&(password.chars().next().unwrap() as u8)));
+        let params = MemorableParams {
+            words: 2,
+            digits: 3,
+            symbol: true,
+        };
+        let mut rng = StdRng::seed_from_u64(302);
+
+        for _ in 0..50 {
+            let pass = generate_memorable(&params, &mut rng);
+            let chars: Vec<char> = pass.chars().collect();
+            let n = chars.len();
+
+            // Capitalized word(s), then digits, then one symbol
+            assert!(chars[0].is_ascii_uppercase(), "{}", pass);
+            assert!(
+                MEMORABLE_SYMBOLS.contains(&(chars[n - 1] as u8)),
+                "{}",
+                pass
+            );
+            assert!(
+                chars[n - 4..n - 1].iter().all(|c| c.is_ascii_digit()),
+                "{}",
+                pass
+            );
+            assert!(
+                chars[..n - 4].iter().all(|c| c.is_ascii_alphabetic()),
+                "{}",
+                pass
+            );
+            assert_eq!(
+                chars[..n - 4]
+                    .iter()
+                    .filter(|c| c.is_ascii_uppercase())
+                    .count(),
+                2
+            );
+        }
     }
 
     #[test]
-    fn test_generate_password_from_pattern_empty_symbols() {
+    fn test_generate_memorable_without_symbol() {
         use rand::{SeedableRng, rngs::StdRng};
 
-        // Character set with no symbols
-        let char_set = vec![b'a', b'b', b'A', b'B', b'0', b'1'];
-        let pattern = vec![PatternChar::Symbol]; // Will fallback to char_set
+        let params = MemorableParams {
+            words: 1,
+            digits: 0,
+            symbol: false,
+        };
+        let mut rng = StdRng::seed_from_u64(303);
+        let pass = generate_memorable(&params, &mut rng);
 
-        let mut rng = StdRng::seed_from_u64(1213);
-        let password = generate_password_from_pattern(&char_set, &pattern, &mut rng);
+        assert!(pass.chars().all(|c| c.is_ascii_alphabetic()));
+        assert!(WORDLIST.contains(&pass.to_ascii_lowercase().as_str()));
+    }
 
-        assert_eq!(password.len(), 1);
-        assert!(char_set.contains(&(password.chars().next().unwrap() as u8)));
+    #[test]
+    fn test_memorable_entropy() {
+        let params = MemorableParams {
+            words: 2,
+            digits: 2,
+            symbol: true,
+        };
+        // 2 words * 8 bits + 2 digits * log2(10) + log2(8) symbol bits
+        let expected = 16.0 + 2.0 * 10f64.log2() + 3.0;
+        assert!((memorable_entropy(&params) - expected).abs() < 1e-9);
     }
 
     #[test]
-    fn test_generate_password_with_minimums() {
-        use rand::{SeedableRng, rngs::StdRng};
+    fn test_batch_stats() {
+        let passwords = ["aA1!".to_string(), "aaaa".to_string()];
+        let stats = batch_stats(&passwords);
+        assert_eq!(stats.count, 2);
+        assert_eq!(stats.characters, 8);
+        assert_eq!(
+            stats.frequencies.into_iter().collect::<Vec<_>>(),
+            [('!', 1), ('1', 1), ('A', 1), ('a', 5)]
+        );
+        assert_eq!(
+            (
+                stats.lowercase,
+                stats.uppercase,
+                stats.digits,
+                stats.symbols
+            ),
+            (5, 1, 1, 1)
+        );
+        // "aaaa" draws from 26 characters, "aA1!" from 94
+        let low = 4.0 * 26f64.log2();
+        let high = 4.0 * 94f64.log2();
+        assert!((stats.min_entropy - low).abs() < 1e-9);
+        assert!((stats.max_entropy - high).abs() < 1e-9);
+        assert!((stats.mean_entropy - (low + high) / 2.0).abs() < 1e-9);
+
+        let text = batch_stats(&passwords).to_string();
+        assert!(text.contains("Batch statistics: 2 passwords, 8 characters"));
+        assert!(text.contains("Lowercase: 5 (62.5%)"));
+        assert!(text.contains("    a 5 (62.50%)"));
+
+        let empty = batch_stats(&[]);
+        assert_eq!((empty.min_entropy, empty.mean_entropy), (0.0, 0.0));
+    }
 
-        let char_set = vec![
-            b'a', b'b', b'c', // lowercase
-            b'A', b'B', b'C', // uppercase
-            b'0', b'1', b'2', // numeric
-            b'!', b'@', b'#', // symbols
-        ];
+    #[test]
+    fn test_derive_seed() {
+        // SplitMix64 seeded with 0 starts with this value
+        assert_eq!(derive_seed(0, 0), 0xE220_A839_7B1D_CDAF);
+        let seeds: BTreeSet<u64> = (0..1000).map(|i| derive_seed(42, i)).collect();
+        assert_eq!(seeds.len(), 1000);
+        assert_ne!(derive_seed(42, 0), derive_seed(43, 0));
+    }
 
-        let mut rng = StdRng::seed_from_u64(456);
-        let password =
-            generate_password_with_minimums(&char_set, 10, Some(2), Some(2), Some(2), &mut rng);
+    /// An RNG that yields a fixed sequence: the first `u32` is `.0`, and
+    /// each one after it adds `.1` (wrapping); bytes are the low 8 bits
+    struct StepRng(u32, u32);
 
-        assert_eq!(password.len(), 10);
+    impl rand::RngCore for StepRng {
+        fn next_u32(&mut self) -> u32 {
+            let value = self.0;
+            self.0 = value.wrapping_add(self.1);
+            value
+        }
 
-        // Count character types
-        let mut capitals = 0;
-        let mut numerals = 0;
-        let mut symbols = 0;
+        fn next_u64(&mut self) -> u64 {
+            u64::from(self.next_u32())
+        }
 
-        for c in password.chars() {
-            if c.is_ascii_uppercase() {
-                capitals += 1;
-            } else if c.is_ascii_digit() {
-                numerals += 1;
-            } else if !c.is_alphanumeric() {
-                symbols += 1;
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            for byte in dest {
+                *byte = self.next_u32() as u8;
             }
         }
-
-        assert!(capitals >= 2);
-        assert!(numerals >= 2);
-        assert!(symbols >= 2);
     }
 
     #[test]
-    fn test_generate_password_with_minimums_empty_sets() {
-        use rand::{SeedableRng, rngs::StdRng};
+    fn test_run_with_rng() {
+        // pick maps a draw x to char_set[x * n / 2^32], so quarter steps of
+        // the u32 range fill with a, b, c, d in turn before the shuffle
+        let args = PasswordArgs {
+            include_chars: Some("abcd".chars().collect()),
+            length: 10,
+            password_count: 2,
+            ..Default::default()
+        };
+        let passwords = run_with_rng(&args, &mut StepRng(0, 1 << 30)).unwrap();
+        assert_eq!(passwords, ["babcdabcda", "adabcdabcd"]);
 
-        // Only lowercase available
-        let char_set = vec![b'a', b'b', b'c'];
+        let passwords = run_with_rng(&args, &mut StepRng(3 << 30, 1 << 30)).unwrap();
+        assert_eq!(passwords, ["adabcdabcd", "dcdabcdabc"]);
 
-        let mut rng = StdRng::seed_from_u64(789);
-        let password =
-            generate_password_with_minimums(&char_set, 5, Some(2), Some(2), Some(2), &mut rng);
+        // Exactly what generate_passwords gives for the same RNG state
+        let args = PasswordArgs {
+            length: 16,
+            password_count: 5,
+            min_numerals: Some(3),
+            ..Default::default()
+        };
+        let char_set = build_char_set(&args).unwrap();
+        let params = GenerationParams {
+            length: 16,
+            count: 5,
+            min_numerals: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(
+            run_with_rng(&args, &mut StepRng(396, 0x9E37_79B9)).unwrap(),
+            generate_passwords(&char_set, &params, &mut StepRng(396, 0x9E37_79B9)).unwrap()
+        );
 
-        assert_eq!(password.len(), 5);
-        // All should be lowercase since that's all available
-        for c in password.chars() {
-            assert!(c.is_ascii_lowercase());
-        }
+        let invalid = PasswordArgs { length: 0, ..args };
+        assert!(matches!(
+            run_with_rng(&invalid, &mut StepRng(0, 1)),
+            Err(PasswordError::InvalidLength)
+        ));
     }
 
     #[test]
-    fn test_generate_password_with_minimums_no_minimums() {
+    fn test_generate_passwords_chunked() {
         use rand::{SeedableRng, rngs::StdRng};
 
-        let char_set = vec![b'a', b'b', b'c', b'A', b'B', b'0', b'1', b'!', b'@'];
+        let char_set = build_char_set(&create_test_args(false, false, false, vec![])).unwrap();
+        let params = GenerationParams {
+            length: 12,
+            count: GENERATION_CHUNK * 2 + 5,
+            ..Default::default()
+        };
+        let passwords = generate_passwords_chunked(&char_set, &params, 395).unwrap();
+        assert_eq!(passwords.len(), params.count as usize);
+        assert_eq!(
+            passwords,
+            generate_passwords_chunked(&char_set, &params, 395).unwrap()
+        );
 
-        let mut rng = StdRng::seed_from_u64(101);
-        let password = generate_password_with_minimums(&char_set, 8, None, None, None, &mut rng);
+        // Each chunk is an ordinary batch from its own derived seed
+        let mut rng = StdRng::seed_from_u64(derive_seed(395, 2));
+        let last = GenerationParams {
+            count: 5,
+            ..params.clone()
+        };
+        let tail = generate_passwords(&char_set, &last, &mut rng).unwrap();
+        assert_eq!(passwords[GENERATION_CHUNK as usize * 2..], tail[..]);
 
-        assert_eq!(password.len(), 8);
+        let empty = GenerationParams { count: 0, ..params };
+        assert!(
+            generate_passwords_chunked(&char_set, &empty, 395)
+                .unwrap()
+                .is_empty()
+        );
     }
 
     #[test]
-    fn test_generate_passwords() {
-        use rand::{SeedableRng, rngs::StdRng};
+    #[cfg(feature = "rayon")]
+    fn test_generate_passwords_parallel() {
+        let char_set = build_char_set(&create_test_args(false, false, false, vec![])).unwrap();
+        for count in [1, GENERATION_CHUNK, GENERATION_CHUNK * 3 + 7] {
+            let params = GenerationParams {
+                length: 16,
+                count,
+                min_numerals: Some(2),
+                ..Default::default()
+            };
+            assert_eq!(
+                generate_passwords_parallel(&char_set, &params, 395).unwrap(),
+                generate_passwords_chunked(&char_set, &params, 395).unwrap()
+            );
+        }
+    }
 
-        let char_set = vec![b'a', b'b', b'c', b'1', b'2', b'3'];
-        let params = GenerationParams {
-            length: 5,
-            count: 3,
-            min_capitals: None,
-            min_numerals: None,
-            min_symbols: None,
-            pattern: None,
-        };
+    #[test]
+    fn test_homoglyphs_table() {
+        let mut confusables = BTreeSet::new();
+        for &(c, latin) in HOMOGLYPHS {
+            assert!(!c.is_ascii() && latin.is_ascii_alphanumeric(), "{}", c);
+            assert!(confusables.insert(c), "duplicate {}", c);
+        }
 
-        let mut rng = StdRng::seed_from_u64(202);
-        let passwords = generate_passwords(&char_set, &params, &mut rng);
+        // Every confusable belongs to a script rpg can generate
+        let scripts: Vec<char> = [Script::Greek, Script::Cyrillic]
+            .into_iter()
+            .flat_map(|s| [s.lowercase(), s.uppercase()].concat())
+            .collect();
+        assert!(confusables.iter().all(|c| scripts.contains(c)));
+    }
 
-        assert_eq!(passwords.len(), 3);
-        for pass in &passwords {
-            assert_eq!(pass.len(), 5);
-        }
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("abc123"), "abc123");
+        assert_eq!(shell_quote("a$b"), "'a$b'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(shell_quote(""), "''");
     }
 
     #[test]
-    fn test_generate_passwords_with_pattern() {
-        use rand::{SeedableRng, rngs::StdRng};
+    fn test_format_columns() {
+        let passwords = vec!["aa".to_string(), "bbbb".to_string(), "c".to_string()];
+        assert_eq!(format_columns(&passwords, 1, false), "aa\nbbbb\nc\n");
+        assert_eq!(
+            format_columns(&passwords, 2, true),
+            "Printing 3 passwords in 2 columns\naa   bbbb\nc    \n"
+        );
+    }
 
-        let char_set = vec![b'a', b'b', b'A', b'B', b'0', b'1', b'!', b'@'];
-        let pattern = vec![
-            PatternChar::Lowercase,
-            PatternChar::Uppercase,
-            PatternChar::Numeric,
-            PatternChar::Symbol,
-        ];
-        let params = GenerationParams {
-            length: 4,
-            count: 2,
-            min_capitals: None,
-            min_numerals: None,
-            min_symbols: None,
-            pattern: Some(pattern),
-        };
+    #[test]
+    fn test_print_columns_streaming_matches_batch() {
+        let passwords: Vec<String> = (0..7).map(|i| format!("pass{:04}", i)).collect();
+        for columns in [1, 3, 7, 8] {
+            let mut out = Vec::new();
+            write_columns_streaming(&mut out, passwords.clone().into_iter(), columns, 8).unwrap();
+            assert_eq!(
+                String::from_utf8(out).unwrap(),
+                format_columns(&passwords, columns, false)
+            );
+        }
 
-        let mut rng = StdRng::seed_from_u64(303);
-        let passwords = generate_passwords(&char_set, &params, &mut rng);
+        // A password longer than the hint isn't truncated
+        let mut out = Vec::new();
+        let mixed = ["ab", "abcdef", "cd"].map(String::from);
+        write_columns_streaming(&mut out, mixed.into_iter(), 2, 2).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "ab abcdef\ncd \n");
+        print_columns_streaming(std::iter::empty(), 4, 16);
+    }
 
-        assert_eq!(passwords.len(), 2);
-        for pass in &passwords {
-            assert_eq!(pass.len(), 4);
-        }
+    #[test]
+    fn test_colorize() {
+        assert_eq!(
+            colorize("ab12!c"),
+            format!(
+                "{LETTER_COLOR}ab{COLOR_RESET}{DIGIT_COLOR}12{COLOR_RESET}{SYMBOL_COLOR}!{COLOR_RESET}{LETTER_COLOR}c{COLOR_RESET}"
+            )
+        );
+        assert_eq!(
+            colorize("a b"),
+            format!("{LETTER_COLOR}a{COLOR_RESET} {LETTER_COLOR}b{COLOR_RESET}")
+        );
+        assert_eq!(colorize(""), "");
+        assert_eq!(display_width(&colorize("ab12!c")), 6);
+
+        // Columns line up by what shows on screen
+        let colored = [colorize("a1"), colorize("b!"), colorize("cd")];
+        let plain = ["a1", "b!", "cd"].map(String::from);
+        let table = format_columns(&colored, 2, false);
+        assert_eq!(
+            table
+                .replace(COLOR_RESET, "")
+                .replace(LETTER_COLOR, "")
+                .replace(DIGIT_COLOR, "")
+                .replace(SYMBOL_COLOR, ""),
+            format_columns(&plain, 2, false)
+        );
     }
 
     #[test]
-    fn test_generate_passwords_with_minimums() {
-        use rand::{SeedableRng, rngs::StdRng};
+    fn test_format_with_entropy() {
+        let passwords = vec!["aa".to_string(), "bbbb".to_string()];
+        assert_eq!(
+            format_with_entropy(&passwords, &[12.0, 26.34]),
+            "aa     12.0 bits\nbbbb   26.3 bits\n"
+        );
+    }
 
-        let char_set = vec![
-            b'a', b'b', b'c', b'A', b'B', b'C', b'0', b'1', b'2', b'!', b'@', b'#',
-        ];
-        let params = GenerationParams {
-            length: 8,
-            count: 2,
-            min_capitals: Some(1),
-            min_numerals: Some(1),
-            min_symbols: Some(1),
-            pattern: None,
-        };
+    #[test]
+    fn test_apply_template_placeholders() {
+        assert_eq!(
+            apply_template("user{n}:{password}", "s3cr3t", 7, 0.0).unwrap(),
+            "user7:s3cr3t"
+        );
+        assert_eq!(
+            apply_template("{password} ({entropy} bits)", "abc", 1, 14.1016).unwrap(),
+            "abc (14.1 bits)"
+        );
+        assert_eq!(
+            apply_template("len={length}", "abcdef", 1, 0.0).unwrap(),
+            "len=6"
+        );
+        assert_eq!(apply_template("plain", "abc", 1, 0.0).unwrap(), "plain");
+    }
 
-        let mut rng = StdRng::seed_from_u64(404);
-        let passwords = generate_passwords(&char_set, &params, &mut rng);
+    #[test]
+    fn test_apply_template_escaped_braces() {
+        assert_eq!(
+            apply_template("{{\"pw\": \"{password}\"}}", "abc", 1, 0.0).unwrap(),
+            "{\"pw\": \"abc\"}"
+        );
+        // The password itself is never re-parsed
+        assert_eq!(apply_template("{password}", "{n}", 3, 0.0).unwrap(), "{n}");
+    }
 
-        assert_eq!(passwords.len(), 2);
-        for pass in &passwords {
-            assert_eq!(pass.len(), 8);
-            // Verify minimums are met
-            let mut has_capital = false;
-            let mut has_numeral = false;
-            let mut has_symbol = false;
-            for c in pass.chars() {
-                if c.is_ascii_uppercase() {
-                    has_capital = true;
-                } else if c.is_ascii_digit() {
-                    has_numeral = true;
-                } else if !c.is_alphanumeric() {
-                    has_symbol = true;
-                }
-            }
-            assert!(has_capital);
-            assert!(has_numeral);
-            assert!(has_symbol);
-        }
+    #[test]
+    fn test_apply_template_errors() {
+        assert!(
+            apply_template("{pass}", "abc", 1, 0.0)
+                .unwrap_err()
+                .contains("Unknown placeholder '{pass}'")
+        );
+        assert!(apply_template("{password", "abc", 1, 0.0).is_err());
+        assert!(apply_template("a}b", "abc", 1, 0.0).is_err());
     }
 
     #[test]
@@ -1328,7 +4536,7 @@ mod tests {
         let msg = err.to_string();
         assert!(msg.contains("Password length must be greater than 0"));
 
-        let err = PasswordError::InvalidLengthTooLong;
+        let err = PasswordError::InvalidLengthTooLong { max: 10_000 };
         let msg = err.to_string();
         assert!(msg.contains("exceeds maximum of 10,000"));
 
@@ -1347,6 +4555,111 @@ mod tests {
         assert!(msg.contains("Hint"));
     }
 
+    #[test]
+    fn test_password_error_codes() {
+        let cases = [
+            (PasswordError::InvalidLength, "invalid_length", 10),
+            (
+                PasswordError::InvalidLengthTooLong { max: 10_000 },
+                "length_too_long",
+                11,
+            ),
+            (PasswordError::InvalidMaxLength, "invalid_max_length", 12),
+            (PasswordError::InvalidCount, "invalid_count", 13),
+            (PasswordError::EmptyCharacterSet, "empty_character_set", 14),
+            (PasswordError::AllTypesDisabled, "all_types_disabled", 15),
+            (
+                PasswordError::RetryLimitExceeded {
+                    constraint: "unique",
+                    attempts: 1,
+                },
+                "retry_limit_exceeded",
+                16,
+            ),
+            (
+                PasswordError::KeyspaceTooSmall {
+                    keyspace: 1,
+                    count: 2,
+                },
+                "keyspace_too_small",
+                17,
+            ),
+            (
+                PasswordError::MixedCaseUnavailable,
+                "mixed_case_unavailable",
+                18,
+            ),
+            (
+                PasswordError::FirstCharAlphaUnavailable,
+                "first_char_alpha_unavailable",
+                19,
+            ),
+            (PasswordError::InvalidWeights, "invalid_weights", 20),
+            (
+                PasswordError::ControlCharacter { byte: 0x09 },
+                "control_character",
+                21,
+            ),
+            (PasswordError::SpaceNotAllowed, "space_not_allowed", 22),
+            (
+                PasswordError::TooManyRequiredTypes {
+                    required: 4,
+                    length: 3,
+                },
+                "too_many_required_types",
+                23,
+            ),
+            (
+                PasswordError::LengthExceedsCharacterSet {
+                    length: 95,
+                    available: 94,
+                },
+                "length_exceeds_character_set",
+                24,
+            ),
+            (
+                PasswordError::InvalidSymbol { ch: 'a' },
+                "invalid_symbol",
+                25,
+            ),
+            (
+                PasswordError::MinimumAboveMaximum {
+                    class: "symbols",
+                    min: 3,
+                    max: 2,
+                },
+                "minimum_above_maximum",
+                26,
+            ),
+            (
+                PasswordError::MaximumsBelowLength {
+                    total: 4,
+                    length: 8,
+                },
+                "maximums_below_length",
+                27,
+            ),
+            (
+                PasswordError::NonAsciiCharacter { ch: 'é' },
+                "non_ascii_character",
+                28,
+            ),
+            (
+                PasswordError::MinimumUnavailable {
+                    class: "symbols",
+                    min: 2,
+                    included: true,
+                },
+                "minimum_unavailable",
+                29,
+            ),
+        ];
+        for (err, code, exit_code) in cases {
+            assert_eq!(err.code(), code);
+            assert_eq!(err.exit_code(), exit_code, "{}", code);
+        }
+    }
+
     #[test]
     fn test_password_error_source() {
         // Test that PasswordError implements std::error::Error
@@ -1363,7 +4676,15 @@ mod tests {
         // Request 5 minimums but length is only 4
         // Minimums take precedence, so password will be length 5
         let mut rng = StdRng::seed_from_u64(1001);
-        let password = generate_password_with_minimums(&char_set, 4, Some(5), None, None, &mut rng);
+        let password = generate_password_with_minimums(
+            &char_set,
+            &CharClasses::from_char_set(&char_set),
+            4,
+            Some(5),
+            None,
+            None,
+            &mut rng,
+        );
 
         // Should generate a password with at least 5 capitals (minimum takes precedence)
         assert!(password.len() >= 5);
@@ -1381,8 +4702,15 @@ mod tests {
         // Request min_capitals=3, min_numerals=3, min_symbols=3, but length=6
         // Minimums take precedence, so password will be at least length 9
         let mut rng = StdRng::seed_from_u64(1002);
-        let password =
-            generate_password_with_minimums(&char_set, 6, Some(3), Some(3), Some(3), &mut rng);
+        let password = generate_password_with_minimums(
+            &char_set,
+            &CharClasses::from_char_set(&char_set),
+            6,
+            Some(3),
+            Some(3),
+            Some(3),
+            &mut rng,
+        );
 
         // Password length should be at least 9 (sum of minimums)
         // May be more if minimums are applied then filled up to length
@@ -1404,8 +4732,15 @@ mod tests {
         let char_set = vec![b'a', b'b', b'A', b'B', b'0', b'1', b'!', b'@'];
         // Request min_capitals=2, min_numerals=2, length=4
         let mut rng = StdRng::seed_from_u64(1003);
-        let password =
-            generate_password_with_minimums(&char_set, 4, Some(2), Some(2), None, &mut rng);
+        let password = generate_password_with_minimums(
+            &char_set,
+            &CharClasses::from_char_set(&char_set),
+            4,
+            Some(2),
+            Some(2),
+            None,
+            &mut rng,
+        );
 
         assert_eq!(password.len(), 4);
         let capitals = password.chars().filter(|c| c.is_ascii_uppercase()).count();
@@ -1415,6 +4750,64 @@ mod tests {
         assert!(numerals >= 2);
     }
 
+    #[test]
+    fn test_generate_password_with_minimums_zero_same_as_none() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let char_set = vec![b'a', b'b', b'A', b'B', b'0', b'1', b'!', b'@'];
+        let classes = CharClasses::from_char_set(&char_set);
+
+        let mut rng = StdRng::seed_from_u64(311);
+        let with_none =
+            generate_password_with_minimums(&char_set, &classes, 12, None, None, None, &mut rng);
+        let mut rng = StdRng::seed_from_u64(311);
+        let with_zero = generate_password_with_minimums(
+            &char_set,
+            &classes,
+            12,
+            Some(0),
+            Some(0),
+            Some(0),
+            &mut rng,
+        );
+
+        // Same output and same RNG consumption
+        assert_eq!(with_none, with_zero);
+        assert_eq!(with_zero.len(), 12);
+    }
+
+    #[test]
+    fn test_generate_password_with_minimums_sum_equals_length() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        // No room for fill characters: output is exactly the required classes
+        let char_set = vec![b'a', b'b', b'A', b'B', b'0', b'1', b'!', b'@'];
+        let classes = CharClasses::from_char_set(&char_set);
+
+        let mut rng = StdRng::seed_from_u64(312);
+        let password = generate_password_with_minimums(
+            &char_set,
+            &classes,
+            6,
+            Some(2),
+            Some(2),
+            Some(2),
+            &mut rng,
+        );
+
+        assert_eq!(password.len(), 6);
+        assert_eq!(
+            password.chars().filter(|c| c.is_ascii_uppercase()).count(),
+            2
+        );
+        assert_eq!(password.chars().filter(|c| c.is_ascii_digit()).count(), 2);
+        assert_eq!(password.chars().filter(|c| !c.is_alphanumeric()).count(), 2);
+        assert_eq!(
+            password.chars().filter(|c| c.is_ascii_lowercase()).count(),
+            0
+        );
+    }
+
     #[test]
     fn test_generate_password_from_pattern_all_same_type() {
         use rand::{SeedableRng, rngs::StdRng};
@@ -1427,7 +4820,12 @@ mod tests {
         ];
 
         let mut rng = StdRng::seed_from_u64(2001);
-        let password = generate_password_from_pattern(&char_set, &pattern, &mut rng);
+        let password = generate_password_from_pattern(
+            &char_set,
+            &CharClasses::from_char_set(&char_set),
+            &pattern,
+            &mut rng,
+        );
 
         assert_eq!(password.len(), 3);
         for c in password.chars() {
@@ -1451,7 +4849,12 @@ mod tests {
             .collect();
 
         let mut rng = StdRng::seed_from_u64(2002);
-        let password = generate_password_from_pattern(&char_set, &pattern, &mut rng);
+        let password = generate_password_from_pattern(
+            &char_set,
+            &CharClasses::from_char_set(&char_set),
+            &pattern,
+            &mut rng,
+        );
 
         assert_eq!(password.len(), 100);
     }
@@ -1472,6 +4875,42 @@ mod tests {
         print_columns(passwords, 5, false);
     }
 
+    #[test]
+    fn test_build_char_set_lowercase_off() {
+        let mut args = create_test_args(false, true, true, vec![]);
+        args.lowercase_off = true;
+        assert_eq!(
+            build_char_set(&args).unwrap(),
+            (b'A'..=b'Z').collect::<Vec<u8>>()
+        );
+
+        args.capitals_off = true;
+        args.numerals_off = false;
+        assert_eq!(build_char_set(&args).unwrap(), b"0123456789");
+
+        args.symbols_off = false;
+        let char_set = build_char_set(&args).unwrap();
+        assert_eq!(char_set.len(), 10 + 32);
+        assert!(!char_set.iter().any(u8::is_ascii_alphabetic));
+
+        let greek = build_script_char_set(&args, Script::Greek).unwrap();
+        assert!(!greek.iter().any(|c| c.is_lowercase()));
+    }
+
+    #[test]
+    fn test_validate_args_all_four_types_disabled() {
+        let mut args = create_test_args(true, true, true, vec![]);
+        args.lowercase_off = true;
+        assert!(matches!(
+            validate_args(&args),
+            Err(PasswordError::AllTypesDisabled)
+        ));
+
+        // Included characters override the type flags
+        args.include_chars = Some(vec!['x', 'y']);
+        assert!(validate_args(&args).is_ok());
+    }
+
     #[test]
     fn test_validate_args_all_types_disabled_lowercase_available() {
         // Test validate_args when all types are disabled but lowercase available
@@ -1,11 +1,37 @@
-use clap::Parser;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, ColorChoice, CommandFactory, FromArgMatches, Parser, Subcommand};
+use rand::rngs::{OsRng, StdRng};
+use rand::{Rng, RngCore, SeedableRng, TryRngCore};
+#[cfg(feature = "argon2")]
+use rpg_util::argon2_hash;
+#[cfg(feature = "bcrypt")]
+use rpg_util::bcrypt_hash;
+#[cfg(feature = "rayon")]
+use rpg_util::generate_passwords_parallel;
+#[cfg(feature = "hash")]
+use rpg_util::sha256_hex;
 use rpg_util::{
-    GenerationParams, PasswordArgs, build_char_set, calculate_entropy, column_count,
-    generate_passwords, parse_exclude_chars, parse_pattern, print_columns, validate_args,
+    ClassWeights, DEFAULT_GUESSES_PER_SECOND, EXAMPLES, GenerationParams, HOMOGLYPHS,
+    MemorableParams, PasswordArgs, PasswordCheck, PasswordError, Policy, PolicyClass,
+    SHELL_UNSAFE_CHARS, Script, Strength, URL_SAFE_ALPHABET, apply_template, batch_stats,
+    build_script_char_set, calculate_entropy, charset_version, check_password, colorize,
+    column_count, crack_time, derive_seed, explain_entropy, format_columns, format_with_entropy,
+    generate_memorable, generate_passwords, generate_passwords_into, length_for_entropy,
+    luhn_check_digit, memorable_entropy, parse_exclude_chars, parse_exclude_classes, parse_mask,
+    parse_pattern, reservoir_sample, restrict_mask, shannon_entropy, shell_quote, validate_args,
+    weighted_fill_entropy,
 };
+use std::collections::BTreeSet;
+use std::io::{ErrorKind, Write};
 
 const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
 const BANNER_WIDTH: usize = 79; // Width of the ASCII art banner
+const DEFAULT_LENGTH: u32 = 16;
+const PROGRESS_INTERVAL: u32 = 10_000; // Passwords between --progress updates
+const STREAM_CHUNK: u32 = 1_000; // Passwords generated at a time for --stream
+const BENCHMARK_COUNT: u32 = 100_000; // Passwords generated and timed by --benchmark
+#[cfg(feature = "hash")]
+const SEED_PHRASE_VAR: &str = "RPG_SEED_PHRASE"; // Master secret for --account
 
 fn format_banner_with_caption() -> String {
     let banner = include_str!("../banner.txt");
@@ -18,16 +44,225 @@ fn format_banner_with_caption() -> String {
     )
 }
 
+/// Whether ANSI escapes may be written at all
+///
+/// Every colored output goes through this: it is false when `NO_COLOR` is
+/// set to anything non-empty (<https://no-color.org>) or when stdout isn't a
+/// terminal, e.g. when piped to a file.
+fn should_color() -> bool {
+    use std::io::IsTerminal;
+
+    std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()) && std::io::stdout().is_terminal()
+}
+
+/// The EXAMPLES section at the end of `--help`, with or without ANSI styling
+fn examples_help(color: bool) -> String {
+    let style = |code: &str, text: &str| {
+        if color {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text.to_string()
+        }
+    };
+    let mut help = format!("\n{}\n", style("1", "EXAMPLES:"));
+    let mut section = "";
+    for example in EXAMPLES {
+        if example.section != section {
+            section = example.section;
+            help.push_str(&format!("\n  {}\n", style("36", &format!("{}:", section))));
+        }
+        help.push_str(&format!(
+            "    {:<36}# {}\n",
+            example.command, example.description
+        ));
+    }
+    help.push_str(&format!(
+        "\nFor more information, visit: {}",
+        style("4", "https://github.com/robot-accomplice/rpg")
+    ));
+    help
+}
+
+/// `EXAMPLES` as a JSON array of `{section, description, command}`, for `--help-examples`
+fn examples_json() -> String {
+    serde_json::to_string_pretty(EXAMPLES).unwrap()
+}
+
+/// Parses the command line, styling help and errors only if `should_color`
+fn parse_args() -> Args {
+    let color = should_color();
+    let command = Args::command()
+        .after_help(examples_help(color))
+        .color(if color {
+            ColorChoice::Auto
+        } else {
+            ColorChoice::Never
+        });
+    parse_argv(&command, std::env::args_os().collect()).unwrap_or_else(|e| e.exit())
+}
+
+/// Options that can be set from the environment, by argument id
+const ENV_VARS: [(&str, &str); 6] = [
+    ("length", "RPG_LENGTH"),
+    ("count", "RPG_COUNT"),
+    ("lowercase_off", "RPG_LOWERCASE_OFF"),
+    ("capitals_off", "RPG_CAPITALS_OFF"),
+    ("numerals_off", "RPG_NUMERALS_OFF"),
+    ("symbols_off", "RPG_SYMBOLS_OFF"),
+];
+
+/// Parses `argv`, with `ENV_VARS` filling in options the command line leaves out
+fn parse_argv(command: &clap::Command, argv: Vec<std::ffi::OsString>) -> Result<Args, clap::Error> {
+    let matches = with_env(command.clone(), &argv).try_get_matches_from(argv)?;
+    args_from_matches(command, &matches)
+}
+
+/// Attaches `ENV_VARS` to the options the command line doesn't decide
+///
+/// A variable is skipped when its option is given, or when an option it
+/// conflicts with is, so the command line always wins over the environment.
+/// `RPG_COUNT` also gives way to a positional count. Subcommands parse their
+/// arguments again through `parse_argv`, so the variables apply there instead.
+fn with_env(mut command: clap::Command, argv: &[std::ffi::OsString]) -> clap::Command {
+    let given = command
+        .clone()
+        .ignore_errors(true)
+        .try_get_matches_from(argv)
+        .ok();
+    if given.as_ref().is_some_and(|m| m.subcommand().is_some()) {
+        return command;
+    }
+    for (id, var) in ENV_VARS {
+        if let Some(ref matches) = given {
+            let decided = command_line_conflict(&command, matches, id).is_some()
+                || (id == "count" && command_line_has(matches, "password_count"));
+            if command_line_has(matches, id) || decided {
+                continue;
+            }
+        }
+        command = command.mut_arg(id, |arg| {
+            let arg = arg.env(var);
+            // Flags take the usual spellings of true and false
+            if !arg.get_action().takes_values() {
+                arg.value_parser(clap::builder::FalseyValueParser::new())
+            } else {
+                arg
+            }
+        });
+    }
+    command
+}
+
+/// Whether the option with this id was given on the command line
+fn command_line_has(matches: &ArgMatches, id: &str) -> bool {
+    matches.value_source(id) == Some(ValueSource::CommandLine)
+}
+
+/// An option given on the command line that conflicts with the option `id`
+fn command_line_conflict<'a>(
+    command: &'a clap::Command,
+    matches: &ArgMatches,
+    id: &str,
+) -> Option<&'a clap::Arg> {
+    let target = command.get_arguments().find(|arg| arg.get_id() == id)?;
+    command.get_arguments().find(|&arg| {
+        command_line_has(matches, arg.get_id().as_str())
+            && (command
+                .get_arg_conflicts_with(arg)
+                .iter()
+                .any(|a| a.get_id() == id)
+                || command
+                    .get_arg_conflicts_with(target)
+                    .iter()
+                    .any(|a| a.get_id() == arg.get_id()))
+    })
+}
+
+/// The flags that each select a generation mode, named as `--algorithm` names them
+const MODE_FLAGS: [&str; 3] = ["pattern", "mask", "memorable"];
+
+/// Builds `Args` from parsed matches, expanding the front-end subcommands
+///
+/// The arguments of `gen`, `check` and `estimate` are parsed again as
+/// top-level options, then `apply_algorithm` runs on the result.
+fn args_from_matches(command: &clap::Command, matches: &ArgMatches) -> Result<Args, clap::Error> {
+    let mut args = Args::from_arg_matches(matches)?;
+    let flat = match args.command.take() {
+        Some(Commands::Gen { args }) => args,
+        Some(Commands::Check { password, args }) => {
+            // Attached, so a password starting with a dash isn't read as a flag
+            std::iter::once(format!("--check={}", password))
+                .chain(args)
+                .collect()
+        }
+        Some(Commands::Estimate { args }) => std::iter::once("--estimate".to_string())
+            .chain(args)
+            .collect(),
+        other => {
+            args.command = other;
+            return apply_algorithm(command, matches, args);
+        }
+    };
+    let argv = std::iter::once(command.get_name().to_string())
+        .chain(flat)
+        .map(Into::into)
+        .collect();
+    parse_argv(command, argv)
+}
+
+/// Folds `--algorithm` into the mode flags of parsed `args`
+///
+/// `--algorithm random` rules out every mode flag, and the other names rule
+/// out the flags of other modes. `--algorithm memorable` turns on
+/// `--memorable`, so it is held to the same conflicts as if `--memorable` had
+/// been given.
+fn apply_algorithm(
+    command: &clap::Command,
+    matches: &ArgMatches,
+    mut args: Args,
+) -> Result<Args, clap::Error> {
+    let Some(algorithm) = args.algorithm.clone() else {
+        return Ok(args);
+    };
+    let conflict = |other: &str| {
+        command.clone().error(
+            clap::error::ErrorKind::ArgumentConflict,
+            format!("--algorithm {} can't be used with --{}", algorithm, other),
+        )
+    };
+    if let Some(mode) = MODE_FLAGS
+        .iter()
+        .find(|&&m| m != algorithm && command_line_has(matches, m))
+    {
+        return Err(conflict(mode));
+    }
+    if algorithm == "memorable" {
+        if let Some(arg) = command_line_conflict(command, matches, "memorable") {
+            return Err(conflict(arg.get_long().unwrap_or(arg.get_id().as_str())));
+        }
+        args.memorable = true;
+    }
+    Ok(args)
+}
+
 /// RPG - Rust Password Generator
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 #[command(
     version,
     about = "Rust Password Generator - A fast and customizable password generator",
     long_about = None,
     before_help = format_banner_with_caption(),
-    after_help = "\n\x1b[1mEXAMPLES:\x1b[0m\n\n  \x1b[36mBasic Usage:\x1b[0m\n    rpg 5                               # Generate 5 passwords\n    rpg 10 --length 20                  # Generate 10 passwords of length 20\n    rpg 25 --table                      # Generate 25 passwords in table format\n\n  \x1b[36mCharacter Customization:\x1b[0m\n    rpg 5 --capitals-off                # Generate without capital letters\n    rpg 5 --numerals-off --symbols-off  # Only alphabetic characters\n    rpg 5 --exclude-chars a-z,0-9       # Exclude ranges of characters\n    rpg 5 --exclude-chars a,b,c         # Exclude specific characters\n    rpg 5 --include-chars a-z,0-9       # Use only specified characters\n\n  \x1b[36mAdvanced Features:\x1b[0m\n    rpg 5 --pattern \"LLLNNNSSS\"         # Pattern-based generation\n    rpg 5 --min-capitals 2              # Minimum capital letters\n    rpg 5 --min-numerals 3              # Minimum numerals\n    rpg 5 --seed 12345                  # Reproducible passwords\n    rpg 1 --copy                        # Copy to clipboard\n    rpg 3 --format json                 # JSON output\n\nFor more information, visit: \x1b[4mhttps://github.com/robot-accomplice/rpg\x1b[0m"
+    subcommand_negates_reqs = true,
+    args_conflicts_with_subcommands = true,
 )]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Commands>,
+
+    /// Disable lowercase letters
+    #[arg(long, default_value = "false")]
+    lowercase_off: bool,
+
     /// Disable capital letters
     #[arg(short, long, default_value = "false")]
     capitals_off: bool,
@@ -44,10 +279,62 @@ struct Args {
     #[arg(short, long, value_delimiter = ',')]
     exclude_chars: Vec<String>,
 
+    /// Exclude both cases of every excluded letter, so -e a also drops A
+    #[arg(long, default_value = "false")]
+    exclude_case_insensitive: bool,
+
+    /// Exclude named character classes: vowels, brackets, quotes (comma-separated)
+    #[arg(long, value_delimiter = ',', value_name = "CLASS")]
+    exclude_class: Vec<String>,
+
+    /// Exclude vowels of either case, the same as --exclude-class vowels
+    #[arg(long, default_value = "false")]
+    no_vowels: bool,
+
+    /// Exclude brackets of every kind, the same as --exclude-class brackets
+    #[arg(long, default_value = "false")]
+    no_brackets: bool,
+
+    /// Exclude quote characters, the same as --exclude-class quotes
+    #[arg(long, default_value = "false")]
+    no_quotes: bool,
+
+    /// Exclude characters that are problematic in shells: $ ` \ " ' ; | & ( )
+    #[arg(long, default_value = "false")]
+    shell_safe: bool,
+
+    /// Exclude Greek and Cyrillic letters that look like Latin letters or digits (e.g. Cyrillic а)
+    #[arg(long, default_value = "false")]
+    exclude_homoglyphs: bool,
+
+    /// Add characters or ranges back after the exclusions, e.g. -e a-z --include-back m,n
+    #[arg(long, value_delimiter = ',', value_name = "CHARS")]
+    include_back: Vec<String>,
+
     /// Include only specific characters or ranges (overrides character type flags)
     #[arg(long, value_delimiter = ',')]
     include_chars: Vec<String>,
 
+    /// Allow a space among --include-chars (rejected by default)
+    #[arg(long, default_value = "false", requires = "include_chars")]
+    allow_space: bool,
+
+    /// Alphabet for letters: latin, greek or cyrillic (digits and symbols stay ASCII)
+    #[arg(long, default_value = "latin", value_name = "NAME", value_parser = ["latin", "greek", "cyrillic"], conflicts_with_all = ["include_chars", "url_safe", "mask", "memorable"])]
+    script: String,
+
+    /// Use exactly these symbols instead of all ASCII symbols, e.g. to match a site's policy (letters and digits are unaffected)
+    #[arg(long, value_name = "STR", allow_hyphen_values = true, conflicts_with_all = ["include_chars", "url_safe", "symbols_off", "safe_symbols"])]
+    symbols: Option<String>,
+
+    /// Use only the symbols !@#$%^&*, which nearly every system accepts
+    #[arg(long, visible_alias = "trim-symbols", default_value = "false", conflicts_with_all = ["include_chars", "url_safe", "symbols_off"])]
+    safe_symbols: bool,
+
+    /// Use only the URL/filename-safe base64url alphabet (A-Z a-z 0-9 - _)
+    #[arg(long, default_value = "false", conflicts_with = "include_chars")]
+    url_safe: bool,
+
     /// Minimum number of capital letters required
     #[arg(long)]
     min_capitals: Option<u32>,
@@ -60,89 +347,860 @@ struct Args {
     #[arg(long)]
     min_symbols: Option<u32>,
 
-    /// Length of the password
-    #[arg(short, long, default_value = "16")]
-    length: u32,
+    /// Maximum number of capital letters allowed
+    #[arg(long, conflicts_with_all = ["pattern", "mask", "memorable"])]
+    max_capitals: Option<u32>,
 
-    /// Number of passwords to generate
-    #[arg(required = true)]
-    password_count: u32,
+    /// Maximum number of numerals allowed
+    #[arg(long, conflicts_with_all = ["pattern", "mask", "memorable"])]
+    max_numerals: Option<u32>,
 
-    /// Print passwords in a table format
-    #[arg(short, long, default_value = "false")]
+    /// Maximum number of symbols allowed
+    #[arg(long, conflicts_with_all = ["pattern", "mask", "memorable"])]
+    max_symbols: Option<u32>,
+
+    /// Longest allowed run of one repeated character (2 allows "aa" but not "aaa")
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..), conflicts_with = "memorable")]
+    max_repeats: Option<u32>,
+
+    /// Re-roll passwords this regex doesn't match, e.g. '^[0-9]' (anchor with ^ and $ to match the whole password; needs the 'regex' feature)
+    #[arg(long = "match", value_name = "REGEX", conflicts_with_all = ["memorable", "check", "estimate"])]
+    match_regex: Option<String>,
+
+    /// Attempts per password before a re-roll constraint (--unique, --max-repeats, --match, ...) gives up [default: 1000, raised for --unique as the batch uses up the keyspace]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    retry_limit: Option<u32>,
+
+    /// Read length limits, required classes, forbidden characters and max repeats from a JSON policy
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["pattern", "mask", "memorable", "all_unique_chars", "stdin"])]
+    policy_file: Option<std::path::PathBuf>,
+
+    /// Length of the password, or bits of entropy with a b suffix, e.g. 128b [default: 16; a pattern or mask sets its own]
+    #[arg(short, long, value_parser = parse_length)]
+    length: Option<LengthSpec>,
+
+    /// Fail instead of warning when --length disagrees with the length of --pattern or --mask
+    #[arg(long, default_value = "false")]
+    strict_length: bool,
+
+    /// Maximum allowed password length (default: 10,000; at most 1,000,000)
+    #[arg(long, value_name = "N")]
+    max_length: Option<u32>,
+
+    /// Number of passwords to generate, or MIN-MAX to pick a random count in that range
+    #[arg(
+        value_name = "COUNT",
+        value_parser = parse_count_range,
+        required_unless_present_any = ["stdin", "count", "check", "estimate", "benchmark", "interactive", "stream", "help_examples", "generate_man"]
+    )]
+    password_count: Option<CountRange>,
+
+    /// Number of passwords to generate (alternative to the positional argument)
+    #[arg(long, value_name = "N")]
+    count: Option<u32>,
+
+    /// Print passwords in a table format (same as --format table)
+    #[arg(short, long, default_value = "false", conflicts_with = "format")]
     table: bool,
 
     /// Suppress header output (quiet mode)
     #[arg(short, long, default_value = "false")]
     quiet: bool,
 
+    /// Color letters, digits and symbols differently in text output (only on a terminal, and not with NO_COLOR set)
+    #[arg(long, default_value = "false")]
+    color: bool,
+
+    /// Never color output, overriding --color
+    #[arg(long, default_value = "false")]
+    no_color: bool,
+
     /// Seed for random number generator (for reproducible passwords)
     #[arg(long)]
     seed: Option<u64>,
 
-    /// Output format: "text" (default) or "json"
+    /// Seed the generator with up to 32 bytes read from a file (zero-padded), e.g. a key from a key-management system
+    #[arg(long, value_name = "PATH", conflicts_with = "seed")]
+    seed_file: Option<std::path::PathBuf>,
+
+    /// Print the seed drawn from the OS to stderr in hex, so a --seed-file holding it repeats the run
+    #[arg(long, default_value = "false", conflicts_with_all = ["seed", "seed_file", "account"])]
+    dump_seed: bool,
+
+    /// Derive the passwords from a master secret and this account name, so the same pair always gives the same passwords; the secret comes from --seed-phrase-file, RPG_SEED_PHRASE or a hidden prompt
+    #[arg(long, value_name = "NAME", conflicts_with_all = ["seed", "seed_file", "stdin"])]
+    account: Option<String>,
+
+    /// Read the master secret for --account from this file, trailing newline ignored (PBKDF2-HMAC-SHA256, 600,000 iterations)
+    #[arg(long, value_name = "PATH", requires = "account")]
+    seed_phrase_file: Option<std::path::PathBuf>,
+
+    /// Secret mixed into --seed (HMAC-SHA256), so the passwords can't be reproduced from the seed alone
+    #[arg(long, value_name = "STR", requires = "seed")]
+    pepper: Option<String>,
+
+    /// Seed each password separately from --seed and its position, so password i is the same whatever the count
+    #[arg(long, requires = "seed", conflicts_with_all = ["unique", "stdin"])]
+    derive_seeds: bool,
+
+    /// Generate one full batch per seed, e.g. 1,2,3, each under a "# seed N" heading (a "seed" field in JSON)
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_name = "SEEDS",
+        conflicts_with_all = [
+            "seed", "seed_file", "account", "dump_seed", "stdin", "stream", "interactive", "check",
+            "estimate", "benchmark", "copy", "qr", "keyring", "labels", "stats", "estimate_entropy",
+        ]
+    )]
+    seeds: Vec<u64>,
+
+    /// Output format: "text" (default), "table", "json", "jsonl" (one object per password), "env" or "plist"
     #[arg(long, default_value = "text")]
     format: String,
 
+    /// Print --format json on one line instead of indented
+    #[arg(long, default_value = "false")]
+    json_compact: bool,
+
+    /// Variable name prefix for --format env (emits PREFIX_1=..., PREFIX_2=...)
+    #[arg(long, default_value = "PASSWORD", value_name = "PREFIX")]
+    env_prefix: String,
+
+    /// Print passwords one per line until the output is closed or rpg is interrupted
+    #[arg(
+        long,
+        default_value = "false",
+        conflicts_with_all = [
+            "password_count", "count", "stdin", "interactive", "check", "format", "table",
+            "sort", "sort_by_length", "unique", "stats", "estimate_entropy", "explain", "copy", "qr", "hash",
+            "repeat", "progress", "show_entropy", "template",
+        ]
+    )]
+    stream: bool,
+
+    /// Tweak settings in a terminal UI with a live preview; Enter prints the first password
+    #[arg(
+        long,
+        default_value = "false",
+        conflicts_with_all = ["stdin", "memorable", "pattern", "mask", "check"]
+    )]
+    interactive: bool,
+
+    /// Print the characters passwords are drawn from, sorted, to stderr
+    #[arg(long, default_value = "false")]
+    show_charset: bool,
+
+    /// Print the resolved settings, with presets and ranges expanded, as JSON to stderr
+    #[arg(long, default_value = "false")]
+    print_config: bool,
+
+    /// Print a breakdown of how the entropy was computed to stderr
+    #[arg(long, default_value = "false", conflicts_with = "memorable")]
+    explain: bool,
+
+    /// Fail unless each password has at least this many bits of estimated entropy (a pattern or mask fixes the length, so it can fall short)
+    #[arg(long, value_name = "BITS")]
+    min_entropy: Option<f64>,
+
+    /// Attacker guesses per second used for the crack-time estimate
+    #[arg(long, default_value_t = DEFAULT_GUESSES_PER_SECOND, value_name = "N")]
+    guesses_per_second: f64,
+
     /// Copy first password to clipboard
     #[arg(long, default_value = "false")]
     copy: bool,
 
+    /// Store the password in the OS keyring under this service instead of printing it
+    #[arg(
+        long,
+        value_name = "SERVICE",
+        conflicts_with_all = ["copy", "qr", "hash", "labels", "stream", "stdin", "interactive", "check"]
+    )]
+    keyring: Option<String>,
+
+    /// Account name for the --keyring entry
+    #[arg(long, value_name = "NAME", default_value = "rpg", requires = "keyring")]
+    keyring_account: String,
+
+    /// Append each password's entropy in bits (text output, not table)
+    #[arg(long, default_value = "false", conflicts_with = "table")]
+    show_entropy: bool,
+
+    /// Format each password with a template: {password}, {n}, {entropy}, {length}; {{ and }} for literal braces
+    #[arg(long, value_name = "STR", conflicts_with_all = ["table", "show_entropy"])]
+    template: Option<String>,
+
+    /// Sort the passwords alphabetically before output
+    #[arg(long, default_value = "false", conflicts_with = "sort_by_length")]
+    sort: bool,
+
+    /// Sort the passwords by length (shortest first) before output
+    #[arg(long, default_value = "false")]
+    sort_by_length: bool,
+
+    /// Label the passwords, one label each: "github,email" prints "github: <password>" and so on
+    #[arg(
+        long,
+        value_name = "LABELS",
+        value_delimiter = ',',
+        conflicts_with_all = ["stdin", "stream", "hash", "repeat", "template", "sort", "sort_by_length"]
+    )]
+    labels: Vec<String>,
+
+    /// Generate COUNT passwords but print only K of them, chosen uniformly at random
+    #[arg(
+        long,
+        value_name = "K",
+        value_parser = clap::value_parser!(u32).range(1..),
+        conflicts_with_all = ["stdin", "stream", "progress"]
+    )]
+    sample: Option<u32>,
+
+    /// Print each password N times in a row, e.g. for backup labels (with --table, one password per row)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..), conflicts_with = "hash")]
+    repeat: Option<u32>,
+
+    /// Show a delimiter after every N characters for readability (cosmetic: not part of the password, its length or entropy)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..), conflicts_with_all = ["hash", "template"])]
+    delimiter_every: Option<u32>,
+
+    /// Delimiter shown by --delimiter-every
+    #[arg(
+        long,
+        default_value = "-",
+        value_name = "CHAR",
+        requires = "delimiter_every"
+    )]
+    delimiter: char,
+
+    /// Output a hash of each password instead of the plaintext: "sha256", "bcrypt" or "argon2"
+    #[arg(long, value_name = "ALGORITHM", value_parser = ["sha256", "bcrypt", "argon2"], conflicts_with_all = ["template", "show_entropy"])]
+    hash: Option<String>,
+
+    /// Work factor for --hash bcrypt (log2 rounds, default 12) or argon2 (iterations, default 2)
+    #[arg(long, value_name = "N", requires = "hash")]
+    hash_cost: Option<u32>,
+
+    /// With --hash, show each plaintext password alongside its hash
+    #[arg(long, default_value = "false", requires = "hash")]
+    show_plain: bool,
+
+    /// Show the first password as a QR code (an SVG string field in JSON output)
+    #[arg(long, default_value = "false")]
+    qr: bool,
+
     /// Pattern for password generation (L=lowercase, U=uppercase, N=numeric, S=symbol)
     /// Example: "LLLNNNSSS" generates 3 lowercase, 3 numeric, 3 symbols
-    #[arg(long)]
+    #[arg(long, required_if_eq("algorithm", "pattern"))]
     pattern: Option<String>,
+
+    /// Generate passwords from a mask of per-position character sets, e.g. "[ab][0-9]{4}[!@#]"
+    #[arg(
+        long,
+        value_name = "MASK",
+        conflicts_with_all = ["pattern", "memorable"],
+        required_if_eq("algorithm", "mask")
+    )]
+    mask: Option<String>,
+
+    /// Re-roll passwords that resemble this string (e.g. the password being rotated out)
+    #[arg(long, value_name = "STR")]
+    exclude_similar_to: Option<String>,
+
+    /// Re-roll passwords that appear in this file (one password per line)
+    #[arg(long, value_name = "PATH")]
+    exclude_password_file: Option<std::path::PathBuf>,
+
+    /// Guarantee no duplicate passwords within the batch
+    #[arg(long)]
+    unique: bool,
+
+    /// Re-roll passwords that lack either a lowercase or an uppercase letter
+    #[arg(long, default_value = "false")]
+    require_mixed_case: bool,
+
+    /// Guarantee at least one character of every enabled type (lowercase, and capitals, numerals, symbols unless turned off)
+    #[arg(long, default_value = "false", conflicts_with_all = ["pattern", "mask", "memorable"])]
+    ensure_all_types: bool,
+
+    /// Use every character at most once per password, so --length can't exceed the character set
+    #[arg(
+        long,
+        default_value = "false",
+        conflicts_with_all = [
+            "pattern", "mask", "memorable", "min_capitals", "min_numerals", "min_symbols",
+            "ensure_all_types", "first_char_alpha", "weight_letters", "weight_digits",
+            "weight_symbols", "max_capitals", "max_numerals", "max_symbols"
+        ]
+    )]
+    all_unique_chars: bool,
+
+    /// Print batch statistics to stderr: character frequencies, per-class counts and min/mean/max entropy
+    #[arg(long, default_value = "false", conflicts_with = "check")]
+    stats: bool,
+
+    /// Compare the observed character entropy of the batch with the theoretical figure, on stderr
+    #[arg(long, default_value = "false", conflicts_with_all = ["check", "memorable"])]
+    estimate_entropy: bool,
+
+    /// Score an existing password instead of generating any
+    #[arg(long, value_name = "STR", conflicts_with_all = ["password_count", "count", "stdin"])]
+    check: Option<String>,
+
+    /// Print the entropy, strength and crack time the options would give, without generating any password
+    #[arg(
+        long,
+        default_value = "false",
+        conflicts_with_all = [
+            "check", "stdin", "interactive", "stream", "copy", "qr", "hash", "keyring", "labels",
+            "sample", "stats", "estimate_entropy", "progress", "repeat", "derive_seeds",
+        ]
+    )]
+    estimate: bool,
+
+    /// Time a batch of 100,000 passwords with these options and report the rate to stderr, printing no passwords
+    #[arg(
+        long,
+        default_value = "false",
+        conflicts_with_all = [
+            "password_count", "count", "check", "estimate", "stdin", "interactive", "stream",
+            "memorable", "copy", "qr", "hash", "keyring", "labels", "sample", "progress",
+        ]
+    )]
+    benchmark: bool,
+
+    /// Make every password start with a letter
+    #[arg(long, default_value = "false")]
+    first_char_alpha: bool,
+
+    /// Relative weight of letters in the fill characters (unset weights default to 1)
+    #[arg(long, value_name = "N", conflicts_with_all = ["pattern", "mask", "memorable"])]
+    weight_letters: Option<u32>,
+
+    /// Relative weight of digits in the fill characters (unset weights default to 1)
+    #[arg(long, value_name = "N", conflicts_with_all = ["pattern", "mask", "memorable"])]
+    weight_digits: Option<u32>,
+
+    /// Relative weight of symbols in the fill characters (unset weights default to 1)
+    #[arg(long, value_name = "N", conflicts_with_all = ["pattern", "mask", "memorable"])]
+    weight_symbols: Option<u32>,
+
+    /// Generate memorable passwords: capitalized words, digits and a trailing symbol (e.g. MapleOtter42!)
+    #[arg(long, default_value = "false")]
+    memorable: bool,
+
+    /// Pick the generation mode by name: random, pattern (needs --pattern), mask (needs --mask) or memorable
+    #[arg(long, value_name = "NAME", value_parser = ["random", "pattern", "mask", "memorable"])]
+    algorithm: Option<String>,
+
+    /// Number of words in memorable mode
+    #[arg(long, default_value = "2", value_name = "N", value_parser = clap::value_parser!(u32).range(1..=10))]
+    memorable_words: u32,
+
+    /// Number of digits in memorable mode
+    #[arg(long, default_value = "2", value_name = "N")]
+    memorable_digits: u32,
+
+    /// Append a trailing symbol in memorable mode
+    #[arg(long, default_value = "true", value_name = "BOOL", action = clap::ArgAction::Set)]
+    memorable_symbol: bool,
+
+    /// Append a check digit to each (numeric) password: "luhn"
+    #[arg(long, value_name = "ALGORITHM", value_parser = ["luhn"], conflicts_with_all = ["memorable", "stdin"])]
+    check_digit: Option<String>,
+
+    /// Read "COUNT LENGTH" requests from stdin, one per line, and print passwords for each
+    #[arg(long, default_value = "false", conflicts_with = "memorable")]
+    stdin: bool,
+
+    /// Report progress on stderr every 10,000 passwords for large batches
+    #[arg(long, default_value = "false")]
+    progress: bool,
+
+    /// Generate on every CPU core, in chunks of 10,000 seeded from the RNG, so --seed still reproduces the batch (needs the 'rayon' feature)
+    #[arg(
+        long,
+        default_value = "false",
+        conflicts_with_all = [
+            "unique", "memorable", "derive_seeds", "check", "estimate", "stdin", "interactive",
+            "stream", "sample", "progress",
+        ]
+    )]
+    parallel: bool,
+
+    /// Print the --help examples as a JSON array of {section, description, command} and exit
+    #[arg(long, default_value = "false")]
+    help_examples: bool,
+
+    /// Print a roff man page generated from these options and exit
+    #[arg(long, default_value = "false", conflicts_with = "help_examples")]
+    generate_man: bool,
 }
 
-fn main() {
-    let args = Args::parse();
+/// Subcommands
+///
+/// `gen`, `check` and `estimate` are front ends to the top-level options:
+/// `args_from_matches` parses their arguments again as if they had been given
+/// without the subcommand, so `rpg gen 5` is `rpg 5` and `rpg check PASSWORD`
+/// is `rpg --check PASSWORD`.
+#[derive(Subcommand, Debug, Clone)]
+enum Commands {
+    /// Generate passwords, the same as running rpg without a subcommand
+    #[command(disable_help_flag = true)]
+    Gen {
+        /// Any count and options accepted without a subcommand
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+    /// Score an existing password instead of generating any
+    Check {
+        /// The password to score; it is never echoed back
+        password: String,
+        /// Output options such as --format json
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+    /// Print the entropy, strength and crack time of the passwords the options would generate
+    #[command(disable_help_flag = true)]
+    Estimate {
+        /// Generation options, as accepted without a subcommand; no count is needed
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+    /// Print a shell completion script to stdout
+    Completions {
+        /// Shell to complete for
+        shell: clap_complete::Shell,
+    },
+}
 
-    // ASCII art banner is only shown in --help output (via before_help)
-    // No banner is printed during normal execution
+/// The completion script for `shell`, generated from the `Args` definition
+fn completions(shell: clap_complete::Shell) -> String {
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut Args::command(), "rpg", &mut script);
+    String::from_utf8(script).unwrap()
+}
 
-    // Parse and expand exclude character ranges
-    let exclude_chars = match parse_exclude_chars(args.exclude_chars) {
-        Ok(chars) => chars,
-        Err(e) => {
-            eprintln!("Error parsing exclude characters: {}", e);
-            std::process::exit(1);
+/// The man page for `--generate-man`, rendered from the `Args` definition
+fn man_page() -> String {
+    let mut page = Vec::new();
+    // The command is named after the package; the page is for the binary
+    clap_mangen::Man::new(Args::command().name("rpg"))
+        .render(&mut page)
+        .unwrap();
+    String::from_utf8(page).unwrap()
+}
+
+/// A positional count, either fixed or a `MIN-MAX` range to pick from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CountRange {
+    min: u32,
+    max: u32,
+}
+
+/// Parses the positional count: `N` or an inclusive `MIN-MAX` range
+///
+/// A plain `0` is let through so `validate_args` reports it as usual.
+fn parse_count_range(s: &str) -> Result<CountRange, String> {
+    let parse = |part: &str| {
+        part.trim()
+            .parse::<u32>()
+            .map_err(|_| format!("invalid count '{}'", part))
+    };
+    let Some((min, max)) = s.split_once('-') else {
+        let count = parse(s)?;
+        return Ok(CountRange {
+            min: count,
+            max: count,
+        });
+    };
+    let (min, max) = (parse(min)?, parse(max)?);
+    if min == 0 || max == 0 {
+        return Err(format!(
+            "both ends of the range '{}' must be greater than 0",
+            s
+        ));
+    }
+    if min > max {
+        return Err(format!(
+            "the range '{}' is backwards; MIN must not be greater than MAX",
+            s
+        ));
+    }
+    Ok(CountRange { min, max })
+}
+
+/// A `--length`: a number of characters, or of bits of entropy to reach
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LengthSpec {
+    Chars(u32),
+    Bits(u32),
+}
+
+impl std::fmt::Display for LengthSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LengthSpec::Chars(length) => write!(f, "{}", length),
+            LengthSpec::Bits(bits) => write!(f, "{}b", bits),
         }
+    }
+}
+
+/// Parses `--length`: `N` characters, or `Nb` for enough characters to carry N bits
+///
+/// A plain `0` is let through so `validate_args` reports it as usual.
+fn parse_length(s: &str) -> Result<LengthSpec, String> {
+    let invalid = || {
+        format!(
+            "invalid length '{}'; use a number of characters such as 16, or of bits such as 128b",
+            s
+        )
     };
+    match s.strip_suffix('b') {
+        Some(bits) => match bits.parse::<u32>().map_err(|_| invalid())? {
+            0 => Err(format!("the bit length '{}' must be greater than 0", s)),
+            bits => Ok(LengthSpec::Bits(bits)),
+        },
+        None => s.parse().map(LengthSpec::Chars).map_err(|_| invalid()),
+    }
+}
 
-    // Parse and expand include character ranges (if specified)
-    let include_chars = if args.include_chars.is_empty() {
-        None
-    } else {
-        match parse_exclude_chars(args.include_chars) {
-            Ok(chars) => Some(chars),
+/// Replaces a `MIN-MAX` count with one drawn from `rng`
+///
+/// Fixed counts don't touch the RNG, so seeded output without a range is
+/// unchanged.
+fn pick_count<R: Rng>(args: &mut Args, rng: &mut R) {
+    if let Some(range) = args.password_count
+        && range.min != range.max
+    {
+        let count = rng.random_range(range.min..=range.max);
+        args.password_count = Some(CountRange {
+            min: count,
+            max: count,
+        });
+    }
+}
+
+/// Parses a stdin request line of the form "COUNT LENGTH"
+fn parse_stdin_request(line: &str) -> Result<(u32, u32), String> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() != 2 {
+        return Err(format!(
+            "expected \"COUNT LENGTH\", got {} field(s)",
+            fields.len()
+        ));
+    }
+    let count = fields[0]
+        .parse::<u32>()
+        .map_err(|_| format!("invalid count '{}'", fields[0]))?;
+    let length = fields[1]
+        .parse::<u32>()
+        .map_err(|_| format!("invalid length '{}'", fields[1]))?;
+    Ok((count, length))
+}
+
+/// Serves "COUNT LENGTH" requests read from stdin until it closes
+///
+/// Malformed or invalid lines are reported on stderr and skipped so one bad
+/// request doesn't abort the whole batch.
+fn run_stdin_requests<R: Rng>(
+    char_set: &[char],
+    password_args: &mut PasswordArgs,
+    base_params: &GenerationParams,
+    rng: &mut R,
+) {
+    use std::io::BufRead;
+
+    let stdin = std::io::stdin();
+    for (line_no, line) in stdin.lock().lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
             Err(e) => {
-                eprintln!("Error parsing include characters: {}", e);
+                eprintln!("Error reading stdin: {}", e);
                 std::process::exit(1);
             }
+        };
+        if line.trim().is_empty() {
+            continue;
         }
-    };
 
-    // Parse pattern if specified
-    let pattern = if let Some(ref pat_str) = args.pattern {
-        match parse_pattern(pat_str) {
-            Ok(pat) => Some(pat),
+        let (count, length) = match parse_stdin_request(&line) {
+            Ok(request) => request,
             Err(e) => {
-                eprintln!("Error parsing pattern: {}", e);
-                std::process::exit(1);
+                eprintln!("Error: stdin line {}: {}", line_no + 1, e);
+                continue;
             }
+        };
+
+        password_args.length = length;
+        password_args.password_count = count;
+        if let Err(e) = validate_args(password_args) {
+            eprintln!("stdin line {}: {}", line_no + 1, e);
+            continue;
         }
-    } else {
+
+        let params = GenerationParams {
+            length,
+            count,
+            ..base_params.clone()
+        };
+        match generate_passwords(char_set, &params, rng) {
+            Ok(passwords) => print_stdout(&format_columns(&passwords, 1, false)),
+            Err(e) => eprintln!("stdin line {}: {}", line_no + 1, e),
+        }
+    }
+}
+
+/// Settings resolved from the command line, ready for generation
+struct Config {
+    password_args: PasswordArgs,
+    char_set: Vec<char>,
+    gen_params: GenerationParams,
+    memorable_params: MemorableParams,
+    password_count: u32,
+    /// Messages for stderr about options that had no effect
+    warnings: Vec<String>,
+}
+
+/// The output format, with the older `--table` flag mapped to `table`
+fn output_format(args: &Args) -> &str {
+    if args.table { "table" } else { &args.format }
+}
+
+/// Checks that `--labels` gives one distinct, non-empty label per password
+fn check_labels(args: &Args, password_count: u32) -> Result<(), String> {
+    let format = output_format(args);
+    if !matches!(format, "text" | "table" | "json" | "plist") {
+        return Err(format!(
+            "Error: --labels can't be used with --format {}.",
+            format
+        ));
+    }
+    if args.labels.len() != password_count as usize {
+        return Err(format!(
+            "Error: {} labels were given for {} passwords.\n\
+            Hint: Pass exactly one label per password.",
+            args.labels.len(),
+            password_count
+        ));
+    }
+    let mut seen = BTreeSet::new();
+    for label in &args.labels {
+        if label.trim().is_empty() {
+            return Err("Error: --labels has an empty label.".to_string());
+        }
+        if !seen.insert(label) {
+            return Err(format!(
+                "Error: The label '{}' is given more than once.",
+                label
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Parses and validates the command line into generation settings
+fn resolve_config(args: &Args) -> Result<Config, Failure> {
+    // --table conflicts with these through clap; --format table is checked here
+    if output_format(args) == "table" {
+        if args.show_entropy {
+            return Err("Error: --show-entropy can't be used with table output."
+                .to_string()
+                .into());
+        }
+        if args.template.is_some() {
+            return Err("Error: --template can't be used with table output."
+                .to_string()
+                .into());
+        }
+    }
+
+    // The env prefix becomes a shell variable name, so it must be a valid identifier
+    if args.format == "env" {
+        let mut chars = args.env_prefix.chars();
+        let valid = chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(format!(
+                "Error: Invalid --env-prefix '{}'. Use letters, digits and underscores, not starting with a digit.",
+                args.env_prefix
+            ).into());
+        }
+    }
+
+    if let Some(ref template) = args.template {
+        apply_template(template, "", 1, 0.0)
+            .map_err(|e| format!("Error: Invalid --template: {}", e))?;
+    }
+
+    if args
+        .min_entropy
+        .is_some_and(|bits| !(bits.is_finite() && bits >= 0.0))
+    {
+        return Err("Error: --min-entropy must be a non-negative number."
+            .to_string()
+            .into());
+    }
+
+    if !(args.guesses_per_second.is_finite() && args.guesses_per_second > 0.0) {
+        return Err("Error: --guesses-per-second must be a positive number."
+            .to_string()
+            .into());
+    }
+
+    // Parse and expand exclude character ranges
+    let mut exclude_chars = parse_exclude_chars(args.exclude_chars.clone())
+        .map_err(|e| format!("Error parsing exclude characters: {}", e))?;
+
+    // Merge in named character classes, including the --no-<class> shorthands
+    let shorthands = [
+        (args.no_vowels, "vowels"),
+        (args.no_brackets, "brackets"),
+        (args.no_quotes, "quotes"),
+    ];
+    let mut class_names = args.exclude_class.clone();
+    class_names.extend(
+        shorthands
+            .iter()
+            .filter(|&&(on, _)| on)
+            .map(|&(_, name)| name.to_string()),
+    );
+    for c in parse_exclude_classes(&class_names)
+        .map_err(|e| format!("Error parsing exclude classes: {}", e))?
+    {
+        if !exclude_chars.contains(&c) {
+            exclude_chars.push(c);
+        }
+    }
+
+    // The shell-safe preset is just more exclusions
+    if args.shell_safe {
+        for &c in SHELL_UNSAFE_CHARS {
+            if !exclude_chars.contains(&c) {
+                exclude_chars.push(c);
+            }
+        }
+    }
+
+    // So is dropping look-alikes; the Latin letters they imitate stay
+    if args.exclude_homoglyphs {
+        for &(c, _) in HOMOGLYPHS {
+            if !exclude_chars.contains(&c) {
+                exclude_chars.push(c);
+            }
+        }
+    }
+
+    // Characters to keep despite the exclusions above
+    let include_back = parse_exclude_chars(args.include_back.clone())
+        .map_err(|e| format!("Error parsing include-back characters: {}", e))?;
+
+    // Parse and expand include character ranges (if specified)
+    let include_chars = if args.url_safe {
+        Some(URL_SAFE_ALPHABET.chars().collect())
+    } else if args.include_chars.is_empty() {
         None
+    } else {
+        Some(
+            parse_exclude_chars(args.include_chars.clone())
+                .map_err(|e| format!("Error parsing include characters: {}", e))?,
+        )
+    };
+
+    // Parse pattern if specified
+    let pattern = match args.pattern {
+        Some(ref pat_str) => {
+            Some(parse_pattern(pat_str).map_err(|e| format!("Error parsing pattern: {}", e))?)
+        }
+        None => None,
     };
 
-    // If pattern is specified, use its length; otherwise use args.length
-    let effective_length = pattern
+    // Parse mask if specified; its pools are restricted to the character set below
+    let mask = match args.mask {
+        Some(ref mask_str) => {
+            Some(parse_mask(mask_str).map_err(|e| format!("Error parsing mask: {}", e))?)
+        }
+        None => None,
+    };
+
+    // If a pattern or mask is specified, use its length; otherwise use args.length
+    let template_length = pattern
         .as_ref()
-        .map(|p| p.len() as u32)
-        .unwrap_or(args.length);
+        .map(|p| (p.len() as u32, "--pattern"))
+        .or(mask.as_ref().map(|m| (m.len() as u32, "--mask")));
+    let mut warnings = Vec::new();
+    if let (Some((length, flag)), Some(requested)) = (template_length, args.length)
+        && requested != LengthSpec::Chars(length)
+    {
+        if args.strict_length {
+            return Err(format!(
+                "Error: --length {} disagrees with {}, which is {} characters long.\n\
+                Hint: Drop --length, or change it to {}.",
+                requested, flag, length, length
+            )
+            .into());
+        }
+        warnings.push(format!(
+            "Warning: --length {} is ignored; {} determines the length ({} characters).",
+            requested, flag, length
+        ));
+    }
+    // A length in bits is worked out once the character set is known
+    let bits_length = match args.length {
+        Some(LengthSpec::Bits(bits)) if template_length.is_none() => Some(bits),
+        _ => None,
+    };
+    let effective_length = template_length
+        .map(|(length, _)| length)
+        .or(match args.length {
+            Some(LengthSpec::Chars(length)) => Some(length),
+            _ => None,
+        })
+        .unwrap_or(DEFAULT_LENGTH);
+
+    // In stdin mode, counts come from each request line
+    let password_count = match (args.password_count.map(|range| range.min), args.count) {
+        (Some(positional), Some(flag)) if positional != flag => {
+            return Err(format!(
+                "Error: Conflicting password counts: {} (positional) and {} (--count).\n\
+                Hint: Pass the count only once.",
+                positional, flag
+            )
+            .into());
+        }
+        (positional, flag) => positional.or(flag).unwrap_or(1),
+    };
+
+    if let Some(k) = args.sample
+        && k > password_count
+    {
+        return Err(format!(
+            "Error: --sample {} is more than the {} passwords to choose from.\n\
+            Hint: Raise the count or lower --sample.",
+            k, password_count
+        )
+        .into());
+    }
+
+    if !args.labels.is_empty() {
+        check_labels(args, args.sample.unwrap_or(password_count))?;
+    }
+
+    if args.keyring.is_some() && args.sample.unwrap_or(password_count) != 1 {
+        return Err(format!(
+            "Error: --keyring stores a single password, but {} were requested.\n\
+            Hint: Generate one password per keyring entry.",
+            args.sample.unwrap_or(password_count)
+        )
+        .into());
+    }
 
     // Convert CLI args to library args
-    let password_args = PasswordArgs {
+    let mut password_args = PasswordArgs {
+        lowercase_off: args.lowercase_off,
         capitals_off: args.capitals_off,
         numerals_off: args.numerals_off,
         symbols_off: args.symbols_off,
@@ -151,47 +1209,1313 @@ fn main() {
         min_capitals: args.min_capitals,
         min_numerals: args.min_numerals,
         min_symbols: args.min_symbols,
+        max_capitals: args.max_capitals,
+        max_numerals: args.max_numerals,
+        max_symbols: args.max_symbols,
         pattern: pattern.clone(),
         length: effective_length,
-        password_count: args.password_count,
+        password_count,
+        max_length: args.max_length,
+        allow_space: args.allow_space,
+        safe_symbols: args.safe_symbols,
+        symbols: args.symbols.as_ref().map(|s| s.chars().collect()),
+        include_back,
+        max_repeats: args.max_repeats,
+        exclude_case_insensitive: args.exclude_case_insensitive,
     };
 
-    // Validate arguments
-    if let Err(e) = validate_args(&password_args) {
-        eprintln!("{}", e);
-        std::process::exit(1);
+    // A policy tightens the settings above, or says why it can't
+    let policy = args.policy_file.as_deref().map(read_policy).transpose()?;
+    if let Some(ref policy) = policy {
+        let explicit_length =
+            matches!(args.length, Some(LengthSpec::Chars(_))) || template_length.is_some();
+        policy
+            .apply(&mut password_args, explicit_length)
+            .map_err(|e| format!("Error: Policy file conflicts with the options: {}.", e))?;
     }
 
+    // Validate arguments
+    validate_args(&password_args)?;
+
     // Build character set once (more efficient than building per character)
-    let char_set = match build_char_set(&password_args) {
-        Ok(set) => set,
-        Err(e) => {
-            eprintln!("{}", e);
-            std::process::exit(1);
+    let script = Script::parse(&args.script).unwrap_or_default();
+    let char_set = build_script_char_set(&password_args, script)?;
+
+    if let Some(bits) = bits_length {
+        let Some(length) = length_for_entropy(char_set.len(), f64::from(bits)) else {
+            return Err(format!(
+                "Error: --length {}b can't be reached with a character set of {}.\n\
+                Hint: Allow more characters.",
+                bits,
+                char_set.len()
+            )
+            .into());
+        };
+        if let Some(ref policy) = policy {
+            policy.check_length(length).map_err(|e| {
+                format!(
+                    "Error: Policy file conflicts with the options: {} (for --length {}b).",
+                    e, bits
+                )
+            })?;
+        }
+        password_args.length = length;
+        validate_args(&password_args)?;
+    }
+
+    // Previously issued passwords, one per line
+    let forbidden_passwords = match args.exclude_password_file {
+        Some(ref path) => std::fs::read_to_string(path)
+            .map_err(|e| {
+                format!(
+                    "Error: Could not read password file '{}': {}",
+                    path.display(),
+                    e
+                )
+            })?
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect(),
+        None => BTreeSet::new(),
+    };
+
+    if args.check_digit.is_some() && !char_set.iter().all(char::is_ascii_digit) {
+        return Err(
+            "Error: --check-digit luhn requires a numeric-only character set.\n\
+            Hint: Add --include-chars 0-9."
+                .to_string()
+                .into(),
+        );
+    }
+
+    // An enabled type can't be guaranteed once exclusions remove all of it
+    if args.ensure_all_types && args.include_chars.is_empty() {
+        let has = |is_type: fn(&char) -> bool| char_set.iter().any(is_type);
+        let types = [
+            (
+                "lowercase letter",
+                !args.lowercase_off,
+                has(|c| c.is_lowercase()),
+            ),
+            (
+                "capital letter",
+                !args.capitals_off,
+                has(|c| c.is_uppercase()),
+            ),
+            ("numeral", !args.numerals_off, has(char::is_ascii_digit)),
+            ("symbol", !args.symbols_off, has(|c| !c.is_alphanumeric())),
+        ];
+        for (name, enabled, present) in types {
+            if enabled && !present {
+                return Err(format!(
+                    "Error: --ensure-all-types requires a {name}, but the exclusions remove every one.\n\
+                    Hint: Turn that type off or exclude fewer characters."
+                )
+                .into());
+            }
+        }
+    }
+
+    let mask = match mask {
+        Some(mask) => {
+            Some(restrict_mask(mask, &char_set).map_err(|e| format!("Error parsing mask: {}", e))?)
         }
+        None => None,
+    };
+
+    #[cfg(feature = "regex")]
+    let must_match = match args.match_regex {
+        Some(ref re) => Some(regex::Regex::new(re).map_err(|e| {
+            format!(
+                "Error: Invalid --match regex: {}\n\
+                Hint: The regex uses Rust regex syntax; quote it so the shell leaves it alone.",
+                e
+            )
+        })?),
+        None => None,
     };
+    #[cfg(not(feature = "regex"))]
+    if args.match_regex.is_some() {
+        return Err(
+            "Error: --match is not available (rpg was built without the 'regex' feature)."
+                .to_string()
+                .into(),
+        );
+    }
 
     // Create generation parameters
     let gen_params = GenerationParams {
-        length: effective_length,
-        count: args.password_count,
-        min_capitals: args.min_capitals,
-        min_numerals: args.min_numerals,
-        min_symbols: args.min_symbols,
-        pattern: pattern.clone(),
+        length: password_args.length,
+        count: password_count,
+        min_capitals: password_args.min_capitals,
+        min_numerals: password_args.min_numerals,
+        min_symbols: password_args.min_symbols,
+        max_capitals: args.max_capitals,
+        max_numerals: args.max_numerals,
+        max_symbols: args.max_symbols,
+        pattern,
+        exclude_similar_to: args.exclude_similar_to.clone(),
+        forbidden_passwords,
+        unique: args.unique,
+        require_mixed_case: args.require_mixed_case,
+        // There is no lowercase minimum, so a policy that requires lowercase
+        // letters turns on the re-roll that ensure_all_types uses for them
+        ensure_all_types: args.ensure_all_types
+            || policy
+                .as_ref()
+                .is_some_and(|p| p.requires(PolicyClass::Lowercase)),
+        max_repeats: password_args.max_repeats,
+        retry_limit: args.retry_limit,
+        all_unique_chars: args.all_unique_chars,
+        mask,
+        first_char_alpha: args.first_char_alpha,
+        weights: class_weights(args),
+        // Bulk bytes would change what a seed reproduces
+        bulk_bytes: !reproducible(args),
+        #[cfg(feature = "regex")]
+        must_match,
+    };
+
+    let memorable_params = MemorableParams {
+        words: args.memorable_words,
+        digits: args.memorable_digits,
+        symbol: args.memorable_symbol,
+    };
+
+    if let Some(min_entropy) = args.min_entropy {
+        let bits = if args.memorable {
+            memorable_entropy(&memorable_params)
+        } else {
+            explain_entropy(&char_set, &gen_params).total_bits()
+        };
+        if bits < min_entropy {
+            // Patterns and masks can't be lengthened to make up the shortfall
+            let hint = if gen_params.pattern.is_some() {
+                "A pattern fixes the length; add positions or use classes with more characters."
+            } else if gen_params.mask.is_some() {
+                "A mask fixes the length; add positions or widen its character classes."
+            } else if args.memorable {
+                "Use more --memorable-words."
+            } else {
+                "Use a longer --length or allow more characters."
+            };
+            return Err(format!(
+                "Error: The passwords would have {:.1} bits of entropy, below --min-entropy {}.\nHint: {}",
+                bits, min_entropy, hint
+            )
+            .into());
+        }
+    }
+
+    Ok(Config {
+        password_args,
+        char_set,
+        gen_params,
+        memorable_params,
+        password_count,
+        warnings,
+    })
+}
+
+/// Writes a progress line to stderr at each `PROGRESS_INTERVAL` and at the end
+fn report_progress(done: u32, total: u32) {
+    if done.is_multiple_of(PROGRESS_INTERVAL) || done == total {
+        eprintln!(
+            "Progress: {}/{} passwords ({}%)",
+            done,
+            total,
+            u64::from(done) * 100 / u64::from(total)
+        );
+    }
+}
+
+/// Generates the passwords described by the resolved configuration
+fn generate<R: Rng>(args: &Args, config: &Config, rng: &mut R) -> Result<Vec<String>, Failure> {
+    match args.sample {
+        Some(k) => generate_sample(args, config, rng, k as usize),
+        None => generate_batch(args, config, rng),
+    }
+}
+
+/// Generates the full count for `--sample` and keeps `k` with `reservoir_sample`
+///
+/// The sampler has its own RNG, seeded from `rng` before anything is
+/// generated. Plain batches are generated a chunk at a time, so only the
+/// sample and one chunk are held in memory; `--unique`, `--memorable` and
+/// `--derive-seeds` generate the whole batch first.
+fn generate_sample<R: Rng>(
+    args: &Args,
+    config: &Config,
+    rng: &mut R,
+    k: usize,
+) -> Result<Vec<String>, Failure> {
+    let mut sampler = StdRng::from_rng(rng);
+    if args.unique || args.memorable || args.derive_seeds {
+        let passwords = generate_batch(args, config, rng)?;
+        return Ok(reservoir_sample(passwords, k, &mut sampler));
+    }
+
+    let count = config.password_count;
+    let mut error = None;
+    let chunks = (0..count).step_by(STREAM_CHUNK as usize).map_while(|done| {
+        let params = GenerationParams {
+            count: (count - done).min(STREAM_CHUNK),
+            ..config.gen_params.clone()
+        };
+        generate_passwords(&config.char_set, &params, rng)
+            .map_err(|e| error = Some(e))
+            .ok()
+    });
+    let sample = reservoir_sample(chunks.flatten(), k, &mut sampler);
+    match error {
+        Some(e) => Err(e.into()),
+        None => Ok(sample),
+    }
+}
+
+/// Generates every password of the batch, with `--progress` reports if asked
+fn generate_batch<R: Rng>(
+    args: &Args,
+    config: &Config,
+    rng: &mut R,
+) -> Result<Vec<String>, Failure> {
+    let count = config.password_count;
+    // Chunks would only be deduplicated within themselves, so --unique
+    // generates in a single batch without progress reports
+    let show_progress = args.progress && !args.quiet && !args.unique && count >= PROGRESS_INTERVAL;
+
+    if let (true, Some(seed)) = (args.derive_seeds, args.seed) {
+        // One password per derived RNG; the shared RNG is left untouched
+        let params = GenerationParams {
+            count: 1,
+            ..config.gen_params.clone()
+        };
+        let mut passwords = Vec::with_capacity(count as usize);
+        for i in 0..count {
+            let mut rng = seeded_rng(derive_seed(seed, u64::from(i)), args.pepper.as_deref())?;
+            if args.memorable {
+                passwords.push(generate_memorable(&config.memorable_params, &mut rng));
+            } else {
+                passwords.append(&mut generate_passwords(
+                    &config.char_set,
+                    &params,
+                    &mut rng,
+                )?);
+            }
+            if show_progress {
+                report_progress(i + 1, count);
+            }
+        }
+        Ok(passwords)
+    } else if args.parallel {
+        generate_parallel(config, &config.gen_params, rng)
+    } else if args.memorable {
+        let mut passwords = Vec::with_capacity(count as usize);
+        for done in 1..=count {
+            passwords.push(generate_memorable(&config.memorable_params, rng));
+            if show_progress {
+                report_progress(done, count);
+            }
+        }
+        Ok(passwords)
+    } else if show_progress {
+        // Generate in chunks so progress can be reported between them. Each
+        // password draws from the RNG independently of the batch size, so the
+        // output is the same as a single batch.
+        let mut passwords = Vec::with_capacity(count as usize);
+        let mut chunk = Vec::new();
+        let mut done = 0;
+        while done < count {
+            let params = GenerationParams {
+                count: (count - done).min(PROGRESS_INTERVAL),
+                ..config.gen_params.clone()
+            };
+            generate_passwords_into(&config.char_set, &params, rng, &mut chunk)?;
+            passwords.append(&mut chunk);
+            done += params.count;
+            report_progress(done, count);
+        }
+        Ok(passwords)
+    } else {
+        Ok(generate_passwords(
+            &config.char_set,
+            &config.gen_params,
+            rng,
+        )?)
+    }
+}
+
+/// Generates a `--parallel` batch with `generate_passwords_parallel`
+///
+/// The chunks are seeded from a single `u64` drawn from `rng`, so a seeded
+/// run gives the same passwords whatever the number of threads, though not
+/// the ones a serial run with that seed gives.
+#[cfg(feature = "rayon")]
+fn generate_parallel<R: Rng>(
+    config: &Config,
+    params: &GenerationParams,
+    rng: &mut R,
+) -> Result<Vec<String>, Failure> {
+    Ok(generate_passwords_parallel(
+        &config.char_set,
+        params,
+        rng.random(),
+    )?)
+}
+
+#[cfg(not(feature = "rayon"))]
+fn generate_parallel<R: Rng>(
+    _config: &Config,
+    _params: &GenerationParams,
+    _rng: &mut R,
+) -> Result<Vec<String>, Failure> {
+    Err(
+        "Error: --parallel is not available (rpg was built without the 'rayon' feature)."
+            .to_string()
+            .into(),
+    )
+}
+
+/// Whether the terminal locale can display the block characters in a QR code
+#[cfg(feature = "qr")]
+fn terminal_supports_unicode() -> bool {
+    // The first non-empty locale variable wins, as in setlocale(3)
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .is_some_and(|locale| {
+            let locale = locale.to_ascii_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        })
+}
+
+/// Renders `text` as a QR code using half-block characters, or `#` when
+/// `unicode` is false
+#[cfg(feature = "qr")]
+fn render_qr(text: &str, unicode: bool) -> Result<String, String> {
+    use qrcode::QrCode;
+    use qrcode::render::unicode::Dense1x2;
+
+    let code = QrCode::new(text.as_bytes())
+        .map_err(|e| format!("Error: Could not create QR code: {}", e))?;
+    Ok(if unicode {
+        code.render::<Dense1x2>().build()
+    } else {
+        // Two columns per module keep the code roughly square
+        code.render::<char>()
+            .module_dimensions(2, 1)
+            .dark_color('#')
+            .light_color(' ')
+            .build()
+    })
+}
+
+/// Renders the first password as a QR code for the selected output format
+///
+/// JSON and plist output get an SVG document, text and table output get a terminal rendering,
+/// and env and JSONL output have nowhere to put one.
+fn qr_output(args: &Args, passwords: &[String]) -> Result<Option<String>, String> {
+    let Some(first) = passwords
+        .first()
+        .filter(|_| args.qr && args.format != "env" && args.format != "jsonl")
+    else {
+        return Ok(None);
+    };
+
+    #[cfg(feature = "qr")]
+    {
+        let rendered = if matches!(args.format.as_str(), "json" | "plist") {
+            use qrcode::QrCode;
+            use qrcode::render::svg;
+
+            QrCode::new(first.as_bytes())
+                .map_err(|e| format!("Error: Could not create QR code: {}", e))?
+                .render::<svg::Color>()
+                .build()
+        } else {
+            render_qr(first, terminal_supports_unicode())?
+        };
+        Ok(Some(rendered))
+    }
+    #[cfg(not(feature = "qr"))]
+    {
+        let _ = first;
+        Err(
+            "Error: QR output is not available (rpg was built without the 'qr' feature)."
+                .to_string(),
+        )
+    }
+}
+
+/// Hashes each password for `--hash`
+fn hash_output(args: &Args, passwords: &[String]) -> Result<Option<Vec<String>>, String> {
+    let Some(ref algorithm) = args.hash else {
+        return Ok(None);
+    };
+    if algorithm == "sha256" && args.hash_cost.is_some() {
+        return Err("Error: --hash-cost only applies to bcrypt and argon2.".to_string());
+    }
+    hash_passwords(algorithm, args.hash_cost, passwords).map(Some)
+}
+
+/// Hashes passwords with one of the algorithms compiled into this build
+fn hash_passwords(
+    algorithm: &str,
+    cost: Option<u32>,
+    passwords: &[String],
+) -> Result<Vec<String>, String> {
+    match algorithm {
+        #[cfg(feature = "hash")]
+        "sha256" => Ok(passwords.iter().map(|pass| sha256_hex(pass)).collect()),
+        #[cfg(feature = "bcrypt")]
+        "bcrypt" => {
+            let cost = cost.unwrap_or(bcrypt::DEFAULT_COST);
+            passwords
+                .iter()
+                .map(|pass| {
+                    bcrypt_hash(pass, cost)
+                        .map_err(|e| format!("Error: Could not hash password with bcrypt: {}", e))
+                })
+                .collect()
+        }
+        #[cfg(feature = "argon2")]
+        "argon2" => {
+            let t_cost = cost.unwrap_or(argon2::Params::DEFAULT_T_COST);
+            passwords
+                .iter()
+                .map(|pass| {
+                    argon2_hash(pass, t_cost)
+                        .map_err(|e| format!("Error: Could not hash password with argon2: {}", e))
+                })
+                .collect()
+        }
+        _ => {
+            let _ = (cost, passwords);
+            Err(format!(
+                "Error: --hash {} is not available (rpg was built without the '{}' feature).",
+                algorithm,
+                if algorithm == "sha256" {
+                    "hash"
+                } else {
+                    algorithm
+                }
+            ))
+        }
+    }
+}
+
+/// Collects the --weight-* flags, or `None` when none of them is set
+fn class_weights(args: &Args) -> Option<ClassWeights> {
+    if args.weight_letters.is_none()
+        && args.weight_digits.is_none()
+        && args.weight_symbols.is_none()
+    {
+        return None;
+    }
+    Some(ClassWeights {
+        letters: args.weight_letters.unwrap_or(1),
+        digits: args.weight_digits.unwrap_or(1),
+        symbols: args.weight_symbols.unwrap_or(1),
+    })
+}
+
+/// A report as a JSON document, or as a single line for `--format jsonl`
+fn report_json(args: &Args, value: &serde_json::Value) -> String {
+    if args.format == "jsonl" {
+        format!("{}\n", value)
+    } else {
+        json_document(args, value)
+    }
+}
+
+/// Renders a `--check` assessment as text or JSON
+///
+/// The password itself is never echoed back.
+fn format_check(args: &Args, check: &PasswordCheck) -> String {
+    let time = crack_time(check.entropy_bits, args.guesses_per_second);
+    if matches!(args.format.as_str(), "json" | "jsonl") {
+        use serde_json::json;
+
+        let value = json!({
+            "length": check.length,
+            "pool_size": check.pool_size,
+            "entropy_bits": check.entropy_bits,
+            "strength": check.strength.to_string(),
+            "crack_time": time,
+            "sequences": check.sequences,
+            "repeats": check.has_repeats,
+        });
+        return report_json(args, &value);
+    }
+
+    let sequences = if check.sequences.is_empty() {
+        "none".to_string()
+    } else {
+        check.sequences.join(", ")
+    };
+    format!(
+        "Length: {}\n\
+        Character pool: {}\n\
+        Entropy: {:.2} bits\n\
+        Strength: {}\n\
+        Estimated time to crack: {}\n\
+        Sequences: {}\n\
+        Repeats: {}\n",
+        check.length,
+        check.pool_size,
+        check.entropy_bits,
+        check.strength,
+        time,
+        sequences,
+        if check.has_repeats { "yes" } else { "no" }
+    )
+}
+
+/// Entropy contributed by each randomly drawn character
+fn bits_per_char(config: &Config) -> f64 {
+    // Weighted fill sampling draws less than log2(set size) bits per character
+    match config.gen_params.weights {
+        Some(ref weights) => weighted_fill_entropy(&config.char_set, weights),
+        None => calculate_entropy(config.char_set.len(), 1),
+    }
+}
+
+/// Entropy of each password the settings generate, before any check digit,
+/// and the number of distinct characters drawn from
+///
+/// Both come from the breakdown `--explain` and `--min-entropy` use, so masks
+/// and patterns count each position's own pool rather than the whole
+/// character set.
+fn entropy_and_pool(args: &Args, config: &Config) -> (f64, usize) {
+    if args.memorable {
+        (
+            memorable_entropy(&config.memorable_params),
+            config.char_set.len(),
+        )
+    } else {
+        let explanation = explain_entropy(&config.char_set, &config.gen_params);
+        (explanation.total_bits(), explanation.charset_size)
+    }
+}
+
+/// Renders `rpg estimate` as text or JSON, in the shape of a `--check` report
+///
+/// Memorable passwords vary in length and aren't drawn from the character
+/// set, so for them the length and pool are left out.
+fn format_estimate(args: &Args, config: &Config) -> String {
+    let (bits, pool_size) = entropy_and_pool(args, config);
+    let strength = Strength::from_entropy(bits);
+    let time = crack_time(bits, args.guesses_per_second);
+    let (length, pool_size) = if args.memorable {
+        (None, None)
+    } else {
+        (Some(config.gen_params.length), Some(pool_size))
+    };
+    if matches!(args.format.as_str(), "json" | "jsonl") {
+        use serde_json::json;
+
+        let value = json!({
+            "length": length,
+            "pool_size": pool_size,
+            "entropy_bits": bits,
+            "strength": strength.to_string(),
+            "crack_time": time,
+        });
+        return report_json(args, &value);
+    }
+
+    let mut text = String::new();
+    if let (Some(length), Some(pool_size)) = (length, pool_size) {
+        text.push_str(&format!(
+            "Length: {}\nCharacter pool: {}\n",
+            length, pool_size
+        ));
+    }
+    text.push_str(&format!(
+        "Entropy: {:.2} bits\n\
+        Strength: {}\n\
+        Estimated time to crack: {}\n",
+        bits, strength, time
+    ));
+    text
+}
+
+/// One `--format jsonl` line: a self-contained JSON object and a newline
+fn jsonl_line(password: Option<&str>, hash: Option<&str>, entropy_bits: f64) -> String {
+    let mut object = serde_json::Map::new();
+    if let Some(password) = password {
+        object.insert("password".to_string(), password.into());
+    }
+    if let Some(hash) = hash {
+        object.insert("hash".to_string(), hash.into());
+    }
+    object.insert("entropy_bits".to_string(), entropy_bits.into());
+    format!("{}\n", serde_json::Value::Object(object))
+}
+
+/// Renders a JSON value as an XML property list, for `--format plist`
+///
+/// Objects become dicts with their keys in order, integers `<integer>`,
+/// other numbers `<real>`. Text is XML-escaped, so any password is safe.
+fn plist_document(value: &serde_json::Value) -> String {
+    fn escape(s: &str) -> String {
+        s.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    }
+
+    fn write_value(out: &mut String, value: &serde_json::Value, indent: usize) {
+        use serde_json::Value;
+
+        let pad = "\t".repeat(indent);
+        match value {
+            Value::Null => {}
+            Value::Bool(b) => out.push_str(&format!("{pad}<{b}/>\n")),
+            Value::Number(n) if n.is_f64() => out.push_str(&format!("{pad}<real>{n}</real>\n")),
+            Value::Number(n) => out.push_str(&format!("{pad}<integer>{n}</integer>\n")),
+            Value::String(s) => out.push_str(&format!("{pad}<string>{}</string>\n", escape(s))),
+            Value::Array(items) => {
+                out.push_str(&format!("{pad}<array>\n"));
+                for item in items {
+                    write_value(out, item, indent + 1);
+                }
+                out.push_str(&format!("{pad}</array>\n"));
+            }
+            Value::Object(map) => {
+                out.push_str(&format!("{pad}<dict>\n"));
+                for (key, item) in map.iter().filter(|(_, item)| !item.is_null()) {
+                    out.push_str(&format!("{pad}\t<key>{}</key>\n", escape(key)));
+                    write_value(out, item, indent + 1);
+                }
+                out.push_str(&format!("{pad}</dict>\n"));
+            }
+        }
+    }
+
+    let mut out = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+         <plist version=\"1.0\">\n",
+    );
+    write_value(&mut out, value, 0);
+    out.push_str("</plist>\n");
+    out
+}
+
+/// Inserts `delimiter` after every `every` characters, for display only
+///
+/// No delimiter follows the last group, so `abcdefgh` with 4 becomes
+/// `abcd-efgh`.
+fn with_delimiters(pass: &str, every: usize, delimiter: char) -> String {
+    let mut out = String::with_capacity(pass.len() + pass.len() / every);
+    for (i, c) in pass.chars().enumerate() {
+        if i > 0 && i % every == 0 {
+            out.push(delimiter);
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// A `--format json` document: indented, or on one line with `--json-compact`
+fn json_document(args: &Args, value: &serde_json::Value) -> String {
+    let json = if args.json_compact {
+        serde_json::to_string(value)
+    } else {
+        serde_json::to_string_pretty(value)
+    };
+    format!("{}\n", json.unwrap())
+}
+
+/// The JSON `passwords` value: an array, or an object keyed by `--labels`
+fn labeled_json(args: &Args, passwords: &[String]) -> serde_json::Value {
+    if args.labels.is_empty() {
+        return passwords.into();
+    }
+    args.labels
+        .iter()
+        .cloned()
+        .zip(passwords.iter().map(|pass| pass.as_str().into()))
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
+
+/// Renders generated passwords in the requested output format
+fn format_output(
+    args: &Args,
+    config: &Config,
+    passwords: &[String],
+    qr: Option<&str>,
+    hashes: Option<&[String]>,
+) -> String {
+    let (entropy_bits, charset_size) = entropy_and_pool(args, config);
+
+    match output_format(args) {
+        format @ ("json" | "plist") => {
+            use serde_json::json;
+            let mut json_output = json!({
+                "passwords": labeled_json(args, passwords),
+                "count": passwords.len(),
+                "length": config.gen_params.length + u32::from(args.check_digit.is_some()),
+                "entropy_bits": entropy_bits,
+                "charset_size": charset_size,
+                "crack_time": crack_time(entropy_bits, args.guesses_per_second),
+                "charset_version": charset_version()
+            });
+            if let Some(svg) = qr {
+                json_output["qr_svg"] = json!(svg);
+            }
+            if let Some(hashes) = hashes {
+                json_output["hashes"] = json!(hashes);
+                if !args.show_plain {
+                    json_output.as_object_mut().unwrap().remove("passwords");
+                }
+            }
+            if format == "plist" {
+                return plist_document(&json_output);
+            }
+            json_document(args, &json_output)
+        }
+        "jsonl" => passwords
+            .iter()
+            .enumerate()
+            .map(|(i, pass)| {
+                let hash = hashes.map(|hashes| hashes[i].as_str());
+                let shown = (hash.is_none() || args.show_plain).then_some(pass.as_str());
+                jsonl_line(shown, hash, entropy_bits)
+            })
+            .collect(),
+        _ if hashes.is_some() => {
+            // Hashes replace the plaintext, or precede it like sha256sum output
+            let shown: Vec<String> = passwords
+                .iter()
+                .zip(hashes.unwrap_or_default())
+                .map(|(pass, hash)| {
+                    if args.show_plain {
+                        format!("{}  {}", hash, pass)
+                    } else {
+                        hash.clone()
+                    }
+                })
+                .collect();
+            format_output(args, config, &shown, qr, None)
+        }
+        "env" => passwords
+            .iter()
+            .enumerate()
+            .map(|(i, pass)| format!("{}_{}={}\n", args.env_prefix, i + 1, shell_quote(pass)))
+            .collect(),
+        format => {
+            let show_header = !args.quiet;
+            // Delimiters and colors only change what's shown; entropy uses
+            // the passwords
+            let decorated: Vec<String>;
+            let shown = if args.delimiter_every.is_some() || args.color || !args.labels.is_empty() {
+                decorated = passwords
+                    .iter()
+                    .enumerate()
+                    .map(|(i, pass)| {
+                        let pass = match args.delimiter_every {
+                            Some(every) => with_delimiters(pass, every as usize, args.delimiter),
+                            None => pass.clone(),
+                        };
+                        let pass = if args.color { colorize(&pass) } else { pass };
+                        match args.labels.get(i) {
+                            Some(label) => format!("{}: {}", label, pass),
+                            None => pass,
+                        }
+                    })
+                    .collect();
+                &decorated[..]
+            } else {
+                passwords
+            };
+            let mut text = if format == "table" {
+                // Repeated copies fill a row each, so every row is one password
+                let columns = match args.repeat {
+                    Some(n) if n > 1 => n as usize,
+                    _ => column_count(passwords.len() as u32),
+                };
+                let table = format_columns(shown, columns, show_header);
+                if show_header {
+                    // Add the crack-time estimate below the "Printing N passwords" line
+                    let (header, rows) = table.split_once('\n').unwrap_or((&table, ""));
+                    format!(
+                        "{}\nEstimated time to crack: {}\n{}",
+                        header,
+                        crack_time(entropy_bits, args.guesses_per_second),
+                        rows
+                    )
+                } else {
+                    table
+                }
+            } else if let Some(ref template) = args.template {
+                // The template was validated in resolve_config
+                passwords
+                    .iter()
+                    .enumerate()
+                    .map(|(i, pass)| {
+                        let line =
+                            apply_template(template, pass, i + 1, entropy_bits).unwrap_or_default();
+                        format!("{}\n", line)
+                    })
+                    .collect()
+            } else if args.show_entropy {
+                format_with_entropy(shown, &vec![entropy_bits; passwords.len()])
+            } else {
+                format_columns(shown, 1, false)
+            };
+            if let Some(code) = qr {
+                text.push('\n');
+                text.push_str(code);
+                text.push('\n');
+            }
+            text
+        }
+    }
+}
+
+/// Whether `--format jsonl` output can be written while generating
+///
+/// `--check` and `--estimate` generate nothing, `--keyring` prints nothing
+/// and `--seeds` runs a batch per seed. Sorting, deduplication, sampling,
+/// hashing and `--explain` need the whole batch, and `--copy` reports after
+/// the run, so those fall back to `run`.
+fn can_stream_jsonl(args: &Args) -> bool {
+    args.format == "jsonl"
+        && !(args.check.is_some()
+            || args.estimate
+            || !args.seeds.is_empty()
+            || args.keyring.is_some()
+            || args.sample.is_some()
+            || args.sort
+            || args.sort_by_length
+            || args.unique
+            || args.hash.is_some()
+            || args.copy
+            || args.explain
+            || args.stats
+            || args.estimate_entropy
+            || args.derive_seeds
+            || args.parallel)
+}
+
+/// Whether a write went through: `Ok(false)` when the reader has closed the pipe
+///
+/// A reader that stops early, like `head`, ends a stream quietly rather than
+/// with an error.
+fn written(result: std::io::Result<()>) -> Result<bool, String> {
+    match result {
+        Err(e) if e.kind() == ErrorKind::BrokenPipe => Ok(false),
+        result => result
+            .map(|()| true)
+            .map_err(|e| format!("Error: Could not write output: {}", e)),
+    }
+}
+
+/// Writes passwords one per line for `--stream` until the reader goes away
+///
+/// Each line is a single write to the line-buffered stdout, so stopping rpg
+/// with Ctrl-C never leaves half a password at the end of the output.
+fn stream_forever<R: Rng, W: Write>(
+    args: &Args,
+    config: &Config,
+    rng: &mut R,
+    out: &mut W,
+) -> Result<(), Failure> {
+    let params = GenerationParams {
+        count: STREAM_CHUNK,
+        ..config.gen_params.clone()
     };
+    let mut chunk = Vec::new();
+    loop {
+        if args.memorable {
+            chunk.extend(
+                (0..STREAM_CHUNK).map(|_| generate_memorable(&config.memorable_params, rng)),
+            );
+        } else {
+            generate_passwords_into(&config.char_set, &params, rng, &mut chunk)?;
+        }
+        for pass in &mut chunk {
+            if args.check_digit.is_some() {
+                let digit = luhn_check_digit(pass);
+                pass.push(digit);
+            }
+            pass.push('\n');
+            if !written(out.write_all(pass.as_bytes()))? {
+                return Ok(());
+            }
+        }
+        chunk.clear();
+    }
+}
+
+/// Writes `--format jsonl` lines in chunks as the passwords are generated
+///
+/// Each password draws from the RNG independently of the chunk size, so the
+/// lines match what `run` produces for the same seed.
+fn stream_jsonl<R: Rng, W: Write>(
+    args: &Args,
+    config: &Config,
+    rng: &mut R,
+    out: &mut W,
+) -> Result<(), Failure> {
+    let (entropy_bits, _) = entropy_and_pool(args, config);
+    let count = config.password_count;
+    let mut chunk = Vec::new();
+    let mut done = 0;
+    while done < count {
+        let params = GenerationParams {
+            count: (count - done).min(PROGRESS_INTERVAL),
+            ..config.gen_params.clone()
+        };
+        if args.memorable {
+            chunk.extend(
+                (0..params.count).map(|_| generate_memorable(&config.memorable_params, rng)),
+            );
+        } else {
+            generate_passwords_into(&config.char_set, &params, rng, &mut chunk)?;
+        }
+        for pass in &mut chunk {
+            if args.check_digit.is_some() {
+                let digit = luhn_check_digit(pass);
+                pass.push(digit);
+            }
+            let line = jsonl_line(Some(pass), None, entropy_bits);
+            for _ in 0..args.repeat.unwrap_or(1) {
+                if !written(out.write_all(line.as_bytes()))? {
+                    return Ok(());
+                }
+            }
+        }
+        if !written(out.flush())? {
+            return Ok(());
+        }
+        chunk.clear();
+        done += params.count;
+        if args.progress && !args.quiet && count >= PROGRESS_INTERVAL {
+            report_progress(done, count);
+        }
+    }
+    Ok(())
+}
+
+/// Output of a generation run: the passwords and the text to print
+struct RunOutput {
+    passwords: Vec<String>,
+    text: String,
+    /// Entropy breakdown for `--explain`, printed to stderr
+    explanation: Option<String>,
+    /// Batch statistics for `--stats`, printed to stderr
+    stats: Option<String>,
+    /// Observed vs theoretical entropy for `--estimate-entropy`, printed to stderr
+    entropy_estimate: Option<String>,
+    /// Resolved settings for `--print-config`, printed to stderr
+    config: Option<String>,
+    /// The character set for `--show-charset`, printed to stderr
+    charset: Option<String>,
+    /// Warnings from `Config`, printed to stderr unless `--quiet`
+    warnings: Vec<String>,
+}
+
+/// A fatal error: the message for stderr and the process exit status
+struct Failure {
+    message: String,
+    exit_code: i32,
+}
+
+impl From<String> for Failure {
+    fn from(message: String) -> Self {
+        Failure {
+            message,
+            exit_code: 1,
+        }
+    }
+}
+
+impl From<PasswordError> for Failure {
+    fn from(e: PasswordError) -> Self {
+        Failure {
+            message: e.to_string(),
+            exit_code: e.exit_code(),
+        }
+    }
+}
+
+/// Runs the parse, generate and format steps without printing anything
+fn run<R: Rng>(args: &Args, rng: &mut R) -> Result<RunOutput, Failure> {
+    // Checking an existing password generates nothing
+    if let Some(ref password) = args.check {
+        return Ok(RunOutput {
+            passwords: Vec::new(),
+            text: format_check(args, &check_password(password)),
+            explanation: None,
+            stats: None,
+            entropy_estimate: None,
+            config: None,
+            charset: None,
+            warnings: Vec::new(),
+        });
+    }
+
+    let config = resolve_config(args)?;
+    let printed_config = config_json(args, &config);
+    let charset = charset_string(args, &config);
+    let explanation = args
+        .explain
+        .then(|| explain_entropy(&config.char_set, &config.gen_params).to_string());
+
+    // Estimating reports on the settings alone
+    if args.estimate {
+        return Ok(RunOutput {
+            passwords: Vec::new(),
+            text: format_estimate(args, &config),
+            explanation,
+            stats: None,
+            entropy_estimate: None,
+            config: printed_config,
+            charset,
+            warnings: config.warnings,
+        });
+    }
+
+    let mut passwords = generate(args, &config, rng)?;
+    // Measured before check digits are appended, which aren't random
+    let entropy_estimate = args
+        .estimate_entropy
+        .then(|| entropy_estimate(&config, &passwords));
+    // The check digit is derived from the password, so it adds no entropy
+    if args.check_digit.is_some() {
+        for pass in &mut passwords {
+            let digit = luhn_check_digit(pass);
+            pass.push(digit);
+        }
+    }
+    // Sorting only reorders the batch, so seeded runs still produce the same set
+    if args.sort {
+        passwords.sort();
+    } else if args.sort_by_length {
+        passwords.sort_by_key(|p| p.chars().count());
+    }
+    // Statistics describe the generated batch, before --repeat copies
+    let stats = args.stats.then(|| batch_stats(&passwords).to_string());
+    // Copies are duplicated, not regenerated, and stay next to each other
+    if let Some(n) = args.repeat {
+        passwords = passwords
+            .into_iter()
+            .flat_map(|pass| std::iter::repeat_n(pass, n as usize))
+            .collect();
+    }
+    let qr = qr_output(args, &passwords)?;
+    let hashes = hash_output(args, &passwords)?;
+    let text = format_output(args, &config, &passwords, qr.as_deref(), hashes.as_deref());
+    Ok(RunOutput {
+        passwords,
+        text,
+        explanation,
+        stats,
+        entropy_estimate,
+        config: printed_config,
+        charset,
+        warnings: config.warnings,
+    })
+}
+
+/// Runs one batch per `--seeds` seed and joins them, labeled with their seed
+///
+/// Each batch is exactly what `--seed` with that seed would print, count
+/// range included, so any one of them can be reproduced on its own. Text
+/// formats put a `# seed N` line above each batch; JSON gives an array of
+/// the batches' documents and JSONL adds the field to every line, both as
+/// `"seed"`. Warnings and the other reports come from the first batch, as
+/// they depend on the options alone.
+fn run_seeds(args: &Args) -> Result<RunOutput, Failure> {
+    let format = output_format(args);
+    // Batches would repeat the same keys and variable names
+    if matches!(format, "plist" | "env") {
+        return Err(format!(
+            "Error: --seeds can't be used with --format {}.\n\
+            Hint: Use --format json, which labels each batch with its seed.",
+            format
+        )
+        .into());
+    }
+    let mut combined: Option<RunOutput> = None;
+    let mut documents = Vec::new();
+    let mut text = String::new();
+    for &seed in &args.seeds {
+        let mut batch_args = Args {
+            seeds: Vec::new(),
+            seed: Some(seed),
+            ..args.clone()
+        };
+        let mut rng = seeded_rng(seed, args.pepper.as_deref())?;
+        pick_count(&mut batch_args, &mut rng);
+        let batch = run(&batch_args, &mut rng)?;
+        let with_seed = |value: &mut serde_json::Value| {
+            if let Some(object) = value.as_object_mut() {
+                object.insert("seed".to_string(), seed.into());
+            }
+        };
+        match format {
+            "json" => {
+                let mut document: serde_json::Value = serde_json::from_str(&batch.text).unwrap();
+                with_seed(&mut document);
+                documents.push(document);
+            }
+            "jsonl" => {
+                for line in batch.text.lines() {
+                    let mut object: serde_json::Value = serde_json::from_str(line).unwrap();
+                    with_seed(&mut object);
+                    text.push_str(&format!("{}\n", object));
+                }
+            }
+            _ => {
+                if !text.is_empty() {
+                    text.push('\n');
+                }
+                text.push_str(&format!("# seed {}\n{}", seed, batch.text));
+            }
+        }
+        match combined {
+            Some(ref mut output) => output.passwords.extend(batch.passwords),
+            None => combined = Some(batch),
+        }
+    }
+    if format == "json" {
+        text = json_document(args, &serde_json::Value::Array(documents));
+    }
+    // clap rejects an empty list, so there was at least one batch
+    let mut output = combined.unwrap();
+    output.text = text;
+    Ok(output)
+}
 
-    // Generate passwords with optional seed
-    let passwords = if let Some(seed) = args.seed {
-        use rand::{SeedableRng, rngs::StdRng};
-        let mut rng = StdRng::seed_from_u64(seed);
-        generate_passwords(&char_set, &gen_params, &mut rng)
+/// Times `generate_passwords` on `BENCHMARK_COUNT` passwords for `--benchmark`
+///
+/// Only generation is timed; resolving the options and formatting the
+/// report are not. Throughput counts the bytes of the generated passwords.
+fn benchmark<R: Rng>(args: &Args, rng: &mut R) -> Result<String, Failure> {
+    let config = resolve_config(args)?;
+    let params = GenerationParams {
+        count: BENCHMARK_COUNT,
+        ..config.gen_params.clone()
+    };
+    let start = std::time::Instant::now();
+    let passwords = if args.parallel {
+        generate_parallel(&config, &params, rng)?
     } else {
-        let mut rng = rand::rng();
-        generate_passwords(&char_set, &gen_params, &mut rng)
+        generate_passwords(&config.char_set, &params, rng)?
     };
+    let seconds = start.elapsed().as_secs_f64().max(f64::MIN_POSITIVE);
+    let bytes: usize = passwords.iter().map(String::len).sum();
+    Ok(format!(
+        "Benchmark: {} passwords of {} characters in {:.3} s\n  \
+        Rate:       {:.0} passwords/s\n  \
+        Throughput: {:.2} MB/s\n",
+        passwords.len(),
+        params.length,
+        seconds,
+        passwords.len() as f64 / seconds,
+        bytes as f64 / seconds / 1_000_000.0
+    ))
+}
 
-    // Handle copy to clipboard
+/// The `--estimate-entropy` report: the Shannon entropy of the characters
+/// actually generated next to what the settings should give
+///
+/// Minimums, patterns and masks don't draw every character from the whole
+/// set, so for those the two are expected to differ somewhat.
+fn entropy_estimate(config: &Config, passwords: &[String]) -> String {
+    let chars: Vec<char> = passwords.iter().flat_map(|pass| pass.chars()).collect();
+    let observed = shannon_entropy(&chars);
+    let theoretical = bits_per_char(config);
+    format!(
+        "Entropy estimate over {} characters:\n  \
+        Observed:    {:.4} bits per character\n  \
+        Theoretical: {:.4} bits per character\n  \
+        Ratio:       {:.2}%\n",
+        chars.len(),
+        observed,
+        theoretical,
+        if theoretical > 0.0 {
+            observed / theoretical * 100.0
+        } else {
+            100.0
+        }
+    )
+}
+
+/// The resolved `PasswordArgs` as JSON, for `--print-config`
+///
+/// Presets are already expanded: `--url-safe` shows up as the included
+/// characters, `--shell-safe` and `--exclude-homoglyphs` as exclusions.
+fn config_json(args: &Args, config: &Config) -> Option<String> {
+    args.print_config
+        .then(|| serde_json::to_string_pretty(&config.password_args).unwrap())
+}
+
+/// The final character set as a sorted string, for `--show-charset`
+fn charset_string(args: &Args, config: &Config) -> Option<String> {
+    args.show_charset.then(|| {
+        let mut chars = config.char_set.clone();
+        chars.sort_unstable();
+        chars.into_iter().collect()
+    })
+}
+
+/// Prints `--print-config` and `--show-charset` output for the streaming paths
+fn print_resolved(args: &Args, config: &Config) {
+    if let Some(json) = config_json(args, config) {
+        eprintln!("{}", json);
+    }
+    if let Some(charset) = charset_string(args, config) {
+        eprintln!("{}", charset);
+    }
+}
+
+/// Writes `text` to stdout, exiting quietly if the reader has gone away
+///
+/// `print!` panics when stdout is a closed pipe, which is how `head` and
+/// `less` say they have read enough.
+fn print_stdout(text: &str) {
+    let mut out = std::io::stdout().lock();
+    match out.write_all(text.as_bytes()).and_then(|()| out.flush()) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::BrokenPipe => std::process::exit(0),
+        Err(e) => {
+            eprintln!("Error: Could not write output: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Runs `--interactive`, printing the password accepted with Enter, if any
+fn interactive<R: Rng>(config: Config, rng: &mut R) -> Result<(), Failure> {
+    #[cfg(feature = "tui")]
+    {
+        let accepted = rpg_util::tui::run_interactive(config.password_args, config.gen_params, rng)
+            .map_err(|e| format!("Error: Interactive mode failed: {}", e))?;
+        if let Some(pass) = accepted {
+            print_stdout(&format!("{}\n", pass));
+        }
+        Ok(())
+    }
+    #[cfg(not(feature = "tui"))]
+    {
+        let _ = (config, rng);
+        Err(
+            "Error: Interactive mode is not available (rpg was built without the 'tui' feature)."
+                .to_string()
+                .into(),
+        )
+    }
+}
+
+/// Copies the first password to the clipboard, reporting the outcome on stderr
+fn copy_to_clipboard(args: &Args, passwords: &[String]) {
     #[cfg(feature = "clipboard")]
     if args.copy && !passwords.is_empty() {
         use arboard::Clipboard;
@@ -209,29 +2533,2256 @@ fn main() {
         }
     }
     #[cfg(not(feature = "clipboard"))]
-    if args.copy && !args.quiet {
-        eprintln!("Warning: Clipboard functionality not available (clipboard feature not enabled)");
+    {
+        let _ = passwords;
+        if args.copy && !args.quiet {
+            eprintln!(
+                "Warning: Clipboard functionality not available (clipboard feature not enabled)"
+            );
+        }
     }
+}
 
-    // Output passwords in requested format
-    match args.format.as_str() {
-        "json" => {
-            use serde_json::json;
-            let json_output = json!({
-                "passwords": passwords,
-                "count": passwords.len(),
-                "length": args.length,
-                "entropy_bits": calculate_entropy(char_set.len(), args.length)
-            });
-            println!("{}", serde_json::to_string_pretty(&json_output).unwrap());
-        }
-        _ => {
-            let show_header = !args.quiet;
-            if args.table {
-                print_columns(passwords, column_count(args.password_count), show_header);
-            } else {
-                print_columns(passwords, 1, false);
+/// Saves `password` in the OS keyring for `--keyring`
+#[cfg(feature = "keyring")]
+fn store_in_keyring(service: &str, account: &str, password: &str) -> Result<(), String> {
+    keyring::Entry::new(service, account)
+        .and_then(|entry| entry.set_password(password))
+        .map_err(|e| {
+            format!(
+                "Error: Could not store the password in the keyring: {}\n\
+                Hint: Check that a keyring service is running and unlocked.",
+                e
+            )
+        })
+}
+
+#[cfg(not(feature = "keyring"))]
+fn store_in_keyring(_service: &str, _account: &str, _password: &str) -> Result<(), String> {
+    Err(
+        "Error: --keyring is not available (rpg was built without the 'keyring' feature)."
+            .to_string(),
+    )
+}
+
+/// Seeds the generator from a fallible entropy source
+///
+/// `rand::rng()` panics when the OS entropy source is unavailable, which
+/// happens in some sandboxes and early-boot environments. Seeding a `StdRng`
+/// up front turns that failure into an ordinary error.
+fn seed_rng<S: TryRngCore>(source: &mut S) -> Result<StdRng, String> {
+    draw_seed(source).map(StdRng::from_seed)
+}
+
+/// Draws the 32 bytes `seed_rng` seeds from, for `--dump-seed` to print
+///
+/// `StdRng::from_seed` on the result is what `StdRng::try_from_rng` would
+/// have built from the same source.
+fn draw_seed<S: TryRngCore>(source: &mut S) -> Result<[u8; 32], String> {
+    let mut seed = [0u8; 32];
+    source.try_fill_bytes(&mut seed).map_err(|e| {
+        format!(
+            "Error: Could not read from the system random number generator: {}\n\
+            Hint: Make sure the OS entropy source (getrandom or /dev/urandom) is available to rpg.",
+            e
+        )
+    })?;
+    Ok(seed)
+}
+
+/// Seeds the generator from `source` like `seed_rng`, and returns the seed in hex too
+///
+/// A file holding that hex is a `--seed-file` that repeats the run.
+fn dumped_seed_rng<S: TryRngCore>(source: &mut S) -> Result<(StdRng, String), String> {
+    let seed = draw_seed(source)?;
+    let hex = seed.iter().map(|b| format!("{:02x}", b)).collect();
+    Ok((StdRng::from_seed(seed), hex))
+}
+
+/// Reads the 32-byte seed for `--seed-file`
+///
+/// A file of exactly 64 hex digits, give or take surrounding whitespace, is
+/// decoded, which is how `--dump-seed` prints a seed. Otherwise only the first
+/// 32 bytes are used, and shorter files are padded with zeros. An empty file
+/// is rejected rather than seeding with all zeros.
+fn read_seed_file(path: &std::path::Path) -> Result<[u8; 32], String> {
+    use std::io::Read;
+
+    let read_error = |e: std::io::Error| {
+        format!(
+            "Error: Could not read seed file '{}': {}",
+            path.display(),
+            e
+        )
+    };
+    // Enough for a hex seed with some whitespace, without reading all of
+    // an endless file such as /dev/urandom
+    let mut contents = Vec::new();
+    std::fs::File::open(path)
+        .and_then(|file| file.take(128).read_to_end(&mut contents))
+        .map_err(read_error)?;
+    if contents.is_empty() {
+        return Err(format!("Error: Seed file '{}' is empty.", path.display()));
+    }
+    let mut seed = [0u8; 32];
+    let text = contents.trim_ascii();
+    if text.len() == 64 && text.iter().all(u8::is_ascii_hexdigit) {
+        for (byte, pair) in seed.iter_mut().zip(text.chunks(2)) {
+            // Both digits were checked above
+            *byte = u8::from_str_radix(std::str::from_utf8(pair).unwrap(), 16).unwrap();
+        }
+    } else {
+        let len = contents.len().min(32);
+        seed[..len].copy_from_slice(&contents[..len]);
+    }
+    Ok(seed)
+}
+
+/// Reads and validates a `--policy-file`
+fn read_policy(path: &std::path::Path) -> Result<Policy, String> {
+    let json = std::fs::read_to_string(path).map_err(|e| {
+        format!(
+            "Error: Could not read policy file '{}': {}",
+            path.display(),
+            e
+        )
+    })?;
+    Policy::from_json(&json)
+        .map_err(|e| format!("Error: Invalid policy file '{}': {}", path.display(), e))
+}
+
+/// Whether the run's passwords can be generated again from a seed: one the
+/// user gave, or the one `--dump-seed` prints
+fn reproducible(args: &Args) -> bool {
+    args.seed.is_some()
+        || args.seed_file.is_some()
+        || args.account.is_some()
+        || args.dump_seed
+        || !args.seeds.is_empty()
+}
+
+/// Builds the RNG for `--seed`, `--seed-file` or `--account`, or `None` to use the OS
+fn user_seeded_rng(args: &Args) -> Result<Option<StdRng>, String> {
+    if let Some(ref account) = args.account {
+        return account_rng(args, account).map(Some);
+    }
+    if let Some(ref path) = args.seed_file {
+        return Ok(Some(StdRng::from_seed(read_seed_file(path)?)));
+    }
+    args.seed
+        .map(|seed| seeded_rng(seed, args.pepper.as_deref()))
+        .transpose()
+}
+
+/// Builds the RNG for `--account`, seeded with `account_seed`
+fn account_rng(args: &Args, account: &str) -> Result<StdRng, String> {
+    #[cfg(feature = "hash")]
+    return Ok(StdRng::from_seed(rpg_util::account_seed(
+        &read_seed_phrase(args)?,
+        account,
+    )));
+    #[cfg(not(feature = "hash"))]
+    {
+        let _ = (args, account);
+        Err(
+            "Error: --account is not available (rpg was built without the 'hash' feature)."
+                .to_string(),
+        )
+    }
+}
+
+/// Reads the master secret for `--account`
+///
+/// It comes from `--seed-phrase-file`, then `RPG_SEED_PHRASE`, then a prompt
+/// on the terminal that doesn't echo, never from the command line, where
+/// shell history and process lists would show it. One trailing line ending
+/// is dropped from the file.
+#[cfg(feature = "hash")]
+fn read_seed_phrase(args: &Args) -> Result<String, String> {
+    let phrase = if let Some(ref path) = args.seed_phrase_file {
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            format!(
+                "Error: Could not read seed phrase file '{}': {}",
+                path.display(),
+                e
+            )
+        })?;
+        let line = text.strip_suffix('\n').unwrap_or(&text);
+        line.strip_suffix('\r').unwrap_or(line).to_string()
+    } else if let Some(phrase) = std::env::var_os(SEED_PHRASE_VAR) {
+        phrase
+            .into_string()
+            .map_err(|_| format!("Error: {} is not valid UTF-8.", SEED_PHRASE_VAR))?
+    } else {
+        rpassword::prompt_password("Seed phrase: ").map_err(|e| {
+            format!(
+                "Error: Could not read the seed phrase: {}\n\
+                Hint: Without a terminal, use --seed-phrase-file or set {}.",
+                e, SEED_PHRASE_VAR
+            )
+        })?
+    };
+    if phrase.is_empty() {
+        return Err("Error: The seed phrase for --account is empty.".to_string());
+    }
+    Ok(phrase)
+}
+
+/// Builds the RNG for `--seed`, keyed with `--pepper` when given
+///
+/// Without a pepper this is `StdRng::seed_from_u64(seed)`, so existing seeded
+/// output is unchanged. With one, the 32-byte seed is `peppered_seed`.
+fn seeded_rng(seed: u64, pepper: Option<&str>) -> Result<StdRng, String> {
+    match pepper {
+        None => Ok(StdRng::seed_from_u64(seed)),
+        #[cfg(feature = "hash")]
+        Some(pepper) => Ok(StdRng::from_seed(rpg_util::peppered_seed(seed, pepper))),
+        #[cfg(not(feature = "hash"))]
+        Some(_) => Err(
+            "Error: --pepper is not available (rpg was built without the 'hash' feature)."
+                .to_string(),
+        ),
+    }
+}
+
+/// Whether `--color` should take effect for this run
+///
+/// Coloring needs `--color`, and is dropped with `--no-color` or whenever
+/// `should_color` says no.
+fn color_enabled(args: &Args) -> bool {
+    args.color && !args.no_color && should_color()
+}
+
+/// Prints configuration warnings to stderr unless `--quiet`
+fn print_warnings(args: &Args, warnings: &[String]) {
+    if !args.quiet {
+        for warning in warnings {
+            eprintln!("{}", warning);
+        }
+    }
+}
+
+fn main() {
+    let mut args = parse_args();
+    if let Some(Commands::Completions { shell }) = args.command {
+        print_stdout(&completions(shell));
+        return;
+    }
+    if args.help_examples {
+        print_stdout(&format!("{}\n", examples_json()));
+        return;
+    }
+    if args.generate_man {
+        print_stdout(&man_page());
+        return;
+    }
+    // From here on, args.color means colors are really on
+    args.color = color_enabled(&args);
+
+    // ASCII art banner is only shown in --help output (via before_help)
+    // No banner is printed during normal execution
+
+    // Set up the RNG once, with optional seed
+    let rng = match user_seeded_rng(&args) {
+        Ok(Some(rng)) => Ok(rng),
+        Ok(None) if args.dump_seed => dumped_seed_rng(&mut OsRng).map(|(rng, hex)| {
+            eprintln!("{}", hex);
+            rng
+        }),
+        Ok(None) => seed_rng(&mut OsRng),
+        Err(e) => Err(e),
+    };
+    let mut rng: Box<dyn RngCore> = match rng {
+        Ok(rng) => Box::new(rng),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    // Each of --seeds picks its own count
+    if args.seeds.is_empty() {
+        pick_count(&mut args, &mut rng);
+    }
+
+    if args.benchmark {
+        match benchmark(&args, &mut rng) {
+            Ok(report) => eprint!("{}", report),
+            Err(e) => {
+                eprintln!("{}", e.message);
+                std::process::exit(e.exit_code);
+            }
+        }
+        return;
+    }
+
+    if args.stdin {
+        let mut config = match resolve_config(&args) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("{}", e.message);
+                std::process::exit(e.exit_code);
             }
+        };
+        print_warnings(&args, &config.warnings);
+        print_resolved(&args, &config);
+        run_stdin_requests(
+            &config.char_set,
+            &mut config.password_args,
+            &config.gen_params,
+            &mut rng,
+        );
+        return;
+    }
+
+    if args.interactive {
+        let result = resolve_config(&args).and_then(|config| {
+            print_warnings(&args, &config.warnings);
+            interactive(config, &mut rng)
+        });
+        if let Err(e) = result {
+            eprintln!("{}", e.message);
+            std::process::exit(e.exit_code);
+        }
+        return;
+    }
+
+    if args.stream {
+        let result = resolve_config(&args).and_then(|config| {
+            print_warnings(&args, &config.warnings);
+            print_resolved(&args, &config);
+            stream_forever(&args, &config, &mut rng, &mut std::io::stdout().lock())
+        });
+        if let Err(e) = result {
+            eprintln!("{}", e.message);
+            std::process::exit(e.exit_code);
         }
+        return;
+    }
+
+    if can_stream_jsonl(&args) {
+        let result = resolve_config(&args).and_then(|config| {
+            print_warnings(&args, &config.warnings);
+            print_resolved(&args, &config);
+            stream_jsonl(&args, &config, &mut rng, &mut std::io::stdout().lock())
+        });
+        if let Err(e) = result {
+            eprintln!("{}", e.message);
+            std::process::exit(e.exit_code);
+        }
+        return;
+    }
+
+    let result = if args.seeds.is_empty() {
+        run(&args, &mut rng)
+    } else {
+        run_seeds(&args)
+    };
+    match result {
+        Ok(output) => {
+            print_warnings(&args, &output.warnings);
+            if let Some(ref config) = output.config {
+                eprintln!("{}", config);
+            }
+            if let Some(ref charset) = output.charset {
+                eprintln!("{}", charset);
+            }
+            copy_to_clipboard(&args, &output.passwords);
+            if let Some(ref explanation) = output.explanation {
+                eprint!("{}", explanation);
+            }
+            if let Some(ref stats) = output.stats {
+                eprint!("{}", stats);
+            }
+            if let Some(ref estimate) = output.entropy_estimate {
+                eprint!("{}", estimate);
+            }
+            // The password goes to the keyring only, never to stdout
+            if let Some(ref service) = args.keyring {
+                let password = output.passwords.first().map_or("", String::as_str);
+                if let Err(e) = store_in_keyring(service, &args.keyring_account, password) {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+                if !args.quiet {
+                    eprintln!(
+                        "Password stored in the keyring (service '{}', account '{}')",
+                        service, args.keyring_account
+                    );
+                }
+                return;
+            }
+            print_stdout(&output.text);
+        }
+        Err(e) => {
+            eprintln!("{}", e.message);
+            std::process::exit(e.exit_code);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rpg_util::{BRACKETS, QUOTES, VOWELS};
+
+    fn run_args(argv: &[&str]) -> Result<RunOutput, String> {
+        let argv = std::iter::once("rpg").chain(argv.iter().copied());
+        let mut args = parse_argv(&Args::command(), argv.map(Into::into).collect())
+            .map_err(|e| e.to_string())?;
+        if !args.seeds.is_empty() {
+            return run_seeds(&args).map_err(|e| e.message);
+        }
+        let mut rng = user_seeded_rng(&args)?.unwrap_or_else(|| StdRng::seed_from_u64(305));
+        pick_count(&mut args, &mut rng);
+        run(&args, &mut rng).map_err(|e| e.message)
+    }
+
+    #[test]
+    fn test_run_text_output() {
+        let output = run_args(&["3", "--length", "20"]).unwrap();
+        let lines: Vec<&str> = output.text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines.iter().all(|l| l.len() == 20));
+        assert_eq!(lines, output.passwords);
+    }
+
+    #[test]
+    fn test_run_format_table_matches_table_flag() {
+        for extra in [&[][..], &["--quiet"], &["--repeat", "2"]] {
+            let flag = run_args(&[&["6", "--table"][..], extra].concat()).unwrap();
+            let format = run_args(&[&["6", "--format", "table"][..], extra].concat()).unwrap();
+            assert_eq!(flag.text, format.text);
+        }
+
+        let err = run_args(&["2", "--format", "table", "--show-entropy"])
+            .err()
+            .unwrap();
+        assert!(err.contains("--show-entropy"), "{err}");
+        assert!(Args::try_parse_from(["rpg", "2", "--table", "--format", "json"]).is_err());
+    }
+
+    #[test]
+    fn test_run_table_output_has_header() {
+        let output = run_args(&["6", "--table"]).unwrap();
+        assert!(
+            output
+                .text
+                .starts_with("Printing 6 passwords in 3 columns\n")
+        );
+        assert_eq!(output.text.lines().count(), 4);
+        assert!(
+            output
+                .text
+                .lines()
+                .nth(1)
+                .unwrap()
+                .starts_with("Estimated time to crack: ")
+        );
+
+        let quiet = run_args(&["6", "--table", "--quiet"]).unwrap();
+        assert!(!quiet.text.contains("Printing"));
+        assert!(!quiet.text.contains("Estimated time to crack"));
+    }
+
+    #[test]
+    fn test_run_json_output() {
+        let output = run_args(&["2", "--format", "json"]).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&output.text).unwrap();
+        assert_eq!(value["count"], 2);
+        assert_eq!(value["passwords"].as_array().unwrap().len(), 2);
+        assert!(value["entropy_bits"].as_f64().unwrap() > 0.0);
+        assert!(value["crack_time"].is_string());
+        assert_eq!(value["charset_version"], rpg_util::charset_version());
+    }
+
+    #[test]
+    fn test_run_json_charset_size() {
+        let output = run_args(&[
+            "1",
+            "--include-chars",
+            "a,a-c,b,0-3",
+            "--length",
+            "10",
+            "--format",
+            "json",
+        ])
+        .unwrap();
+        let value: serde_json::Value = serde_json::from_str(&output.text).unwrap();
+        assert_eq!(value["charset_size"], 7);
+        let bits = value["entropy_bits"].as_f64().unwrap();
+        assert!((bits - 7f64.log2() * 10.0).abs() < 1e-9, "{bits}");
+
+        // A pattern draws from its class pools only, the figure --min-entropy checks
+        let output = run_args(&["2", "--pattern", "NNNN", "--format", "json"]).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&output.text).unwrap();
+        assert_eq!(value["charset_size"], 10);
+        let bits = value["entropy_bits"].as_f64().unwrap();
+        assert!((bits - 4.0 * 10f64.log2()).abs() < 1e-9, "{bits}");
+        let err = run_args(&["2", "--pattern", "NNNN", "--min-entropy", "14"])
+            .err()
+            .unwrap();
+        assert!(err.contains(&format!("{:.1} bits", bits)), "{err}");
+    }
+
+    #[test]
+    fn test_run_labels() {
+        let plain = run_args(&["3", "--seed", "382"]).unwrap();
+        let labels = ["--labels", "github,email,bank"];
+        let output = run_args(&[&["3", "--seed", "382"], &labels[..]].concat()).unwrap();
+        assert_eq!(output.passwords, plain.passwords);
+        let lines: Vec<&str> = output.text.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                format!("github: {}", plain.passwords[0]),
+                format!("email: {}", plain.passwords[1]),
+                format!("bank: {}", plain.passwords[2]),
+            ]
+        );
+
+        let output =
+            run_args(&[&["3", "--seed", "382", "--format", "json"], &labels[..]].concat()).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&output.text).unwrap();
+        assert_eq!(value["passwords"]["github"], plain.passwords[0].as_str());
+        assert_eq!(value["passwords"]["email"], plain.passwords[1].as_str());
+        assert_eq!(value["passwords"]["bank"], plain.passwords[2].as_str());
+
+        let err = run_args(&["2", "--labels", "github,email,bank"])
+            .err()
+            .unwrap();
+        assert!(err.contains("3 labels were given for 2 passwords"), "{err}");
+        let err = run_args(&["2", "--labels", "a,a"]).err().unwrap();
+        assert!(err.contains("'a' is given more than once"), "{err}");
+        let err = run_args(&["2", "--labels", "a,b", "--format", "env"])
+            .err()
+            .unwrap();
+        assert!(
+            err.contains("--labels can't be used with --format env"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_run_sample() {
+        let output = run_args(&["2500", "--sample", "4", "--seed", "383"]).unwrap();
+        assert_eq!(output.passwords.len(), 4);
+        assert_eq!(output.text.lines().count(), 4);
+        let again = run_args(&["2500", "--sample", "4", "--seed", "383"]).unwrap();
+        assert_eq!(again.passwords, output.passwords);
+
+        // The sample is drawn from the batch, and --labels counts the sample
+        let output = run_args(&[
+            "20",
+            "--include-chars",
+            "a,b",
+            "--length",
+            "1",
+            "--sample",
+            "2",
+            "--labels",
+            "x,y",
+            "--format",
+            "json",
+        ])
+        .unwrap();
+        assert_eq!(output.passwords.len(), 2);
+        assert!(output.passwords.iter().all(|p| p == "a" || p == "b"));
+        let value: serde_json::Value = serde_json::from_str(&output.text).unwrap();
+        assert_eq!(value["count"], 2);
+
+        let err = run_args(&["3", "--sample", "5"]).err().unwrap();
+        assert!(
+            err.contains("--sample 5 is more than the 3 passwords to choose from"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_run_keyring_single_password() {
+        let err = run_args(&["3", "--keyring", "rpg-test"]).err().unwrap();
+        assert!(
+            err.contains("--keyring stores a single password, but 3 were requested"),
+            "{err}"
+        );
+        assert!(run_args(&["1", "--keyring", "rpg-test"]).is_ok());
+    }
+
+    #[cfg(feature = "keyring")]
+    #[test]
+    fn test_keyring_round_trip() {
+        let (service, account) = ("rpg-util-test", "round-trip");
+        // CI runners and containers often have no keyring to talk to
+        if let Err(e) = store_in_keyring(service, account, "s3cret-P@ss") {
+            eprintln!("skipping: {e}");
+            return;
+        }
+        let entry = keyring::Entry::new(service, account).unwrap();
+        assert_eq!(entry.get_password().unwrap(), "s3cret-P@ss");
+        entry.delete_credential().unwrap();
+    }
+
+    #[test]
+    fn test_run_json_compact() {
+        let pretty = run_args(&["3", "--format", "json", "--seed", "377"]).unwrap();
+        let compact =
+            run_args(&["3", "--format", "json", "--seed", "377", "--json-compact"]).unwrap();
+        assert_eq!(compact.text.lines().count(), 1);
+        assert!(compact.text.len() < pretty.text.len());
+        let pretty: serde_json::Value = serde_json::from_str(&pretty.text).unwrap();
+        let compact: serde_json::Value = serde_json::from_str(&compact.text).unwrap();
+        assert_eq!(compact, pretty);
+
+        let check =
+            run_args(&["--check", "hunter2", "--format", "json", "--json-compact"]).unwrap();
+        assert_eq!(check.text.lines().count(), 1);
+    }
+
+    #[test]
+    fn test_run_guesses_per_second() {
+        // 10 digits: log2(10) * 10 = 33.2 bits, about 158 years at one guess per second
+        let args = [
+            "1",
+            "--include-chars",
+            "0-9",
+            "--length",
+            "10",
+            "--format",
+            "json",
+        ];
+        let output = run_args(&[&args[..], &["--guesses-per-second", "1"]].concat()).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&output.text).unwrap();
+        assert_eq!(value["crack_time"], "1 century");
+
+        let err = run_args(&[&args[..], &["--guesses-per-second", "0"]].concat())
+            .err()
+            .unwrap();
+        assert!(err.contains("--guesses-per-second"));
+    }
+
+    #[test]
+    fn test_run_env_output() {
+        let output = run_args(&["2", "--format", "env", "--env-prefix", "TOKEN"]).unwrap();
+        let lines: Vec<&str> = output.text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("TOKEN_1="));
+        assert!(lines[1].starts_with("TOKEN_2="));
+    }
+
+    #[test]
+    fn test_run_plist_output() {
+        // Every symbol, including the XML specials & < >, to check the escaping
+        let output = run_args(&[
+            "20",
+            "--format",
+            "plist",
+            "--include-chars",
+            "&,<,>,a",
+            "--length",
+            "8",
+        ])
+        .unwrap();
+        assert!(output.text.starts_with("<?xml"));
+        let value = plist::Value::from_reader_xml(output.text.as_bytes()).unwrap();
+        let dict = value.as_dictionary().unwrap();
+        let passwords: Vec<&str> = dict["passwords"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p.as_string().unwrap())
+            .collect();
+        assert_eq!(passwords, output.passwords);
+        assert_eq!(dict["count"].as_unsigned_integer(), Some(20));
+        assert_eq!(dict["length"].as_unsigned_integer(), Some(8));
+        assert!(dict["entropy_bits"].as_real().unwrap() > 0.0);
+        assert!(dict["crack_time"].as_string().is_some());
+        assert!(output.passwords.iter().any(|p| p.contains('<')));
+    }
+
+    #[test]
+    fn test_run_jsonl_output() {
+        let argv = [
+            "3",
+            "--format",
+            "jsonl",
+            "--length",
+            "12",
+            "--check-digit",
+            "luhn",
+            "--include-chars",
+            "0-9",
+        ];
+        let output = run_args(&argv).unwrap();
+        let lines: Vec<&str> = output.text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        for (line, pass) in lines.iter().zip(&output.passwords) {
+            let value: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert_eq!(value["password"], pass.as_str());
+            // The check digit adds no entropy
+            let bits = value["entropy_bits"].as_f64().unwrap();
+            assert!((bits - 12.0 * 10f64.log2()).abs() < 1e-9);
+        }
+
+        // Streaming writes the same lines as a buffered run
+        let args = Args::parse_from(std::iter::once("rpg").chain(argv));
+        assert!(can_stream_jsonl(&args));
+        let config = resolve_config(&args).ok().unwrap();
+        let mut streamed = Vec::new();
+        stream_jsonl(
+            &args,
+            &config,
+            &mut StdRng::seed_from_u64(305),
+            &mut streamed,
+        )
+        .ok()
+        .unwrap();
+        assert_eq!(String::from_utf8(streamed).unwrap(), output.text);
+
+        // The keyring gets the password instead of stdout
+        let args = Args::parse_from(["rpg", "1", "--keyring", "svc", "--format", "jsonl"]);
+        assert!(!can_stream_jsonl(&args));
+
+        let output = run_args(&["2", "--format", "jsonl", "--hash", "sha256"]).unwrap();
+        for line in output.text.lines() {
+            let value: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert!(value.get("password").is_none());
+            assert_eq!(value["hash"].as_str().unwrap().len(), 64);
+        }
+    }
+
+    #[test]
+    fn test_run_seeded_is_reproducible() {
+        let first = run_args(&["5", "--seed", "42"]).unwrap();
+        let second = run_args(&["5", "--seed", "42"]).unwrap();
+        assert_eq!(first.text, second.text);
+
+        // Pinned across releases; see generate_passwords in the library
+        let output = run_args(&["3", "--seed", "42", "--length", "12"]).unwrap();
+        assert_eq!(
+            output.passwords,
+            ["|6>~NMZXxdm7", "m:4<r>7VKT^a", "b!R8eW.X/p7u"]
+        );
+    }
+
+    #[test]
+    fn test_run_exclude_class() {
+        let output = run_args(&["20", "--exclude-class", "vowels,brackets"]).unwrap();
+        for pass in &output.passwords {
+            assert!(!pass.chars().any(|c| "aeiouAEIOU()[]{}<>".contains(c)));
+        }
+
+        let err = run_args(&["1", "--exclude-class", "nope"]).err().unwrap();
+        assert!(err.contains("Valid classes"));
+    }
+
+    #[test]
+    fn test_run_no_class_shorthands() {
+        let output = run_args(&["20", "--length", "40", "--no-vowels"]).unwrap();
+        for pass in &output.passwords {
+            assert!(!pass.chars().any(|c| VOWELS.contains(&c)), "{pass}");
+        }
+
+        // The shorthands compose with each other and with other exclusions
+        let output = run_args(&[
+            "20",
+            "--length",
+            "40",
+            "--no-vowels",
+            "--no-brackets",
+            "--no-quotes",
+            "--exclude-class",
+            "vowels",
+            "-e",
+            "xyz",
+        ])
+        .unwrap();
+        for pass in &output.passwords {
+            assert!(
+                !pass
+                    .chars()
+                    .any(|c| VOWELS.contains(&c) || BRACKETS.contains(&c) || QUOTES.contains(&c)),
+                "{pass}"
+            );
+            assert!(!pass.chars().any(|c| "xyz".contains(c)), "{pass}");
+        }
+    }
+
+    #[test]
+    fn test_run_exclude_homoglyphs() {
+        let argv = [
+            "1",
+            "--script",
+            "cyrillic",
+            "--numerals-off",
+            "--symbols-off",
+        ];
+        let config = resolve_config(&Args::parse_from(std::iter::once("rpg").chain(argv)))
+            .ok()
+            .unwrap();
+        assert!(config.char_set.contains(&'а'));
+
+        let argv = [&argv[..], &["--exclude-homoglyphs"]].concat();
+        let config = resolve_config(&Args::parse_from(std::iter::once("rpg").chain(argv)))
+            .ok()
+            .unwrap();
+        assert!(!config.char_set.contains(&'а') && !config.char_set.contains(&'Р'));
+        assert!(config.char_set.contains(&'б') && config.char_set.contains(&'Ж'));
+
+        // Canonical Latin letters and digits remain in the default set
+        let config = resolve_config(&Args::parse_from(["rpg", "1", "--exclude-homoglyphs"]))
+            .ok()
+            .unwrap();
+        for &(_, latin) in HOMOGLYPHS {
+            assert!(config.char_set.contains(&latin), "{}", latin);
+        }
+    }
+
+    #[test]
+    fn test_run_shell_safe() {
+        // Long passwords with symbols enabled would almost surely hit an unsafe char otherwise
+        let output = run_args(&["50", "--length", "64", "--shell-safe"]).unwrap();
+        for pass in &output.passwords {
+            assert!(
+                !pass.chars().any(|c| SHELL_UNSAFE_CHARS.contains(&c)),
+                "{}",
+                pass
+            );
+        }
+        assert!(
+            output
+                .passwords
+                .iter()
+                .any(|p| p.chars().any(|c| !c.is_alphanumeric()))
+        );
+
+        // Composes with other exclusions and with --symbols-off
+        let output = run_args(&["5", "--shell-safe", "--symbols-off", "-e", "a-z"]).unwrap();
+        for pass in &output.passwords {
+            assert!(
+                pass.chars()
+                    .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+            );
+        }
+    }
+
+    #[test]
+    fn test_run_show_charset() {
+        let output = run_args(&[
+            "1",
+            "--show-charset",
+            "--symbols-off",
+            "--numerals-off",
+            "--capitals-off",
+        ])
+        .unwrap();
+        assert_eq!(
+            output.charset.as_deref(),
+            Some("abcdefghijklmnopqrstuvwxyz")
+        );
+
+        let output = run_args(&["1", "--show-charset", "--include-chars", "z,a-c,0"]).unwrap();
+        assert_eq!(output.charset.as_deref(), Some("0abcz"));
+        assert!(run_args(&["1"]).unwrap().charset.is_none());
+    }
+
+    #[test]
+    fn test_run_print_config() {
+        let output = run_args(&["2", "--url-safe", "--print-config"]).unwrap();
+        let config: serde_json::Value = serde_json::from_str(&output.config.unwrap()).unwrap();
+        let include: String = config["include_chars"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| c.as_str().unwrap())
+            .collect();
+        assert_eq!(include, URL_SAFE_ALPHABET);
+        assert_eq!(config["password_count"], 2);
+        assert_eq!(config["length"], 16);
+
+        let output = run_args(&["1", "--shell-safe", "-e", "a", "--print-config"]).unwrap();
+        let config: serde_json::Value = serde_json::from_str(&output.config.unwrap()).unwrap();
+        let exclude = config["exclude_chars"].as_array().unwrap();
+        assert!(exclude.contains(&serde_json::json!("a")));
+        for c in SHELL_UNSAFE_CHARS {
+            assert!(exclude.contains(&serde_json::json!(c.to_string())), "{c}");
+        }
+
+        assert!(run_args(&["1"]).unwrap().config.is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "regex")]
+    fn test_run_match() {
+        let output = run_args(&["200", "--length", "8", "--match", "^[0-9]"]).unwrap();
+        assert_eq!(output.passwords.len(), 200);
+        for pass in &output.passwords {
+            assert!(pass.starts_with(|c: char| c.is_ascii_digit()), "{pass}");
+        }
+
+        let err = run_args(&["1", "--match", "^[0-9"]).err().unwrap();
+        assert!(err.contains("Invalid --match regex"), "{err}");
+        assert!(err.contains("unclosed character class"), "{err}");
+
+        let args = ["1", "-n", "--match", "[0-9]", "--retry-limit", "3"];
+        let err = run_args(&args).err().unwrap();
+        assert!(
+            err.contains("Could not satisfy the match constraint after 3 attempts"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    #[cfg(not(feature = "regex"))]
+    fn test_run_match_unavailable() {
+        let error = run_args(&["5", "--match", "^[0-9]"]).err().unwrap();
+        assert!(
+            error.contains("built without the 'regex' feature"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_run_retry_limit() {
+        let args = [
+            "1",
+            "--include-chars",
+            "a",
+            "--length",
+            "3",
+            "--max-repeats",
+            "1",
+        ];
+        let err = run_args(&[&args[..], &["--retry-limit", "5"]].concat())
+            .err()
+            .unwrap();
+        assert!(
+            err.contains("Could not satisfy the max-repeats constraint after 5 attempts"),
+            "{err}"
+        );
+        let err = run_args(&args).err().unwrap();
+        assert!(err.contains("after 1000 attempts"), "{err}");
+    }
+
+    #[test]
+    fn test_run_policy_file() {
+        let path = std::env::temp_dir().join(format!("rpg-policy-{}.json", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"{
+                "min_length": 24,
+                "max_length": 32,
+                "required_classes": ["lowercase", "capitals", "numerals", "symbols"],
+                "forbidden_chars": "\"'`\\",
+                "max_repeats": 1
+            }"#,
+        )
+        .unwrap();
+        let policy = path.to_str().unwrap();
+
+        let output = run_args(&["50", "--policy-file", policy]).unwrap();
+        for pass in &output.passwords {
+            assert_eq!(pass.len(), 24, "{pass}");
+            assert!(pass.chars().any(|c| c.is_ascii_lowercase()), "{pass}");
+            assert!(pass.chars().any(|c| c.is_ascii_uppercase()), "{pass}");
+            assert!(pass.chars().any(|c| c.is_ascii_digit()), "{pass}");
+            assert!(pass.chars().any(|c| c.is_ascii_punctuation()), "{pass}");
+            assert!(!pass.chars().any(|c| "\"'`\\".contains(c)), "{pass}");
+            let bytes = pass.as_bytes();
+            assert!(bytes.windows(2).all(|w| w[0] != w[1]), "{pass}");
+        }
+
+        // Flags merge with the policy, and contradictions are reported
+        let output = run_args(&["5", "--policy-file", policy, "--length", "30"]).unwrap();
+        assert!(output.passwords.iter().all(|p| p.len() == 30));
+        let err = run_args(&["1", "--policy-file", policy, "--length", "8"])
+            .err()
+            .unwrap();
+        assert!(
+            err.contains("outside the policy's range of 24 to 32"),
+            "{err}"
+        );
+        let err = run_args(&["1", "--policy-file", policy, "--symbols-off"])
+            .err()
+            .unwrap();
+        assert!(err.contains("requires symbols"), "{err}");
+
+        std::fs::write(&path, r#"{"min_length": 10, "max_length": 5}"#).unwrap();
+        let err = run_args(&["1", "--policy-file", policy]).err().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(err.contains("Invalid policy file"), "{err}");
+    }
+
+    #[test]
+    fn test_run_include_chars_lacking_minimum_class() {
+        let err = run_args(&["1", "--include-chars", "ab", "--min-symbols", "2"])
+            .err()
+            .unwrap();
+        assert!(err.contains("--min-symbols 2 needs symbols"), "{err}");
+        assert!(run_args(&["1", "--include-chars", "a,b,!", "--min-symbols", "2"]).is_ok());
+    }
+
+    #[test]
+    fn test_run_include_chars_non_ascii() {
+        let err = run_args(&["1", "--include-chars", "a,é"]).err().unwrap();
+        assert!(
+            err.contains("'é' (U+00E9) is not an ASCII character"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_run_exclude_case_insensitive() {
+        let args = ["1", "--exclude-chars", "a", "--show-charset"];
+        let charset = run_args(&args).unwrap().charset.unwrap();
+        assert!(!charset.contains('a') && charset.contains('A'));
+
+        let charset = run_args(&[&args[..], &["--exclude-case-insensitive"]].concat())
+            .unwrap()
+            .charset
+            .unwrap();
+        assert!(!charset.contains('a') && !charset.contains('A'));
+        assert!(charset.contains('b') && charset.contains('B'));
+    }
+
+    #[test]
+    fn test_run_include_back() {
+        let output = run_args(&[
+            "20",
+            "--length",
+            "40",
+            "--exclude-chars",
+            "a-z",
+            "--include-back",
+            "m",
+            "--show-charset",
+        ])
+        .unwrap();
+        let charset = output.charset.unwrap();
+        assert!(charset.contains('m'));
+        assert!(!charset.contains('a'));
+        assert!(output.passwords.iter().any(|p| p.contains('m')));
+        for pass in &output.passwords {
+            assert!(!pass.chars().any(|c| c.is_ascii_lowercase() && c != 'm'));
+        }
+
+        let err = run_args(&["1", "--include-back", "é"]).err().unwrap();
+        assert!(err.contains("not an ASCII character"), "{err}");
+    }
+
+    #[test]
+    fn test_run_safe_symbols() {
+        let output = run_args(&["50", "--safe-symbols", "--min-symbols", "4"]).unwrap();
+        for pass in &output.passwords {
+            for c in pass.chars().filter(|c| c.is_ascii_punctuation()) {
+                assert!(rpg_util::SAFE_SYMBOLS.contains(c), "{c} in {pass}");
+            }
+        }
+        assert!(run_args(&["1", "--trim-symbols"]).is_ok());
+    }
+
+    #[test]
+    fn test_run_inline_symbols() {
+        let output = run_args(&["50", "--symbols", "-_.", "--min-symbols", "4"]).unwrap();
+        for pass in &output.passwords {
+            for c in pass.chars().filter(|c| !c.is_ascii_alphanumeric()) {
+                assert!("-_.".contains(c), "{c} in {pass}");
+            }
+            assert!(pass.chars().any(|c| c.is_ascii_uppercase()), "{pass}");
+        }
+
+        let err = run_args(&["1", "--symbols", "!x"]).err().unwrap();
+        assert!(err.contains("'x' is not a symbol"), "{err}");
+    }
+
+    #[test]
+    fn test_run_url_safe_entropy() {
+        let output = run_args(&["1", "--url-safe", "--length", "10", "--format", "json"]).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&output.text).unwrap();
+        assert_eq!(value["entropy_bits"].as_f64().unwrap(), 60.0);
+    }
+
+    #[test]
+    fn test_run_explain_pattern() {
+        let output = run_args(&["1", "--pattern", "LLNS", "--explain"]).unwrap();
+        let explanation = output.explanation.unwrap();
+        assert!(explanation.contains("Position 1 (lowercase): 1 x log2(26) = 4.70 bits"));
+        assert!(explanation.contains("Position 3 (numeric): 1 x log2(10) = 3.32 bits"));
+        assert!(explanation.contains("Position 4 (symbol): 1 x log2(32) = 5.00 bits"));
+        assert!(explanation.contains("Final: 17.72 bits"));
+
+        // Explanations stay off stdout
+        assert_eq!(output.text.lines().count(), 1);
+        assert!(run_args(&["1"]).unwrap().explanation.is_none());
+    }
+
+    #[cfg(feature = "qr")]
+    #[test]
+    fn test_render_qr() {
+        let unicode = render_qr("correct-horse-42", true).unwrap();
+        assert!(unicode.contains('█') || unicode.contains('▀') || unicode.contains('▄'));
+
+        let ascii = render_qr("correct-horse-42", false).unwrap();
+        assert!(ascii.is_ascii());
+        assert!(ascii.contains('#'));
+        // Every row has the same width
+        let widths: std::collections::HashSet<usize> = ascii.lines().map(str::len).collect();
+        assert_eq!(widths.len(), 1);
+    }
+
+    #[cfg(feature = "qr")]
+    #[test]
+    fn test_run_qr_output() {
+        let output = run_args(&["2", "--qr", "--format", "json"]).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&output.text).unwrap();
+        assert!(value["qr_svg"].as_str().unwrap().contains("<svg"));
+
+        // The QR code follows the passwords in text output
+        let output = run_args(&["2", "--qr"]).unwrap();
+        assert!(output.text.starts_with(&format!(
+            "{}\n{}\n\n",
+            output.passwords[0], output.passwords[1]
+        )));
+
+        let output = run_args(&["2", "--qr", "--format", "env"]).unwrap();
+        assert_eq!(output.text.lines().count(), 2);
+    }
+
+    #[test]
+    fn test_run_count_flag() {
+        assert_eq!(run_args(&["3"]).unwrap().passwords.len(), 3);
+        assert_eq!(run_args(&["--count", "4"]).unwrap().passwords.len(), 4);
+        // Repeating the same value is harmless
+        assert_eq!(run_args(&["5", "--count", "5"]).unwrap().passwords.len(), 5);
+
+        let err = run_args(&["3", "--count", "4"]).err().unwrap();
+        assert!(err.contains("Conflicting password counts"));
+
+        // Without either, the count is still required
+        assert!(Args::try_parse_from(["rpg", "--length", "20"]).is_err());
+    }
+
+    #[test]
+    fn test_run_check_digit() {
+        let output = run_args(&[
+            "5",
+            "--include-chars",
+            "0-9",
+            "--length",
+            "9",
+            "--check-digit",
+            "luhn",
+        ])
+        .unwrap();
+        for pass in &output.passwords {
+            assert_eq!(pass.len(), 10);
+            let (body, check) = pass.split_at(9);
+            assert_eq!(check.chars().next(), Some(luhn_check_digit(body)));
+        }
+
+        let err = run_args(&["1", "--check-digit", "luhn"]).err().unwrap();
+        assert!(err.contains("numeric-only"));
+    }
+
+    #[test]
+    fn test_run_show_entropy() {
+        let output = run_args(&["3", "--length", "12", "--show-entropy"]).unwrap();
+        let lines: Vec<&str> = output.text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        for (line, pass) in lines.iter().zip(&output.passwords) {
+            let rest = line.strip_prefix(pass.as_str()).unwrap();
+            let bits: f64 = rest.trim().strip_suffix(" bits").unwrap().parse().unwrap();
+            assert!((bits - calculate_entropy(94, 12)).abs() < 0.05);
+        }
+    }
+
+    #[test]
+    fn test_run_template() {
+        let output = run_args(&[
+            "2",
+            "--length",
+            "8",
+            "--template",
+            "{{\"user{n}\": \"{password}\", \"len\": {length}}}",
+        ])
+        .unwrap();
+        let lines: Vec<&str> = output.text.lines().collect();
+        assert_eq!(
+            lines[0],
+            format!("{{\"user1\": \"{}\", \"len\": 8}}", output.passwords[0])
+        );
+        assert_eq!(
+            lines[1],
+            format!("{{\"user2\": \"{}\", \"len\": 8}}", output.passwords[1])
+        );
+
+        let output = run_args(&["1", "--include-chars", "0-9", "--template", "{entropy}"]).unwrap();
+        assert_eq!(output.text, "53.2\n");
+
+        let err = run_args(&["1", "--template", "{nope}"]).err().unwrap();
+        assert!(err.contains("Invalid --template"));
+    }
+
+    #[test]
+    fn test_run_sort() {
+        let unsorted = run_args(&["20", "--seed", "323"]).unwrap().passwords;
+        let sorted = run_args(&["20", "--seed", "323", "--sort"]).unwrap();
+        assert!(sorted.passwords.is_sorted());
+        assert_eq!(sorted.text.lines().collect::<Vec<_>>(), sorted.passwords);
+
+        // Same multiset of passwords as the unsorted run
+        let mut expected = unsorted;
+        expected.sort();
+        assert_eq!(sorted.passwords, expected);
+    }
+
+    #[test]
+    fn test_run_sort_by_length() {
+        let memorable = [
+            "8",
+            "--seed",
+            "323",
+            "--memorable",
+            "--memorable-words",
+            "3",
+        ];
+        let unsorted = run_args(&memorable).unwrap().passwords;
+        let sorted = run_args(&[&memorable[..], &["--sort-by-length"]].concat())
+            .unwrap()
+            .passwords;
+        assert!(sorted.is_sorted_by_key(String::len));
+
+        let mut expected = unsorted;
+        expected.sort_by_key(String::len);
+        assert_eq!(sorted, expected);
+    }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    fn test_run_hash() {
+        let output = run_args(&["2", "--hash", "sha256"]).unwrap();
+        let lines: Vec<&str> = output.text.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                sha256_hex(&output.passwords[0]),
+                sha256_hex(&output.passwords[1])
+            ]
+        );
+
+        let output = run_args(&["1", "--hash", "sha256", "--show-plain"]).unwrap();
+        let pass = &output.passwords[0];
+        assert_eq!(output.text, format!("{}  {}\n", sha256_hex(pass), pass));
+    }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    fn test_run_hash_json() {
+        let output = run_args(&["2", "--hash", "sha256", "--format", "json"]).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&output.text).unwrap();
+        assert_eq!(value["hashes"][1], sha256_hex(&output.passwords[1]));
+        assert!(value.get("passwords").is_none());
+
+        let output =
+            run_args(&["2", "--hash", "sha256", "--show-plain", "--format", "json"]).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&output.text).unwrap();
+        assert_eq!(value["passwords"][0], output.passwords[0].as_str());
+        assert_eq!(value["hashes"][0], sha256_hex(&output.passwords[0]));
+    }
+
+    #[cfg(feature = "bcrypt")]
+    #[test]
+    fn test_run_hash_bcrypt() {
+        let argv = [
+            "2",
+            "--hash",
+            "bcrypt",
+            "--hash-cost",
+            "4",
+            "--format",
+            "json",
+        ];
+        let output = run_args(&argv).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&output.text).unwrap();
+        for (pass, hash) in output
+            .passwords
+            .iter()
+            .zip(value["hashes"].as_array().unwrap())
+        {
+            assert!(bcrypt::verify(pass, hash.as_str().unwrap()).unwrap());
+        }
+    }
+
+    #[cfg(feature = "argon2")]
+    #[test]
+    fn test_run_hash_argon2() {
+        use argon2::password_hash::{PasswordHash, PasswordVerifier};
+
+        let output = run_args(&["2", "--hash", "argon2", "--hash-cost", "1"]).unwrap();
+        for (pass, hash) in output.passwords.iter().zip(output.text.lines()) {
+            let parsed = PasswordHash::new(hash).unwrap();
+            assert!(
+                argon2::Argon2::default()
+                    .verify_password(pass.as_bytes(), &parsed)
+                    .is_ok()
+            );
+        }
+    }
+
+    #[test]
+    fn test_run_hash_cost_rejected_for_sha256() {
+        let err = run_args(&["1", "--hash", "sha256", "--hash-cost", "4"])
+            .err()
+            .unwrap();
+        assert!(err.contains("--hash-cost only applies to bcrypt and argon2"));
+    }
+
+    #[test]
+    fn test_run_mask() {
+        let output = run_args(&["10", "--mask", "[A-F]{2}-[0-9]{3}"]).unwrap();
+        for pass in &output.passwords {
+            let bytes = pass.as_bytes();
+            assert_eq!(bytes.len(), 6);
+            assert!(bytes[..2].iter().all(|b| (b'A'..=b'F').contains(b)));
+            assert_eq!(bytes[2], b'-');
+            assert!(bytes[3..].iter().all(u8::is_ascii_digit));
+        }
+
+        let err = run_args(&["1", "--mask", "[a-z][!@]", "--symbols-off"])
+            .err()
+            .unwrap();
+        assert!(err.contains("Mask position 2 has no characters left"));
+
+        // Oversized repeats are errors, not allocation failures
+        let err = run_args(&["1", "--mask", "a{4000000000}"]).err().unwrap();
+        assert!(err.contains("Mask is longer than the maximum"), "{err}");
+        let err = run_args(&["1", "--mask", "a{20000}"]).err().unwrap();
+        assert!(err.contains("exceeds maximum of 10,000"), "{err}");
+
+        // Entropy counts each position's pool, as --explain does
+        let output = run_args(&["2", "--mask", "[ab]{8}", "--format", "json"]).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&output.text).unwrap();
+        assert_eq!(value["entropy_bits"].as_f64().unwrap(), 8.0);
+        assert_eq!(value["crack_time"], "less than a second");
+        let output = run_args(&["2", "--mask", "[ab]{8}", "--format", "jsonl"]).unwrap();
+        for line in output.text.lines() {
+            let value: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert_eq!(value["entropy_bits"].as_f64().unwrap(), 8.0);
+        }
+        let output = run_args(&["2", "--mask", "[ab]{8}", "--show-entropy"]).unwrap();
+        assert!(output.text.lines().all(|line| line.ends_with(" 8.0 bits")));
+    }
+
+    #[test]
+    fn test_run_class_weights() {
+        let output = run_args(&[
+            "5",
+            "--weight-digits",
+            "0",
+            "--weight-symbols",
+            "0",
+            "--format",
+            "json",
+        ])
+        .unwrap();
+        let value: serde_json::Value = serde_json::from_str(&output.text).unwrap();
+        for pass in value["passwords"].as_array().unwrap() {
+            assert!(
+                pass.as_str()
+                    .unwrap()
+                    .bytes()
+                    .all(|b| b.is_ascii_alphabetic())
+            );
+        }
+        // Only letters are drawn, so the entropy is that of the letters alone
+        let bits = value["entropy_bits"].as_f64().unwrap();
+        assert!((bits - calculate_entropy(52, 16)).abs() < 1e-9);
+
+        let err = run_args(&[
+            "1",
+            "--weight-letters",
+            "0",
+            "--weight-digits",
+            "0",
+            "--weight-symbols",
+            "0",
+        ])
+        .err()
+        .unwrap();
+        assert!(err.contains("non-zero weight"));
+    }
+
+    /// Entropy source that always fails, like a sandbox without getrandom
+    struct FailingRng;
+
+    impl TryRngCore for FailingRng {
+        type Error = std::io::Error;
+
+        fn try_next_u32(&mut self) -> Result<u32, Self::Error> {
+            Err(std::io::Error::other("entropy source unavailable"))
+        }
+
+        fn try_next_u64(&mut self) -> Result<u64, Self::Error> {
+            Err(std::io::Error::other("entropy source unavailable"))
+        }
+
+        fn try_fill_bytes(&mut self, _dst: &mut [u8]) -> Result<(), Self::Error> {
+            Err(std::io::Error::other("entropy source unavailable"))
+        }
+    }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    fn test_run_account() {
+        let path = std::env::temp_dir().join(format!("rpg-phrase-{}.txt", std::process::id()));
+        std::fs::write(&path, "master\n").unwrap();
+        let file = path.to_str().unwrap();
+        let run = |account: &str| {
+            run_args(&["2", "--seed-phrase-file", file, "--account", account])
+                .unwrap()
+                .passwords
+        };
+        let first = run("example.com");
+        assert_eq!(first, run("example.com"));
+        assert_ne!(first, run("example.org"));
+        assert_ne!(first, run_args(&["2"]).unwrap().passwords);
+
+        // The trailing newline isn't part of the secret
+        std::fs::write(&path, "master").unwrap();
+        assert_eq!(first, run("example.com"));
+
+        std::fs::write(&path, "\n").unwrap();
+        let err = run_args(&["1", "--seed-phrase-file", file, "--account", "example.com"])
+            .err()
+            .unwrap();
+        assert!(err.contains("seed phrase for --account is empty"), "{err}");
+        std::fs::remove_file(&path).unwrap();
+
+        // The secret is never taken from the command line
+        let err = Args::try_parse_from(["rpg", "1", "--seed-phrase", "master"]).unwrap_err();
+        assert_eq!(err.kind(), clap::error::ErrorKind::UnknownArgument);
+        let err = Args::try_parse_from(["rpg", "1", "--seed-phrase-file", file]).unwrap_err();
+        assert_eq!(err.kind(), clap::error::ErrorKind::MissingRequiredArgument);
+    }
+
+    #[test]
+    fn test_read_seed_file() {
+        let path = std::env::temp_dir().join(format!("rpg-seed-{}.bin", std::process::id()));
+        std::fs::write(&path, [7u8; 40]).unwrap();
+        assert_eq!(read_seed_file(&path).unwrap(), [7u8; 32]);
+
+        // Short files are zero-padded
+        std::fs::write(&path, b"key").unwrap();
+        let seed = read_seed_file(&path).unwrap();
+        assert_eq!(&seed[..3], b"key");
+        assert!(seed[3..].iter().all(|&b| b == 0));
+
+        std::fs::write(&path, b"").unwrap();
+        let err = read_seed_file(&path).err().unwrap();
+        assert!(err.contains("is empty"), "{err}");
+
+        // 64 hex digits, as --dump-seed prints them, are decoded
+        std::fs::write(&path, format!("  {}\n", "0a".repeat(32))).unwrap();
+        assert_eq!(read_seed_file(&path).unwrap(), [10u8; 32]);
+        std::fs::write(&path, "0a".repeat(31)).unwrap();
+        assert_eq!(&read_seed_file(&path).unwrap()[..2], b"0a");
+        std::fs::remove_file(&path).unwrap();
+        assert!(read_seed_file(&path).is_err());
+    }
+
+    #[test]
+    fn test_dump_seed_round_trip() {
+        let (mut rng, hex) = dumped_seed_rng(&mut StdRng::seed_from_u64(388)).unwrap();
+        assert_eq!(hex.len(), 64);
+        let mut args = Args::parse_from(["rpg", "3", "--dump-seed"]);
+        pick_count(&mut args, &mut rng);
+        let dumped = run(&args, &mut rng).ok().unwrap().passwords;
+
+        // The dumped seed, fed back as a seed file, repeats the passwords
+        let path = std::env::temp_dir().join(format!("rpg-dump-seed-{}.txt", std::process::id()));
+        std::fs::write(&path, format!("{}\n", hex)).unwrap();
+        let replayed = run_args(&["3", "--seed-file", path.to_str().unwrap()]);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(replayed.unwrap().passwords, dumped);
+
+        // Seeding through the dump changes nothing about the generator
+        let direct = seed_rng(&mut StdRng::seed_from_u64(388))
+            .unwrap()
+            .random::<u64>();
+        let (mut rng, _) = dumped_seed_rng(&mut StdRng::seed_from_u64(388)).unwrap();
+        assert_eq!(rng.random::<u64>(), direct);
+        assert!(dumped_seed_rng(&mut FailingRng).is_err());
+        assert!(Args::try_parse_from(["rpg", "1", "--dump-seed", "--seed", "1"]).is_err());
+    }
+
+    #[test]
+    fn test_seed_rng_failure() {
+        let err = seed_rng(&mut FailingRng).err().unwrap();
+        assert!(err.starts_with("Error: Could not read from the system random number generator"));
+        assert!(err.contains("entropy source unavailable"));
+
+        assert!(seed_rng(&mut OsRng).is_ok());
+    }
+
+    #[test]
+    fn test_run_check_weak() {
+        let output = run_args(&["--check", "abc123"]).unwrap();
+        assert!(output.passwords.is_empty());
+        assert!(output.text.contains("Character pool: 36"));
+        assert!(output.text.contains("Strength: weak"));
+        assert!(output.text.contains("Sequences: abc, 123"));
+        assert!(output.text.contains("Repeats: no"));
+        // The password is never echoed
+        assert!(!output.text.contains("abc123"));
+    }
+
+    #[test]
+    fn test_run_check_strong_json() {
+        let output = run_args(&["--check", "vR7#qLp2!zWm9$Tx", "--format", "json"]).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&output.text).unwrap();
+        assert_eq!(value["length"], 16);
+        assert_eq!(value["pool_size"], 94);
+        assert_eq!(value["strength"], "strong");
+        assert_eq!(value["sequences"].as_array().unwrap().len(), 0);
+        assert_eq!(value["repeats"], false);
+        let bits = value["entropy_bits"].as_f64().unwrap();
+        assert!((bits - calculate_entropy(94, 16)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_run_include_space() {
+        let err = run_args(&["1", "--include-chars", "a b"]).err().unwrap();
+        assert!(err.contains("contain a space"));
+
+        let output = run_args(&["20", "--include-chars", "a b", "--allow-space"]).unwrap();
+        assert!(output.passwords.iter().any(|p| p.contains(' ')));
+
+        // Control characters are rejected even with --allow-space
+        let err = run_args(&["1", "--include-chars", "a\tb", "--allow-space"])
+            .err()
+            .unwrap();
+        assert!(err.contains("control character 0x09"));
+    }
+
+    #[test]
+    fn test_run_repeat() {
+        let plain = run_args(&["3"]).unwrap();
+        let output = run_args(&["3", "--repeat", "2"]).unwrap();
+        assert_eq!(output.passwords.len(), 6);
+        for pass in &plain.passwords {
+            assert_eq!(output.text.lines().filter(|l| l == pass).count(), 2);
+        }
+
+        // In a table, each row holds the copies of one password
+        let output = run_args(&["3", "--repeat", "2", "--table", "--quiet"]).unwrap();
+        for (line, pass) in output.text.lines().zip(&plain.passwords) {
+            assert_eq!(line.split_whitespace().collect::<Vec<_>>(), [pass, pass]);
+        }
+    }
+
+    #[test]
+    fn test_run_script() {
+        let output =
+            run_args(&["5", "--script", "greek", "--numerals-off", "--symbols-off"]).unwrap();
+        let greek = |c: char| ('\u{391}'..='\u{3c9}').contains(&c);
+        for pass in &output.passwords {
+            assert_eq!(pass.chars().count(), 16);
+            assert!(pass.chars().all(greek), "{pass}");
+        }
+
+        let output = run_args(&[
+            "5",
+            "--script",
+            "cyrillic",
+            "--capitals-off",
+            "--symbols-off",
+        ])
+        .unwrap();
+        for pass in &output.passwords {
+            assert!(
+                pass.chars()
+                    .all(|c| ('а'..='я').contains(&c) || c.is_ascii_digit())
+            );
+        }
+
+        let output = run_args(&[
+            "1",
+            "--script",
+            "greek",
+            "--exclude-chars",
+            "0-9",
+            "--include-back",
+            "7",
+            "--show-charset",
+        ])
+        .unwrap();
+        let charset = output.charset.unwrap();
+        assert!(charset.contains('7') && !charset.contains('5'), "{charset}");
+    }
+
+    #[test]
+    fn test_run_delimiter_every() {
+        let output = run_args(&[
+            "2",
+            "--length",
+            "16",
+            "--symbols-off",
+            "--delimiter-every",
+            "4",
+        ])
+        .unwrap();
+        for (line, pass) in output.text.lines().zip(&output.passwords) {
+            assert_eq!(line.matches('-').count(), 3);
+            assert_eq!(line.replace('-', ""), *pass);
+            // The returned passwords (used for --copy) have no delimiters
+            assert_eq!(pass.len(), 16);
+        }
+
+        // Entropy ignores the delimiters
+        let plain = run_args(&["1", "--length", "16", "--show-entropy"]).unwrap();
+        let output = run_args(&[
+            "1",
+            "--length",
+            "16",
+            "--show-entropy",
+            "--delimiter-every",
+            "5",
+            "--delimiter",
+            " ",
+        ])
+        .unwrap();
+        let bits = |text: &str| text.split_whitespace().rev().nth(1).unwrap().to_string();
+        assert_eq!(bits(&output.text), bits(&plain.text));
+        assert_eq!(with_delimiters("abcdefghij", 5, ' '), "abcde fghij");
+        assert_eq!(with_delimiters("abc", 4, '-'), "abc");
+    }
+
+    #[test]
+    fn test_run_derive_seeds() {
+        let three = run_args(&["3", "--seed", "7", "--derive-seeds"]).unwrap();
+        let ten = run_args(&["10", "--seed", "7", "--derive-seeds"]).unwrap();
+        assert_eq!(three.passwords[2], ten.passwords[2]);
+        assert_eq!(ten.passwords[..3], three.passwords[..]);
+
+        // Without it, one stream feeds every password in turn
+        let plain = run_args(&["3", "--seed", "7"]).unwrap();
+        assert_ne!(plain.passwords, three.passwords);
+
+        let three = run_args(&["3", "--seed", "7", "--derive-seeds", "--memorable"]).unwrap();
+        let ten = run_args(&["10", "--seed", "7", "--derive-seeds", "--memorable"]).unwrap();
+        assert_eq!(three.passwords[2], ten.passwords[2]);
+    }
+
+    #[test]
+    fn test_run_bulk_bytes_only_unseeded() {
+        let bulk = |argv: &[&str]| {
+            let args = Args::parse_from(std::iter::once("rpg").chain(argv.iter().copied()));
+            resolve_config(&args).ok().unwrap().gen_params.bulk_bytes
+        };
+        assert!(bulk(&["1"]));
+        assert!(!bulk(&["1", "--seed", "42"]));
+        assert!(!bulk(&["1", "--dump-seed"]));
+        assert!(!bulk(&["1", "--seeds", "1,2"]));
+
+        // A seeded PIN keeps one pick per digit
+        let output = run_args(&["3", "--include-chars", "0-9", "-l", "6", "--seed", "42"]).unwrap();
+        assert_eq!(output.passwords, ["285651", "946034", "168014"]);
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn test_run_parallel() {
+        let output = run_args(&["25000", "--seed", "7", "--parallel"]).unwrap();
+        assert_eq!(output.passwords.len(), 25_000);
+        let again = run_args(&["25000", "--seed", "7", "--parallel"]).unwrap();
+        assert_eq!(output.passwords, again.passwords);
+
+        // The chunks are seeded from one draw of the --seed RNG
+        let master = StdRng::seed_from_u64(7).random();
+        let config = resolve_config(&Args::parse_from(["rpg", "25000"]))
+            .ok()
+            .unwrap();
+        let serial =
+            rpg_util::generate_passwords_chunked(&config.char_set, &config.gen_params, master)
+                .unwrap();
+        assert_eq!(output.passwords, serial);
+
+        assert!(run_args(&["5", "--parallel", "--unique"]).is_err());
+    }
+
+    #[test]
+    #[cfg(not(feature = "rayon"))]
+    fn test_run_parallel_unavailable() {
+        let error = run_args(&["5", "--parallel"]).err().unwrap();
+        assert!(
+            error.contains("built without the 'rayon' feature"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_run_ensure_all_types() {
+        let output = run_args(&["100", "--length", "4", "--ensure-all-types"]).unwrap();
+        for pass in &output.passwords {
+            assert_eq!(pass.len(), 4);
+            assert!(pass.chars().any(|c| c.is_ascii_lowercase()), "{pass}");
+            assert!(pass.chars().any(|c| c.is_ascii_uppercase()), "{pass}");
+            assert!(pass.chars().any(|c| c.is_ascii_digit()), "{pass}");
+            assert!(pass.chars().any(|c| c.is_ascii_punctuation()), "{pass}");
+        }
+
+        // Disabled types aren't required
+        let output = run_args(&["50", "--length", "2", "--ensure-all-types", "-c", "-s"]).unwrap();
+        for pass in &output.passwords {
+            assert!(pass.chars().any(|c| c.is_ascii_lowercase()), "{pass}");
+            assert!(pass.chars().any(|c| c.is_ascii_digit()), "{pass}");
+        }
+
+        let err = run_args(&["1", "--length", "3", "--ensure-all-types"])
+            .err()
+            .unwrap();
+        assert!(err.contains("needs at least 4 characters"), "{err}");
+        let err = run_args(&["1", "--ensure-all-types", "--exclude-chars", "0-9"])
+            .err()
+            .unwrap();
+        assert!(err.contains("requires a numeral"), "{err}");
+    }
+
+    #[test]
+    fn test_run_all_unique_chars() {
+        let output = run_args(&["50", "--length", "20", "--all-unique-chars"]).unwrap();
+        for pass in &output.passwords {
+            let distinct: std::collections::BTreeSet<char> = pass.chars().collect();
+            assert_eq!(distinct.len(), 20, "{pass}");
+        }
+
+        let output = run_args(&[
+            "1",
+            "--include-chars",
+            "a-e",
+            "--length",
+            "5",
+            "--all-unique-chars",
+            "--format",
+            "json",
+        ])
+        .unwrap();
+        let value: serde_json::Value = serde_json::from_str(&output.text).unwrap();
+        assert!((value["entropy_bits"].as_f64().unwrap() - 120f64.log2()).abs() < 1e-9);
+
+        let err = run_args(&[
+            "1",
+            "--include-chars",
+            "a-e",
+            "--length",
+            "6",
+            "--all-unique-chars",
+        ])
+        .err()
+        .unwrap();
+        assert!(err.contains("needs 6 different characters"), "{err}");
+    }
+
+    #[test]
+    fn test_run_min_entropy() {
+        // Four digits can't reach 40 bits, and the pattern fixes the length
+        let err = run_args(&["1", "--pattern", "NNNN", "--min-entropy", "40"])
+            .err()
+            .unwrap();
+        assert!(err.contains("13.3 bits"), "{err}");
+        assert!(err.contains("A pattern fixes the length"), "{err}");
+
+        assert!(run_args(&["1", "--pattern", "LLLLUUUUNNNNSSSS", "--min-entropy", "40"]).is_ok());
+        assert!(run_args(&["1", "--min-entropy", "100"]).is_ok());
+        let err = run_args(&["1", "--length", "8", "--min-entropy", "100"])
+            .err()
+            .unwrap();
+        assert!(err.contains("longer --length"), "{err}");
+        assert!(run_args(&["1", "--min-entropy=-1"]).is_err());
+    }
+
+    #[test]
+    fn test_run_maximums() {
+        let output = run_args(&[
+            "50",
+            "--length",
+            "32",
+            "--max-symbols",
+            "3",
+            "--max-capitals",
+            "1",
+        ])
+        .unwrap();
+        for pass in &output.passwords {
+            assert!(
+                pass.chars().filter(char::is_ascii_punctuation).count() <= 3,
+                "{pass}"
+            );
+            assert!(
+                pass.chars().filter(char::is_ascii_uppercase).count() <= 1,
+                "{pass}"
+            );
+        }
+
+        let err = run_args(&["1", "--min-symbols", "4", "--max-symbols", "3"])
+            .err()
+            .unwrap();
+        assert!(
+            err.contains("At least 4 symbols are required, but at most 3"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_run_estimate_entropy() {
+        let output = run_args(&[
+            "500",
+            "--include-chars",
+            "a-p",
+            "--length",
+            "32",
+            "--estimate-entropy",
+            "--seed",
+            "384",
+        ])
+        .unwrap();
+        let report = output.entropy_estimate.unwrap();
+        assert!(report.contains("over 16000 characters"), "{report}");
+        assert!(
+            report.contains("Theoretical: 4.0000 bits per character"),
+            "{report}"
+        );
+        let observed: f64 = report
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("Observed:"))
+            .and_then(|rest| rest.split_whitespace().next())
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!((observed - 4.0).abs() < 0.01, "{observed}");
+        assert!(run_args(&["1"]).unwrap().entropy_estimate.is_none());
+    }
+
+    #[test]
+    fn test_run_stats() {
+        let output = run_args(&["20", "--include-chars", "a-f", "--stats"]).unwrap();
+        let stats = output.stats.unwrap();
+        assert!(stats.contains("Batch statistics: 20 passwords, 320 characters"));
+        assert!(stats.contains("Lowercase: 320 (100.0%)"));
+        // Only the included characters show up
+        let listed: Vec<&str> = stats
+            .lines()
+            .skip_while(|l| !l.contains("Character frequencies"))
+            .skip(1)
+            .map(|l| l.split_whitespace().next().unwrap())
+            .collect();
+        assert_eq!(listed, ["a", "b", "c", "d", "e", "f"]);
+        assert!(run_args(&["1"]).unwrap().stats.is_none());
+    }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    fn test_run_pepper() {
+        let plain = run_args(&["5", "--seed", "7"]).unwrap();
+        let first = run_args(&["5", "--seed", "7", "--pepper", "first"]).unwrap();
+        let second = run_args(&["5", "--seed", "7", "--pepper", "second"]).unwrap();
+        for i in 0..5 {
+            assert_ne!(first.passwords[i], plain.passwords[i]);
+            assert_ne!(first.passwords[i], second.passwords[i]);
+        }
+        // Still reproducible with the same seed and pepper
+        let again = run_args(&["5", "--seed", "7", "--pepper", "first"]).unwrap();
+        assert_eq!(again.passwords, first.passwords);
+    }
+
+    #[test]
+    fn test_run_warns_when_length_is_ignored() {
+        let output = run_args(&["1", "--pattern", "LLNN", "--length", "20"]).unwrap();
+        assert_eq!(output.passwords[0].len(), 4);
+        assert_eq!(
+            output.warnings,
+            ["Warning: --length 20 is ignored; --pattern determines the length (4 characters)."]
+        );
+
+        let output = run_args(&["1", "--mask", "[ab]{3}", "--length", "8"]).unwrap();
+        assert!(output.warnings[0].contains("--mask determines the length (3 characters)"));
+
+        // Nothing to warn about when the lengths agree or --length is absent
+        assert!(
+            run_args(&["1", "--pattern", "LLNN", "--length", "4"])
+                .unwrap()
+                .warnings
+                .is_empty()
+        );
+        assert!(
+            run_args(&["1", "--pattern", "LLNN"])
+                .unwrap()
+                .warnings
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_run_strict_length() {
+        let err = run_args(&[
+            "1",
+            "--pattern",
+            "LLNN",
+            "--length",
+            "20",
+            "--strict-length",
+        ])
+        .err()
+        .unwrap();
+        assert!(
+            err.contains("--length 20 disagrees with --pattern, which is 4 characters long"),
+            "{err}"
+        );
+        let err = run_args(&["1", "--mask", "[ab]{3}", "--length", "8", "--strict-length"])
+            .err()
+            .unwrap();
+        assert!(err.contains("--mask, which is 3 characters long"), "{err}");
+
+        // Matching lengths are fine
+        let output =
+            run_args(&["1", "--pattern", "LLNN", "--length", "4", "--strict-length"]).unwrap();
+        assert_eq!(output.passwords[0].len(), 4);
+        assert!(output.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_run_lowercase_off() {
+        let output =
+            run_args(&["20", "--lowercase-off", "--numerals-off", "--symbols-off"]).unwrap();
+        for pass in &output.passwords {
+            assert!(pass.chars().all(|c| c.is_ascii_uppercase()), "{pass}");
+        }
+
+        let output = run_args(&[
+            "5",
+            "--lowercase-off",
+            "--ensure-all-types",
+            "--length",
+            "3",
+        ])
+        .unwrap();
+        for pass in &output.passwords {
+            assert!(!pass.chars().any(|c| c.is_ascii_lowercase()), "{pass}");
+        }
+
+        let err = run_args(&["1", "--lowercase-off", "-c", "-n", "-s"])
+            .err()
+            .unwrap();
+        assert!(err.contains("All character types are disabled"), "{err}");
+    }
+
+    #[test]
+    fn test_run_color() {
+        // run() colors whenever args.color is set; main decides whether it is
+        let output = run_args(&["3", "--color", "--include-chars", "a,7,#"]).unwrap();
+        for (line, pass) in output.text.lines().zip(&output.passwords) {
+            assert_eq!(line, colorize(pass));
+            assert!(line.contains("\x1b[0m"));
+        }
+        let output = run_args(&["1", "--color", "--include-chars", "7", "--length", "4"]).unwrap();
+        assert_eq!(output.text, "\x1b[33m7777\x1b[0m\n");
+        assert!(!run_args(&["3"]).unwrap().text.contains('\x1b'));
+
+        let args = Args::parse_from(["rpg", "1", "--color", "--no-color"]);
+        assert!(!color_enabled(&args));
+    }
+
+    #[test]
+    fn test_examples_help() {
+        let plain = examples_help(false);
+        assert!(!plain.contains('\x1b'));
+        assert!(plain.starts_with("\nEXAMPLES:\n"));
+        let styled = examples_help(true);
+        assert!(styled.contains("\x1b[1mEXAMPLES:\x1b[0m"));
+        assert_eq!(rpg_util::display_width(&styled), plain.chars().count());
+    }
+
+    #[test]
+    fn test_run_reports_errors() {
+        let err = run_args(&["1", "--pattern", "LLX"]).err().unwrap();
+        assert!(err.contains("Invalid pattern character"));
+
+        let err = run_args(&["1", "--length", "0"]).err().unwrap();
+        assert!(err.contains("Password length must be greater than 0"));
+    }
+
+    #[test]
+    fn test_help_examples() {
+        let examples: Vec<serde_json::Value> = serde_json::from_str(&examples_json()).unwrap();
+        assert_eq!(examples.len(), EXAMPLES.len());
+        assert_eq!(examples[0]["command"], "rpg 5");
+        assert_eq!(examples[0]["description"], "Generate 5 passwords");
+        assert_eq!(examples[0]["section"], "Basic Usage");
+
+        let help = examples_help(false);
+        assert!(help.contains(
+            "\n  Basic Usage:\n    rpg 5                               # Generate 5 passwords\n"
+        ));
+        assert!(
+            help.contains("    rpg 5 --pattern \"LLLNNNSSS\"         # Pattern-based generation\n")
+        );
+    }
+
+    #[test]
+    fn test_completions() {
+        let script = completions(clap_complete::Shell::Bash);
+        for flag in [
+            "--length",
+            "--exclude-chars",
+            "--format",
+            "--seed",
+            "completions",
+        ] {
+            assert!(script.contains(flag), "{flag}");
+        }
+        for shell in [
+            clap_complete::Shell::Zsh,
+            clap_complete::Shell::Fish,
+            clap_complete::Shell::PowerShell,
+        ] {
+            assert!(completions(shell).contains("--length"), "{shell}");
+        }
+
+        // The subcommand parses without a count, and a count still parses without it
+        let args = Args::try_parse_from(["rpg", "completions", "bash"]).unwrap();
+        assert!(matches!(
+            args.command,
+            Some(Commands::Completions {
+                shell: clap_complete::Shell::Bash
+            })
+        ));
+        let args = Args::try_parse_from(["rpg", "5"]).unwrap();
+        assert!(args.command.is_none());
+        assert!(Args::try_parse_from(["rpg", "completions", "tcsh"]).is_err());
+    }
+
+    #[test]
+    fn test_man_page() {
+        let page = man_page();
+        assert!(page.starts_with(".ie"), "{}", &page[..40]);
+        assert!(page.contains(".TH rpg 1"));
+        for option in [r"\-\-length", r"\-\-exclude\-chars", r"\-\-generate\-man"] {
+            assert!(page.contains(option), "{option}");
+        }
+    }
+
+    #[test]
+    fn test_run_count_range() {
+        let first = run_args(&["3-7", "--seed", "371"]).unwrap().passwords;
+        assert!((3..=7).contains(&first.len()), "{}", first.len());
+        for _ in 0..3 {
+            assert_eq!(
+                run_args(&["3-7", "--seed", "371"]).unwrap().passwords,
+                first
+            );
+        }
+
+        // A one-value range is just that count
+        assert_eq!(run_args(&["4-4"]).unwrap().passwords.len(), 4);
+
+        assert_eq!(parse_count_range("2-9"), Ok(CountRange { min: 2, max: 9 }));
+        assert!(parse_count_range("7-3").unwrap_err().contains("backwards"));
+        assert!(
+            parse_count_range("0-3")
+                .unwrap_err()
+                .contains("greater than 0")
+        );
+        assert!(
+            parse_count_range("3-")
+                .unwrap_err()
+                .contains("invalid count")
+        );
+        assert!(
+            parse_count_range("x")
+                .unwrap_err()
+                .contains("invalid count")
+        );
+    }
+
+    #[test]
+    fn test_run_algorithm() {
+        // Each name routes to the mode its flag would select
+        let output = run_args(&["2", "--algorithm", "random", "--length", "10"]).unwrap();
+        assert!(output.passwords.iter().all(|p| p.len() == 10));
+        let output = run_args(&["2", "--algorithm", "pattern", "--pattern", "NNNN"]).unwrap();
+        assert!(
+            output
+                .passwords
+                .iter()
+                .all(|p| p.len() == 4 && p.chars().all(|c| c.is_ascii_digit()))
+        );
+        let output = run_args(&["2", "--algorithm", "mask", "--mask", "[ab]{3}"]).unwrap();
+        assert!(
+            output
+                .passwords
+                .iter()
+                .all(|p| p.len() == 3 && p.chars().all(|c| "ab".contains(c)))
+        );
+        let by_name = run_args(&["2", "--algorithm", "memorable"]).unwrap();
+        let by_flag = run_args(&["2", "--memorable"]).unwrap();
+        assert_eq!(by_name.passwords, by_flag.passwords);
+
+        // Pattern and mask need their definition, and modes can't be mixed
+        let err = run_args(&["1", "--algorithm", "pattern"]).err().unwrap();
+        assert!(err.contains("--pattern <PATTERN>"), "{err}");
+        let err = run_args(&["1", "--algorithm", "random", "--memorable"])
+            .err()
+            .unwrap();
+        assert!(
+            err.contains("--algorithm random can't be used with --memorable"),
+            "{err}"
+        );
+        let err = run_args(&["1", "--algorithm", "memorable", "--check-digit", "luhn"])
+            .err()
+            .unwrap();
+        assert!(
+            err.contains("--algorithm memorable can't be used with --check-digit"),
+            "{err}"
+        );
+        let err = run_args(&["1", "--algorithm", "pronounceable"])
+            .err()
+            .unwrap();
+        assert!(err.contains("invalid value 'pronounceable'"), "{err}");
+    }
+
+    #[test]
+    fn test_run_length_bits() {
+        // A plain number still counts characters
+        let output = run_args(&["2", "--length", "16"]).unwrap();
+        assert!(output.passwords.iter().all(|p| p.len() == 16));
+
+        // 128 bits over the 94 default characters takes 20 of them, and over
+        // the 62 alphanumerics 22
+        let output = run_args(&["2", "--length", "128b"]).unwrap();
+        assert!(output.passwords.iter().all(|p| p.len() == 20));
+        let output = run_args(&["2", "-l", "128b", "--symbols-off"]).unwrap();
+        assert!(output.passwords.iter().all(|p| p.len() == 22));
+
+        // The length still has to fit the policy and the maximum
+        let path = std::env::temp_dir().join(format!("rpg-bits-{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"max_length": 12}"#).unwrap();
+        let err = run_args(&["1", "-l", "128b", "--policy-file", path.to_str().unwrap()]);
+        std::fs::remove_file(&path).unwrap();
+        let err = err.err().unwrap();
+        assert!(
+            err.contains("the length 20 is outside the policy's range"),
+            "{err}"
+        );
+        let err = run_args(&["1", "-l", "128b", "--max-length", "10"])
+            .err()
+            .unwrap();
+        assert!(err.contains("10"), "{err}");
+        let err = run_args(&["1", "-l", "8b", "--include-chars", "a"])
+            .err()
+            .unwrap();
+        assert!(
+            err.contains("can't be reached with a character set of 1"),
+            "{err}"
+        );
+
+        for bad in ["12x", "b", "0b", "-3", "128bits"] {
+            assert!(parse_length(bad).is_err(), "{bad}");
+        }
+        assert_eq!(parse_length("0"), Ok(LengthSpec::Chars(0)));
+        assert_eq!(parse_length("64b"), Ok(LengthSpec::Bits(64)));
+    }
+
+    #[test]
+    fn test_subcommands() {
+        // Without a subcommand nothing changes, and gen is the same thing
+        let plain = run_args(&["5", "--length", "12"]).unwrap();
+        assert_eq!(plain.passwords.len(), 5);
+        let generated = run_args(&["gen", "5", "--length", "12"]).unwrap();
+        assert_eq!(generated.passwords, plain.passwords);
+
+        // check routes to the --check report
+        let check = run_args(&["check", "foo"]).unwrap();
+        assert!(check.passwords.is_empty());
+        assert_eq!(check.text, run_args(&["--check", "foo"]).unwrap().text);
+        assert!(check.text.starts_with("Length: 3\n"), "{}", check.text);
+        let check = run_args(&["check", "--", "-abc", "--format", "json"]).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&check.text).unwrap();
+        assert_eq!(json["length"], 4);
+
+        // estimate reports on the options without generating
+        let estimate = run_args(&["estimate", "--length", "20"]).unwrap();
+        assert!(estimate.passwords.is_empty());
+        assert!(
+            estimate.text.contains("Character pool: 94"),
+            "{}",
+            estimate.text
+        );
+        assert!(
+            estimate.text.contains("Entropy: 131.09 bits"),
+            "{}",
+            estimate.text
+        );
+        assert!(
+            estimate.text.contains("Strength: very strong"),
+            "{}",
+            estimate.text
+        );
+        let estimate = run_args(&["estimate", "--memorable", "--format", "json"]).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&estimate.text).unwrap();
+        assert!(json["length"].is_null() && json["entropy_bits"].as_f64().unwrap() > 25.0);
+        let err = run_args(&["estimate", "--copy"]).err().unwrap();
+        assert!(err.contains("cannot be used with '--copy'"), "{err}");
+
+        // Options before a subcommand are rejected rather than dropped
+        assert!(run_args(&["--quiet", "gen", "5"]).is_err());
+    }
+
+    #[test]
+    fn test_run_seeds() {
+        let argv = ["3", "--seeds", "7,8,9", "--length", "12"];
+        let first = run_args(&argv).unwrap();
+        assert_eq!(first.passwords.len(), 9);
+        assert_eq!(run_args(&argv).unwrap().text, first.text);
+
+        // Each group is labeled, and is the batch --seed alone gives
+        let groups: Vec<&str> = first.text.split("\n\n").collect();
+        assert_eq!(groups.len(), 3);
+        for (group, seed) in groups.iter().zip(["7", "8", "9"]) {
+            let (heading, body) = group.split_once('\n').unwrap();
+            assert_eq!(heading, format!("# seed {}", seed));
+            let alone = run_args(&["3", "--seed", seed, "--length", "12"]).unwrap();
+            assert_eq!(body.lines().collect::<Vec<_>>(), alone.passwords);
+        }
+
+        // Count ranges are picked per seed, as --seed would
+        let ranged = run_args(&["1-9", "--seeds", "4,5"]).unwrap();
+        let alone: usize = ["4", "5"]
+            .iter()
+            .map(|seed| run_args(&["1-9", "--seed", seed]).unwrap().passwords.len())
+            .sum();
+        assert_eq!(ranged.passwords.len(), alone);
+
+        let json = run_args(&["2", "--seeds", "1,2", "--format", "json"]).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&json.text).unwrap();
+        assert_eq!(json[1]["seed"], 2);
+        assert_eq!(json[1]["passwords"].as_array().unwrap().len(), 2);
+        let jsonl = run_args(&["2", "--seeds", "1,2", "--format", "jsonl"]).unwrap();
+        let seeds: Vec<u64> = jsonl
+            .text
+            .lines()
+            .map(|line| {
+                serde_json::from_str::<serde_json::Value>(line).unwrap()["seed"]
+                    .as_u64()
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(seeds, [1, 1, 2, 2]);
+
+        let err = run_args(&["1", "--seeds", "1", "--format", "env"])
+            .err()
+            .unwrap();
+        assert!(
+            err.contains("--seeds can't be used with --format env"),
+            "{err}"
+        );
+        assert!(run_args(&["1", "--seeds", "1", "--seed", "2"]).is_err());
     }
 }
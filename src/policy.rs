@@ -0,0 +1,190 @@
+//! Password policies loaded from JSON, for `--policy-file`
+//!
+//! A policy states what a password system accepts rather than how to
+//! generate for it. `Policy::apply` folds it into `PasswordArgs` on top of
+//! whatever the command line already asked for, and fails when the two
+//! contradict each other instead of quietly letting one side win.
+
+use crate::{CharClasses, PasswordArgs, build_char_set};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use serde::Deserialize;
+
+/// A character class a policy can require
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyClass {
+    Lowercase,
+    Capitals,
+    Numerals,
+    Symbols,
+}
+
+impl PolicyClass {
+    fn name(self) -> &'static str {
+        match self {
+            PolicyClass::Lowercase => "lowercase",
+            PolicyClass::Capitals => "capitals",
+            PolicyClass::Numerals => "numerals",
+            PolicyClass::Symbols => "symbols",
+        }
+    }
+}
+
+/// Password constraints from a policy document
+///
+/// Every field is optional; unknown fields are rejected so a typo can't
+/// silently drop a rule:
+///
+/// ```json
+/// {
+///   "min_length": 12,
+///   "max_length": 64,
+///   "required_classes": ["lowercase", "capitals", "numerals"],
+///   "forbidden_chars": "\"'\\",
+///   "max_repeats": 2
+/// }
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Policy {
+    pub min_length: Option<u32>,
+    pub max_length: Option<u32>,
+    /// Classes every password must contain at least one of
+    pub required_classes: Vec<PolicyClass>,
+    /// Characters no password may contain
+    pub forbidden_chars: String,
+    /// Longest run of one repeated character, e.g. 2 allows `aa` but not `aaa`
+    pub max_repeats: Option<u32>,
+}
+
+impl Policy {
+    /// Parses a policy document and checks it is self-consistent
+    pub fn from_json(json: &str) -> Result<Policy, String> {
+        let policy: Policy = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    /// Rejects policies no password could satisfy
+    pub fn validate(&self) -> Result<(), String> {
+        if self.min_length == Some(0) || self.max_length == Some(0) {
+            return Err("min_length and max_length must be greater than 0".into());
+        }
+        if let (Some(min), Some(max)) = (self.min_length, self.max_length)
+            && min > max
+        {
+            return Err(format!(
+                "min_length {} is greater than max_length {}",
+                min, max
+            ));
+        }
+        if self.max_repeats == Some(0) {
+            return Err("max_repeats must be greater than 0".into());
+        }
+        let mut required = self.required_classes.clone();
+        required.sort_by_key(|class| class.name());
+        required.dedup();
+        if let Some(max) = self.max_length
+            && required.len() as u32 > max
+        {
+            return Err(format!(
+                "{} classes are required, but max_length is {}",
+                required.len(),
+                max
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether the policy requires at least one character of `class`
+    pub fn requires(&self, class: PolicyClass) -> bool {
+        self.required_classes.contains(&class)
+    }
+
+    /// Checks that a length the user chose falls in the policy's range
+    pub fn check_length(&self, length: u32) -> Result<(), String> {
+        let min = self.min_length.unwrap_or(1);
+        let max = self.max_length.unwrap_or(u32::MAX);
+        if (min..=max).contains(&length) {
+            return Ok(());
+        }
+        Err(format!(
+            "the length {} is outside the policy's range of {} to {} characters",
+            length,
+            min,
+            self.max_length
+                .map_or_else(|| String::from("any number of"), |max| format!("{}", max))
+        ))
+    }
+
+    /// Merges the policy into `args`, which already hold the command-line settings
+    ///
+    /// Without `explicit_length` the length is the one in `args` moved into
+    /// the policy's range; an explicit length outside it is an error. Required
+    /// capitals, numerals and symbols raise the matching minimums to 1 (a
+    /// required lowercase letter is left to the caller, as there is no
+    /// lowercase minimum), forbidden characters join the exclusions, and the
+    /// stricter of the two `max_repeats` wins.
+    pub fn apply(&self, args: &mut PasswordArgs, explicit_length: bool) -> Result<(), String> {
+        if explicit_length {
+            self.check_length(args.length)?;
+        }
+        let min = self.min_length.unwrap_or(1);
+        let max = self.max_length.unwrap_or(u32::MAX);
+        args.length = args.length.clamp(min, max);
+
+        for &class in &self.required_classes {
+            let (off, min) = match class {
+                PolicyClass::Lowercase => (args.lowercase_off, None),
+                PolicyClass::Capitals => (args.capitals_off, Some(&mut args.min_capitals)),
+                PolicyClass::Numerals => (args.numerals_off, Some(&mut args.min_numerals)),
+                PolicyClass::Symbols => (args.symbols_off, Some(&mut args.min_symbols)),
+            };
+            if off && args.include_chars.is_none() {
+                return Err(format!(
+                    "the policy requires {}, but --{}-off turns them off",
+                    class.name(),
+                    class.name()
+                ));
+            }
+            if let Some(min) = min {
+                *min = Some(min.unwrap_or(0).max(1));
+            }
+        }
+
+        for c in self.forbidden_chars.chars() {
+            if !args.exclude_chars.contains(&c) {
+                args.exclude_chars.push(c);
+            }
+        }
+
+        args.max_repeats = match (args.max_repeats, self.max_repeats) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+
+        // Exclusions or an include set may still leave a required class empty.
+        // A set that fails to build is left for the caller's own validation.
+        let Ok(char_set) = build_char_set(args) else {
+            return Ok(());
+        };
+        let classes = CharClasses::from_char_set(&char_set);
+        for &class in &self.required_classes {
+            let pool = match class {
+                PolicyClass::Lowercase => &classes.lowercase,
+                PolicyClass::Capitals => &classes.uppercase,
+                PolicyClass::Numerals => &classes.numeric,
+                PolicyClass::Symbols => &classes.symbols,
+            };
+            if pool.is_empty() {
+                return Err(format!(
+                    "the policy requires {}, but the character set has none",
+                    class.name()
+                ));
+            }
+        }
+        Ok(())
+    }
+}
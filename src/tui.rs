@@ -0,0 +1,266 @@
+//! Interactive terminal UI for trying settings with a live preview
+//!
+//! Enabled by the `tui` feature, which `rpg --interactive` needs. Every change
+//! rebuilds the character set with `build_char_set` and regenerates the preview
+//! with `generate_passwords`, so what you see is what the same flags would
+//! print on the command line.
+
+use crate::{GenerationParams, PasswordArgs, build_char_set, generate_passwords, validate_args};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use rand::Rng;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+
+/// The rows of the settings list, in display order
+pub const SETTINGS: [Setting; 5] = [
+    Setting::Length,
+    Setting::Lowercase,
+    Setting::Capitals,
+    Setting::Numerals,
+    Setting::Symbols,
+];
+
+/// A setting that can be changed from the keyboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Setting {
+    Length,
+    Lowercase,
+    Capitals,
+    Numerals,
+    Symbols,
+}
+
+/// What the event loop should do after a key press
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Continue,
+    Quit,
+    /// Leave and print this password
+    Accept(String),
+}
+
+/// The settings being edited and the preview they produce
+#[derive(Debug, Clone)]
+pub struct Model {
+    pub args: PasswordArgs,
+    pub params: GenerationParams,
+    /// Index into `SETTINGS` of the highlighted row
+    pub selected: usize,
+    pub preview: Vec<String>,
+    /// Why the current settings can't generate, shown instead of a preview
+    pub error: Option<String>,
+}
+
+impl Model {
+    /// Starts from resolved command-line settings; call `refresh` to fill the preview
+    pub fn new(args: PasswordArgs, params: GenerationParams) -> Self {
+        Model {
+            args,
+            params,
+            selected: 0,
+            preview: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Regenerates the preview from the current settings
+    pub fn refresh<R: Rng>(&mut self, rng: &mut R) {
+        self.params.length = self.args.length;
+        let result = validate_args(&self.args)
+            .and_then(|()| build_char_set(&self.args))
+            .and_then(|char_set| generate_passwords(&char_set, &self.params, rng));
+        match result {
+            Ok(passwords) => {
+                self.preview = passwords;
+                self.error = None;
+            }
+            Err(e) => {
+                self.preview.clear();
+                self.error = Some(e.to_string());
+            }
+        }
+    }
+
+    /// Flips a character class on or off, or nudges the length by `delta`
+    pub fn change(&mut self, setting: Setting, delta: i32) {
+        match setting {
+            Setting::Length => {
+                self.args.length = self.args.length.saturating_add_signed(delta).max(1)
+            }
+            Setting::Lowercase => self.args.lowercase_off = !self.args.lowercase_off,
+            Setting::Capitals => self.args.capitals_off = !self.args.capitals_off,
+            Setting::Numerals => self.args.numerals_off = !self.args.numerals_off,
+            Setting::Symbols => self.args.symbols_off = !self.args.symbols_off,
+        }
+    }
+
+    /// Applies one key press, refreshing the preview when a setting changed
+    pub fn handle_key<R: Rng>(&mut self, key: KeyCode, rng: &mut R) -> Action {
+        let setting = SETTINGS[self.selected];
+        match key {
+            KeyCode::Char('q') | KeyCode::Esc => return Action::Quit,
+            KeyCode::Enter => {
+                return match self.preview.first() {
+                    Some(pass) => Action::Accept(pass.clone()),
+                    None => Action::Continue,
+                };
+            }
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => self.selected = (self.selected + 1).min(SETTINGS.len() - 1),
+            KeyCode::Left => self.change(setting, -1),
+            KeyCode::Right => self.change(setting, 1),
+            KeyCode::Char(' ') if setting != Setting::Length => self.change(setting, 0),
+            KeyCode::Char('r') => {}
+            _ => return Action::Continue,
+        }
+        if !matches!(key, KeyCode::Up | KeyCode::Down) {
+            self.refresh(rng);
+        }
+        Action::Continue
+    }
+
+    /// The label and current value of a settings row
+    fn row(&self, setting: Setting) -> String {
+        let on_off = |off: bool| if off { "off" } else { "on" };
+        match setting {
+            Setting::Length => format!("Length      < {} >", self.args.length),
+            Setting::Lowercase => format!("Lowercase   {}", on_off(self.args.lowercase_off)),
+            Setting::Capitals => format!("Capitals    {}", on_off(self.args.capitals_off)),
+            Setting::Numerals => format!("Numerals    {}", on_off(self.args.numerals_off)),
+            Setting::Symbols => format!("Symbols     {}", on_off(self.args.symbols_off)),
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [settings_area, preview_area, help_area] = Layout::vertical([
+            Constraint::Length(SETTINGS.len() as u16 + 2),
+            Constraint::Min(3),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let items: Vec<ListItem> = SETTINGS
+            .iter()
+            .map(|&setting| ListItem::new(self.row(setting)))
+            .collect();
+        let list = List::new(items)
+            .block(Block::bordered().title(" Settings "))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        let mut state = ListState::default().with_selected(Some(self.selected));
+        frame.render_stateful_widget(list, settings_area, &mut state);
+
+        let lines: Vec<Line> = match self.error {
+            Some(ref error) => vec![Line::raw(error.as_str())],
+            None => self.preview.iter().map(|p| Line::raw(p.as_str())).collect(),
+        };
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(" Preview ")),
+            preview_area,
+        );
+        frame.render_widget(
+            Paragraph::new(
+                "up/down select  left/right change  space toggle  r regenerate  enter accept  q quit",
+            ),
+            help_area,
+        );
+    }
+}
+
+/// Runs the interactive UI until the user quits, returning the accepted password
+///
+/// The terminal is restored before returning, including on errors.
+pub fn run_interactive<R: Rng>(
+    args: PasswordArgs,
+    params: GenerationParams,
+    rng: &mut R,
+) -> std::io::Result<Option<String>> {
+    let mut model = Model::new(args, params);
+    model.refresh(rng);
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &mut model, rng);
+    ratatui::restore();
+    result
+}
+
+fn event_loop<R: Rng>(
+    terminal: &mut DefaultTerminal,
+    model: &mut Model,
+    rng: &mut R,
+) -> std::io::Result<Option<String>> {
+    loop {
+        terminal.draw(|frame| model.draw(frame))?;
+        match event::read()? {
+            // Windows also reports key releases
+            Event::Key(key) if key.kind == KeyEventKind::Press => {
+                match model.handle_key(key.code, rng) {
+                    Action::Continue => {}
+                    Action::Quit => return Ok(None),
+                    Action::Accept(pass) => return Ok(Some(pass)),
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_toggle_updates_preview() {
+        let args = PasswordArgs {
+            length: 12,
+            password_count: 3,
+            ..Default::default()
+        };
+        let params = GenerationParams {
+            length: 12,
+            count: 3,
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(364);
+        let mut model = Model::new(args, params);
+        model.refresh(&mut rng);
+        assert_eq!(model.preview.len(), 3);
+
+        // Select Symbols and switch them off
+        for _ in 0..4 {
+            model.handle_key(KeyCode::Down, &mut rng);
+        }
+        assert_eq!(SETTINGS[model.selected], Setting::Symbols);
+        assert_eq!(
+            model.handle_key(KeyCode::Char(' '), &mut rng),
+            Action::Continue
+        );
+        assert!(model.args.symbols_off);
+        assert_eq!(model.preview.len(), 3);
+        for pass in &model.preview {
+            assert!(pass.chars().all(|c| c.is_ascii_alphanumeric()), "{pass}");
+        }
+
+        // The length slider regenerates at the new length
+        model.selected = 0;
+        model.handle_key(KeyCode::Right, &mut rng);
+        assert!(model.preview.iter().all(|p| p.len() == 13));
+
+        // Turning every class off shows the error instead of a preview
+        model.args.lowercase_off = true;
+        model.args.capitals_off = true;
+        model.selected = 3;
+        model.handle_key(KeyCode::Char(' '), &mut rng);
+        assert!(model.preview.is_empty());
+        assert!(model.error.is_some());
+        assert_eq!(model.handle_key(KeyCode::Enter, &mut rng), Action::Continue);
+        assert_eq!(model.handle_key(KeyCode::Char('q'), &mut rng), Action::Quit);
+    }
+}
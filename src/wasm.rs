@@ -0,0 +1,103 @@
+//! WebAssembly bindings for browser use
+//!
+//! Enabled by the `wasm` feature. Build a `cdylib` for the browser with
+//! `cargo rustc --lib --release --target wasm32-unknown-unknown --features wasm --crate-type cdylib`
+//! and run `wasm-bindgen` on the output, then call `generate` from
+//! JavaScript. Unseeded calls draw from `crypto.getRandomValues` through
+//! getrandom's `wasm_js` backend.
+
+use crate::{
+    GenerationParams, PasswordArgs, build_char_set, generate_passwords, parse_exclude_chars,
+    parse_pattern, validate_args,
+};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use serde::Deserialize;
+use wasm_bindgen::prelude::*;
+
+/// Options object accepted by `generate`, named after the CLI flags in camelCase
+///
+/// Every field is optional; `excludeChars` and `includeChars` take the same
+/// range syntax as the CLI (e.g. `"a-z,0-9"`).
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Options {
+    lowercase_off: bool,
+    capitals_off: bool,
+    numerals_off: bool,
+    symbols_off: bool,
+    safe_symbols: bool,
+    symbols: Option<String>,
+    exclude_chars: Option<String>,
+    include_chars: Option<String>,
+    min_capitals: Option<u32>,
+    min_numerals: Option<u32>,
+    min_symbols: Option<u32>,
+    pattern: Option<String>,
+    seed: Option<u64>,
+}
+
+/// Generates `count` passwords of `length` characters as a JS array of strings
+///
+/// `opts` may be `undefined`, `null`, or an options object such as
+/// `{ symbolsOff: true, minNumerals: 2, seed: 42 }`. Invalid options throw an
+/// `Error` with the same message the CLI prints.
+#[wasm_bindgen]
+pub fn generate(length: u32, count: u32, opts: JsValue) -> Result<JsValue, JsError> {
+    let opts: Options = if opts.is_undefined() || opts.is_null() {
+        Options::default()
+    } else {
+        serde_wasm_bindgen::from_value(opts)?
+    };
+    let passwords = generate_with_options(length, count, &opts).map_err(|e| JsError::new(&e))?;
+    Ok(serde_wasm_bindgen::to_value(&passwords)?)
+}
+
+/// Builds the character set and parameters from `opts` and runs the generator
+fn generate_with_options(length: u32, count: u32, opts: &Options) -> Result<Vec<String>, String> {
+    let ranges = |s: &Option<String>| -> Result<Option<Vec<char>>, String> {
+        s.as_ref()
+            .map(|s| parse_exclude_chars(s.split(',').map(str::to_string).collect()))
+            .transpose()
+    };
+    let pattern = opts.pattern.as_deref().map(parse_pattern).transpose()?;
+    // A pattern fixes the length, as on the command line
+    let length = pattern.as_ref().map_or(length, |p| p.len() as u32);
+
+    let args = PasswordArgs {
+        lowercase_off: opts.lowercase_off,
+        capitals_off: opts.capitals_off,
+        numerals_off: opts.numerals_off,
+        symbols_off: opts.symbols_off,
+        safe_symbols: opts.safe_symbols,
+        symbols: opts.symbols.as_ref().map(|s| s.chars().collect()),
+        exclude_chars: ranges(&opts.exclude_chars)?.unwrap_or_default(),
+        include_chars: ranges(&opts.include_chars)?,
+        min_capitals: opts.min_capitals,
+        min_numerals: opts.min_numerals,
+        min_symbols: opts.min_symbols,
+        pattern: pattern.clone(),
+        length,
+        password_count: count,
+        ..Default::default()
+    };
+    validate_args(&args).map_err(|e| e.to_string())?;
+    let char_set = build_char_set(&args).map_err(|e| e.to_string())?;
+
+    let params = GenerationParams {
+        length,
+        count,
+        min_capitals: opts.min_capitals,
+        min_numerals: opts.min_numerals,
+        min_symbols: opts.min_symbols,
+        pattern,
+        ..Default::default()
+    };
+    let mut rng: StdRng = match opts.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::try_from_rng(&mut rand::rngs::OsRng).map_err(|e| e.to_string())?,
+    };
+    generate_passwords(&char_set, &params, &mut rng).map_err(|e| e.to_string())
+}
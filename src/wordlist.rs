@@ -0,0 +1,35 @@
+//! Built-in wordlist for memorable password generation
+//!
+//! 256 short, common, lowercase English words, so each word contributes
+//! exactly 8 bits of entropy. The order is part of the seeded-output contract:
+//! changing it changes which word a given RNG draw maps to.
+
+/// Words used by memorable mode (256 entries, 8 bits per word)
+pub const WORDLIST: [&str; 256] = [
+    "baby", "bake", "ball", "barn", "beam", "bean", "bear", "beef", "bell", "belt", "bench",
+    "berry", "bike", "bird", "blue", "boat", "bold", "bone", "book", "boot", "bowl", "brave",
+    "bread", "brick", "bright", "brook", "brush", "bulb", "bunny", "cable", "cake", "calm",
+    "camel", "camp", "candy", "cargo", "carpet", "castle", "cedar", "chair", "chalk", "cheek",
+    "cherry", "chess", "chip", "cider", "clay", "cliff", "clock", "cloud", "coal", "coast", "coat",
+    "cocoa", "coin", "comet", "coral", "corn", "couch", "crab", "crane", "crisp", "crown", "cube",
+    "curve", "daisy", "dance", "dawn", "deer", "delta", "diary", "dinner", "dolphin", "dove",
+    "dragon", "dream", "drum", "duck", "eagle", "earth", "easel", "echo", "elbow", "elder",
+    "ember", "engine", "fabric", "fairy", "falcon", "farm", "feast", "fence", "ferry", "field",
+    "finch", "flag", "flame", "flute", "foam", "forest", "fossil", "fox", "frost", "fruit",
+    "galaxy", "garden", "gecko", "giant", "ginger", "glass", "globe", "glove", "goat", "gold",
+    "grape", "grass", "gravel", "green", "guitar", "hammer", "harbor", "hazel", "heart", "hedge",
+    "helmet", "honey", "horse", "hotel", "island", "ivory", "jacket", "jelly", "jewel", "jungle",
+    "kayak", "kettle", "kite", "koala", "ladder", "lake", "lamp", "lantern", "lemon", "lily",
+    "lion", "llama", "lobster", "lunar", "magnet", "mango", "maple", "marble", "meadow", "melon",
+    "meteor", "mint", "mirror", "monkey", "moose", "mosaic", "motor", "mountain", "muffin",
+    "nectar", "needle", "nutmeg", "oasis", "ocean", "olive", "onion", "orange", "orbit", "otter",
+    "owl", "paddle", "palm", "panda", "paper", "parrot", "pasta", "peach", "pebble", "pencil",
+    "pepper", "piano", "pillow", "pilot", "planet", "plum", "pocket", "pony", "puzzle", "quail",
+    "quartz", "quilt", "rabbit", "radar", "radio", "rain", "raven", "river", "robin", "rocket",
+    "rose", "ruby", "saddle", "salmon", "sand", "scarf", "shadow", "shell", "silver", "sketch",
+    "sky", "slate", "snow", "socket", "spark", "spice", "spoon", "spring", "squid", "star",
+    "stone", "storm", "sugar", "summer", "sunset", "swan", "table", "tango", "teapot", "tiger",
+    "timber", "toast", "tomato", "torch", "tower", "trail", "tulip", "tunnel", "turtle",
+    "umbrella", "valley", "velvet", "violet", "walnut", "walrus", "wave", "willow", "window",
+    "winter", "wizard", "wolf", "yacht", "zebra",
+];
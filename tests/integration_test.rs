@@ -1,4 +1,12 @@
-use std::process::Command;
+// The older tests keep their original style rather than clippy's rewrites
+#![allow(
+    clippy::filter_next,
+    clippy::needless_borrows_for_generic_args,
+    clippy::nonminimal_bool
+)]
+
+use std::io::Write;
+use std::process::{Command, Stdio};
 
 #[test]
 fn test_basic_generation() {
@@ -135,6 +143,22 @@ fn test_cli_invalid_pattern() {
     );
 }
 
+#[test]
+fn test_cli_empty_pattern() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+        .args(["3", "--pattern", ""])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(
+        !output.status.success(),
+        "Should fail with an empty pattern"
+    );
+    assert!(output.stdout.is_empty(), "Should not print blank passwords");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Pattern is empty"), "{stderr}");
+}
+
 #[test]
 fn test_cli_invalid_length_zero() {
     let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
@@ -192,13 +216,14 @@ fn test_cli_table_output() {
     let stdout = String::from_utf8(output.stdout).unwrap();
     let lines: Vec<&str> = stdout.lines().filter(|l| !l.is_empty()).collect();
 
-    // Should have passwords in table format (may be on multiple lines in columns)
-    // With 6 passwords and 2 columns, we'd expect at least 3 lines
-    assert!(
-        lines.len() >= 3,
-        "Expected at least 3 lines in table format, got {}",
+    // With 6 passwords and 3 columns, we'd expect 2 full rows
+    assert_eq!(
+        lines.len(),
+        2,
+        "Expected 2 lines in table format, got {}",
         lines.len()
     );
+    assert!(lines.iter().all(|l| l.split_whitespace().count() == 3));
     // Verify passwords are present by checking non-empty content
     assert!(!stdout.trim().is_empty(), "Output should contain passwords");
 }
@@ -217,6 +242,23 @@ fn test_cli_table_with_header() {
     assert!(stdout.contains("Printing"));
 }
 
+#[test]
+fn test_cli_format_table_matches_table_flag() {
+    let run = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+            .args(args)
+            .output()
+            .expect("Failed to execute command");
+        assert!(output.status.success(), "Command failed: {:?}", output);
+        output.stdout
+    };
+
+    assert_eq!(
+        run(&["6", "--seed", "352", "--table"]),
+        run(&["6", "--seed", "352", "--format", "table"])
+    );
+}
+
 #[test]
 fn test_cli_quiet_mode() {
     let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
@@ -516,3 +558,765 @@ fn test_cli_exclude_chars_with_range() {
     // Should not contain lowercase letters
     assert!(!password.chars().any(|c| c.is_ascii_lowercase()));
 }
+
+#[test]
+fn test_cli_exclude_similar_to_unsatisfiable() {
+    // With only 'a' available, every password equals the excluded string
+    let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+        .args([
+            "1",
+            "--include-chars",
+            "a",
+            "--length",
+            "4",
+            "--exclude-similar-to",
+            "aaaa",
+            "--quiet",
+        ])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(
+        !output.status.success(),
+        "Should fail when every password is similar"
+    );
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("exclude-similar-to"));
+}
+
+#[test]
+fn test_cli_memorable_structure() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+        .args([
+            "10",
+            "--memorable",
+            "--memorable-words",
+            "2",
+            "--memorable-digits",
+            "3",
+            "--quiet",
+        ])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success(), "Command failed: {:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let passwords: Vec<&str> = stdout.lines().filter(|l| !l.is_empty()).collect();
+    assert_eq!(passwords.len(), 10);
+
+    for pass in passwords {
+        let chars: Vec<char> = pass.chars().collect();
+        let n = chars.len();
+        assert!(chars[0].is_ascii_uppercase(), "{}", pass);
+        assert!(!chars[n - 1].is_alphanumeric(), "{}", pass);
+        assert!(
+            chars[n - 4..n - 1].iter().all(|c| c.is_ascii_digit()),
+            "{}",
+            pass
+        );
+        assert!(
+            chars[..n - 4].iter().all(|c| c.is_ascii_alphabetic()),
+            "{}",
+            pass
+        );
+    }
+}
+
+#[test]
+fn test_cli_stdin_requests() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rpg"))
+        .args(["--stdin"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to execute command");
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"2 8\nnot a request\n3 12\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(output.status.success(), "Command failed: {:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 5, "Expected 2 + 3 passwords: {:?}", lines);
+    assert!(lines[..2].iter().all(|l| l.len() == 8));
+    assert!(lines[2..].iter().all(|l| l.len() == 12));
+
+    // The malformed line is reported but doesn't stop processing
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("stdin line 2"));
+}
+
+#[test]
+fn test_cli_format_env_is_valid_shell() {
+    // Include quotes and shell metacharacters to exercise escaping
+    let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+        .args([
+            "3",
+            "--format",
+            "env",
+            "--env-prefix",
+            "DB_PASS",
+            "--include-chars",
+            "a,',$,\",`,;,\\",
+            "--length",
+            "12",
+            "--seed",
+            "304",
+        ])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success(), "Command failed: {:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 3);
+
+    // Source the output in a real shell and read each variable back
+    let script = format!(
+        "{}\nprintf '%s\\n' \"$DB_PASS_1\" \"$DB_PASS_2\" \"$DB_PASS_3\"",
+        stdout
+    );
+    let shell = Command::new("sh")
+        .args(["-c", &script])
+        .output()
+        .expect("Failed to run sh");
+    assert!(shell.status.success(), "Shell rejected output: {:?}", shell);
+
+    let values = String::from_utf8(shell.stdout).unwrap();
+    for (line, value) in lines.iter().zip(values.lines()) {
+        assert!(line.starts_with("DB_PASS_"));
+        assert_eq!(value.len(), 12, "Value was mangled: {:?}", value);
+        assert!(value.chars().all(|c| "a'$\"`;\\".contains(c)));
+    }
+}
+
+#[test]
+fn test_cli_format_env_invalid_prefix() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+        .args(["1", "--format", "env", "--env-prefix", "1BAD"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Invalid --env-prefix"));
+}
+
+#[test]
+fn test_cli_url_safe() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+        .args(["20", "--url-safe", "--length", "32"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success(), "Command failed: {:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    for line in stdout.lines() {
+        // Equivalent to ^[A-Za-z0-9_-]+$
+        assert!(!line.is_empty());
+        assert!(
+            line.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'),
+            "Not URL-safe: {}",
+            line
+        );
+    }
+}
+
+#[test]
+fn test_cli_url_safe_conflicts_with_include_chars() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+        .args(["1", "--url-safe", "--include-chars", "a-z"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("cannot be used with"));
+}
+
+#[test]
+fn test_cli_progress_does_not_affect_stdout() {
+    let run = |extra: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rpg"))
+            .args(["25000", "--length", "8", "--seed", "309"])
+            .args(extra)
+            .output()
+            .expect("Failed to execute command")
+    };
+
+    let plain = run(&[]);
+    let with_progress = run(&["--progress"]);
+
+    assert!(plain.status.success());
+    assert!(with_progress.status.success());
+    assert_eq!(plain.stdout, with_progress.stdout);
+
+    // Progress goes to stderr only
+    let stderr = String::from_utf8(with_progress.stderr).unwrap();
+    assert!(stderr.contains("Progress: 10000/25000"));
+    assert!(stderr.contains("Progress: 25000/25000 passwords (100%)"));
+    assert!(plain.stderr.is_empty());
+}
+
+#[test]
+fn test_cli_seed_file_is_reproducible() {
+    let path = std::env::temp_dir().join(format!("rpg-seed-{}.bin", std::process::id()));
+    std::fs::write(&path, b"0123456789abcdef0123456789abcdef").unwrap();
+
+    let run = || {
+        let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+            .args(["5", "--quiet", "--seed-file"])
+            .arg(&path)
+            .output()
+            .expect("Failed to execute command");
+        assert!(output.status.success(), "Command failed: {:?}", output);
+        output.stdout
+    };
+    let first = run();
+    let second = run();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(first, second);
+    assert_eq!(String::from_utf8(first).unwrap().lines().count(), 5);
+}
+
+#[test]
+fn test_cli_dump_seed_replays_with_seed_file() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+        .args(["4", "--quiet", "--dump-seed"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success(), "Command failed: {:?}", output);
+    let seed = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(seed.trim().len(), 64, "{seed}");
+
+    let path = std::env::temp_dir().join(format!("rpg-dumped-{}.hex", std::process::id()));
+    std::fs::write(&path, &seed).unwrap();
+    let replay = Command::new(env!("CARGO_BIN_EXE_rpg"))
+        .args(["4", "--quiet", "--seed-file"])
+        .arg(&path)
+        .output()
+        .expect("Failed to execute command");
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(replay.status.success(), "Command failed: {:?}", replay);
+    assert_eq!(output.stdout, replay.stdout);
+}
+
+#[test]
+fn test_cli_env_vars() {
+    let run = |args: &[&str], env: &[(&str, &str)]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+            .args(args)
+            .envs(env.iter().copied())
+            .output()
+            .expect("Failed to execute command");
+        assert!(output.status.success(), "Command failed: {:?}", output);
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    // RPG_LENGTH stands in for --length
+    let stdout = run(&["3", "--quiet"], &[("RPG_LENGTH", "24")]);
+    assert_eq!(stdout.lines().count(), 3);
+    assert!(stdout.lines().all(|line| line.len() == 24), "{stdout}");
+
+    // The command line wins over the environment
+    let stdout = run(&["1", "--quiet", "--length", "8"], &[("RPG_LENGTH", "24")]);
+    assert_eq!(stdout.trim().len(), 8, "{stdout}");
+    let stdout = run(&["2", "--quiet"], &[("RPG_COUNT", "5")]);
+    assert_eq!(stdout.lines().count(), 2, "{stdout}");
+
+    // RPG_COUNT replaces the required count, and flags take true or false
+    let stdout = run(
+        &["--quiet", "--length", "40"],
+        &[("RPG_COUNT", "4"), ("RPG_SYMBOLS_OFF", "true")],
+    );
+    assert_eq!(stdout.lines().count(), 4);
+    assert!(
+        stdout
+            .lines()
+            .all(|line| line.chars().all(|c| c.is_ascii_alphanumeric())),
+        "{stdout}"
+    );
+    let stdout = run(
+        &["4", "--quiet", "--symbols-off"],
+        &[("RPG_SYMBOLS_OFF", "0")],
+    );
+    assert!(
+        stdout
+            .lines()
+            .all(|line| line.chars().all(|c| c.is_ascii_alphanumeric()))
+    );
+
+    // An environment setting that contradicts the command line gives way
+    let stdout = run(
+        &["1", "--quiet", "--symbols", "!@", "--length", "30"],
+        &[("RPG_SYMBOLS_OFF", "1")],
+    );
+    assert_eq!(stdout.trim().len(), 30, "{stdout}");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+        .args(["1"])
+        .env("RPG_LENGTH", "long")
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid length 'long'"));
+}
+
+#[test]
+fn test_cli_benchmark() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+        .args(["--benchmark", "--length", "12"])
+        .output()
+        .expect("Failed to execute command");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{stderr}");
+    assert!(output.stdout.is_empty());
+    assert!(
+        stderr.contains("Benchmark: 100000 passwords of 12 characters"),
+        "{stderr}"
+    );
+    let rate: f64 = stderr
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("Rate:"))
+        .and_then(|rest| rest.trim().strip_suffix(" passwords/s"))
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(rate > 0.0);
+    assert!(stderr.contains("MB/s"), "{stderr}");
+}
+
+#[test]
+fn test_cli_exclude_password_file() {
+    // Forbid every possible single-character password
+    let path = std::env::temp_dir().join(format!("rpg-forbidden-{}.txt", std::process::id()));
+    std::fs::write(&path, "a\nb\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+        .args([
+            "1",
+            "--include-chars",
+            "a,b",
+            "--length",
+            "1",
+            "--exclude-password-file",
+        ])
+        .arg(&path)
+        .output()
+        .expect("Failed to execute command");
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(
+        !output.status.success(),
+        "Should fail when all outputs are forbidden"
+    );
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("exclude-password-file"));
+}
+
+#[test]
+fn test_cli_unique() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+        .args([
+            "16",
+            "--include-chars",
+            "a,b",
+            "--length",
+            "4",
+            "--unique",
+            "--quiet",
+        ])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let passwords: Vec<&str> = stdout.lines().collect();
+    let distinct: std::collections::HashSet<&str> = passwords.iter().copied().collect();
+    assert_eq!(passwords.len(), 16);
+    assert_eq!(distinct.len(), 16);
+
+    // Every 4-digit PIN
+    let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+        .args([
+            "10000",
+            "--include-chars",
+            "0-9",
+            "--length",
+            "4",
+            "--unique",
+            "--quiet",
+        ])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let distinct: std::collections::HashSet<&str> = stdout.lines().collect();
+    assert_eq!(distinct.len(), 10_000);
+
+    // 17 unique passwords can't be drawn from a 16-password keyspace
+    let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+        .args(["17", "--include-chars", "a,b", "--length", "4", "--unique"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Only 16 distinct passwords are possible"));
+}
+
+#[test]
+fn test_cli_unique_retry_limit() {
+    let args = [
+        "16",
+        "--include-chars",
+        "a,b",
+        "--length",
+        "4",
+        "--unique",
+        "--seed",
+        "379",
+    ];
+
+    // Drawing the whole keyspace collides long before the 16th password,
+    // and an explicit limit is not scaled up
+    let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+        .args(args)
+        .args(["--retry-limit", "1"])
+        .output()
+        .expect("Failed to execute command");
+    assert_eq!(output.status.code(), Some(16));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Could not satisfy the unique constraint after 1 attempts"));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+        .args(args)
+        .args(["--retry-limit", "100000", "--quiet"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let distinct: std::collections::HashSet<&str> = stdout.lines().collect();
+    assert_eq!(distinct.len(), 16);
+}
+
+#[test]
+fn test_cli_require_mixed_case() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+        .args(["50", "--length", "4", "--require-mixed-case", "--quiet"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    for line in stdout.lines() {
+        assert!(line.chars().any(|c| c.is_ascii_lowercase()), "{}", line);
+        assert!(line.chars().any(|c| c.is_ascii_uppercase()), "{}", line);
+    }
+
+    // Contradicts --capitals-off
+    let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+        .args(["1", "--require-mixed-case", "--capitals-off"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Mixed case is required"));
+}
+
+#[test]
+fn test_cli_first_char_alpha() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+        .args(["100", "--first-char-alpha", "--quiet"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 100);
+    for line in stdout.lines() {
+        assert!(line.as_bytes()[0].is_ascii_alphabetic(), "{}", line);
+    }
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+        .args(["1", "--first-char-alpha", "--include-chars", "0-9"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_cli_error_exit_codes() {
+    // Library errors exit with their documented status
+    let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+        .args(["1", "--length", "0"])
+        .output()
+        .expect("Failed to execute rpg");
+    assert_eq!(output.status.code(), Some(10));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+        .args(["1", "--include-chars", "a", "--exclude-chars", "a"])
+        .output()
+        .expect("Failed to execute rpg");
+    assert_eq!(output.status.code(), Some(14));
+
+    // Anything else exits with 1
+    let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+        .args(["1", "--pattern", "LLX"])
+        .output()
+        .expect("Failed to execute rpg");
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn test_cli_jsonl_output() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+        .args(["25", "--format", "jsonl", "--length", "10"])
+        .output()
+        .expect("Failed to execute rpg");
+    assert!(output.status.success(), "Command failed: {:?}", output);
+
+    // Every line stands alone as a JSON object
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 25);
+    for line in stdout.lines() {
+        let value: serde_json::Value = serde_json::from_str(line).unwrap();
+        assert_eq!(value["password"].as_str().unwrap().len(), 10);
+        assert!(value["entropy_bits"].as_f64().unwrap() > 60.0);
+    }
+}
+
+#[test]
+fn test_cli_jsonl_check() {
+    // Scores the given password on one line rather than streaming new ones
+    let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+        .args(["--check", "hunter2", "--format", "jsonl"])
+        .output()
+        .expect("Failed to execute rpg");
+    assert!(output.status.success(), "Command failed: {:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 1);
+    let value: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(value["length"], 7);
+    assert!(value.get("password").is_none());
+}
+
+#[test]
+fn test_cli_jsonl_sample() {
+    let run = |format: &str| {
+        let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+            .args(["5", "--sample", "2", "--seed", "1", "--format", format])
+            .output()
+            .expect("Failed to execute rpg");
+        assert!(output.status.success(), "Command failed: {:?}", output);
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    // The same 2 of the 5 that the text format prints
+    let passwords: Vec<String> = run("jsonl")
+        .lines()
+        .map(|line| {
+            let value: serde_json::Value = serde_json::from_str(line).unwrap();
+            value["password"].as_str().unwrap().to_string()
+        })
+        .collect();
+    assert_eq!(passwords, run("text").lines().collect::<Vec<_>>());
+    assert_eq!(passwords.len(), 2);
+}
+
+// Without the feature the store fails, so no test entry is left in a real keyring
+#[cfg(not(feature = "keyring"))]
+#[test]
+fn test_cli_jsonl_keyring_prints_nothing() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+        .args(["1", "--keyring", "rpg-test", "--format", "jsonl"])
+        .output()
+        .expect("Failed to execute rpg");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("--keyring is not available"), "{stderr}");
+}
+
+#[test]
+fn test_cli_subcommands_jsonl() {
+    // Reports print as one JSON line; nothing is generated
+    for args in [
+        &["check", "hunter2", "--format", "jsonl"][..],
+        &["estimate", "--length", "12", "--format", "jsonl"],
+        &["--estimate", "--length", "12", "--format", "jsonl"],
+    ] {
+        let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+            .args(args)
+            .output()
+            .expect("Failed to execute rpg");
+        assert!(output.status.success(), "Command failed: {:?}", output);
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert_eq!(stdout.lines().count(), 1, "{args:?}");
+        let value: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+        assert!(value.get("password").is_none(), "{args:?}");
+        assert!(value["strength"].is_string(), "{args:?}");
+    }
+}
+
+#[test]
+fn test_cli_jsonl_seeds() {
+    let run = || {
+        let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+            .args(["2", "--seeds", "1,2", "--format", "jsonl"])
+            .output()
+            .expect("Failed to execute rpg");
+        assert!(output.status.success(), "Command failed: {:?}", output);
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    // Each batch comes from its own seed, so the output is reproducible
+    let first = run();
+    assert_eq!(first, run());
+    let seeds: Vec<u64> = first
+        .lines()
+        .map(|line| {
+            let value: serde_json::Value = serde_json::from_str(line).unwrap();
+            value["seed"].as_u64().unwrap()
+        })
+        .collect();
+    assert_eq!(seeds, [1, 1, 2, 2]);
+}
+
+#[test]
+fn test_cli_warns_when_length_is_ignored() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+        .args(["1", "--pattern", "LLNN", "--length", "20"])
+        .output()
+        .expect("Failed to execute rpg");
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("--length 20 is ignored"), "{}", stderr);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+        .args(["1", "--pattern", "LLNN", "--length", "20", "--quiet"])
+        .output()
+        .expect("Failed to execute rpg");
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn test_cli_color_needs_a_terminal() {
+    // Captured stdout is a pipe, so --color prints plain text
+    let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+        .args(["3", "--color"])
+        .output()
+        .expect("Failed to execute rpg");
+    assert!(output.status.success());
+    assert!(!output.stdout.contains(&0x1b));
+}
+
+#[test]
+fn test_cli_no_color_strips_escapes() {
+    for args in [
+        &["--help"][..],
+        &["3", "--color", "--table"],
+        &["1", "--nope"],
+    ] {
+        let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+            .args(args)
+            .env("NO_COLOR", "1")
+            .output()
+            .expect("Failed to execute rpg");
+        assert!(!output.stdout.contains(&0x1b), "{:?}", args);
+        assert!(!output.stderr.contains(&0x1b), "{:?}", args);
+    }
+}
+
+/// Reads a little of a large batch, then closes stdout the way `head` does
+fn run_with_early_close(args: &[&str]) -> std::process::Output {
+    use std::io::Read;
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rpg"))
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn command");
+    let mut stdout = child.stdout.take().unwrap();
+    let mut head = [0u8; 64];
+    stdout.read_exact(&mut head).unwrap();
+    drop(stdout);
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn test_cli_stream_until_reader_closes() {
+    use std::io::{BufRead, BufReader};
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rpg"))
+        .args(["--stream", "--length", "12"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn command");
+    let lines: Vec<String> = BufReader::new(child.stdout.take().unwrap())
+        .lines()
+        .take(2000)
+        .map(|line| line.unwrap())
+        .collect();
+    // Dropping the reader closes the pipe, which must end the stream cleanly
+    assert_eq!(lines.len(), 2000);
+    assert!(lines.iter().all(|line| line.chars().count() == 12));
+    let output = child.wait_with_output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{stderr}");
+    assert!(stderr.is_empty(), "{stderr}");
+}
+
+#[test]
+fn test_cli_broken_pipe_exits_cleanly() {
+    for args in [
+        &["200000", "--quiet"][..],
+        &["200000", "--quiet", "--format", "jsonl"][..],
+    ] {
+        let output = run_with_early_close(args);
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(output.status.success(), "{args:?}: {stderr}");
+        assert!(stderr.is_empty(), "{args:?}: {stderr}");
+    }
+}
+
+#[cfg(feature = "hash")]
+#[test]
+fn test_cli_account_seed_phrase_sources() {
+    let path = std::env::temp_dir().join(format!("rpg-cli-phrase-{}.txt", std::process::id()));
+    std::fs::write(&path, "master\n").unwrap();
+    let run = |args: &[&str], env: &[(&str, &str)]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_rpg"))
+            .args(["2", "--quiet", "--account", "example.com"])
+            .args(args)
+            .env_remove("RPG_SEED_PHRASE")
+            .envs(env.iter().copied())
+            .output()
+            .expect("Failed to execute command");
+        assert!(output.status.success(), "Command failed: {:?}", output);
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    // The environment and a file give the same secret, and the file wins
+    let from_env = run(&[], &[("RPG_SEED_PHRASE", "master")]);
+    let from_file = run(&["--seed-phrase-file", path.to_str().unwrap()], &[]);
+    assert_eq!(from_env, from_file);
+    let both = run(
+        &["--seed-phrase-file", path.to_str().unwrap()],
+        &[("RPG_SEED_PHRASE", "other")],
+    );
+    assert_eq!(both, from_file);
+    assert_ne!(run(&[], &[("RPG_SEED_PHRASE", "other")]), from_env);
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,36 @@
+//! Browser binding tests, run with `wasm-pack test --headless --firefox --features wasm`
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
+
+use rpg_util::wasm::generate;
+use serde::Serialize;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+fn strings(value: JsValue) -> Vec<String> {
+    serde_wasm_bindgen::from_value(value).unwrap()
+}
+
+#[wasm_bindgen_test]
+fn test_generate_defaults() {
+    let passwords = strings(generate(16, 3, JsValue::UNDEFINED).unwrap());
+    assert_eq!(passwords.len(), 3);
+    assert!(passwords.iter().all(|p| p.len() == 16));
+}
+
+#[wasm_bindgen_test]
+fn test_generate_seeded_options() {
+    let opts = serde_json::json!({"symbolsOff": true, "pattern": "LLNN", "seed": 42})
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .unwrap();
+    let first = strings(generate(16, 2, opts.clone()).unwrap());
+    let second = strings(generate(16, 2, opts).unwrap());
+    assert_eq!(first, second);
+    for pass in &first {
+        let bytes = pass.as_bytes();
+        assert_eq!(bytes.len(), 4);
+        assert!(bytes[..2].iter().all(u8::is_ascii_lowercase));
+        assert!(bytes[2..].iter().all(u8::is_ascii_digit));
+    }
+}